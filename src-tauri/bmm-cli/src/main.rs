@@ -0,0 +1,1177 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use clap::{Parser, Subcommand};
+
+use bmm_lib::archive_store;
+use bmm_lib::cache::{self, Mod};
+use bmm_lib::catalog::CatalogStore;
+use bmm_lib::database::{Database, InstalledMod};
+use bmm_lib::download;
+use bmm_lib::errors::AppError;
+use bmm_lib::finder::get_lovely_mods_dir;
+use bmm_lib::github_api;
+use bmm_lib::index::{self, IndexSource};
+use bmm_lib::installer;
+use bmm_lib::launcher;
+use bmm_lib::lockfile::{self, Lockfile, SyncReport, LOCKFILE_NAME};
+use bmm_lib::manager::ModManager;
+use bmm_lib::mod_collections::{self, CollectionFormat, ModCollection};
+use bmm_lib::profiles::{self, ProfileReport};
+use bmm_lib::releases;
+use bmm_lib::resolver;
+use bmm_lib::search::{SearchIndex, SearchQuery};
+use bmm_lib::updater;
+
+const MIB: u64 = 1024 * 1024;
+
+#[derive(Parser)]
+#[command(name = "bmm", version, about = "Headless Balatro Mod Manager")]
+struct Cli {
+    #[command(subcommand)]
+    command: Commands,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Install a mod from the index by title or folder name, or from a direct archive URL
+    Install {
+        name: String,
+        /// Install a specific release (Steamodded and Talisman only)
+        #[arg(long)]
+        version: Option<String>,
+        /// Folder to install into when installing from a URL
+        #[arg(long)]
+        folder_name: Option<String>,
+        /// Expected SHA-256 of the archive when installing from a URL
+        #[arg(long)]
+        sha256: Option<String>,
+    },
+    /// Remove an installed mod
+    Uninstall {
+        name: String,
+        /// Also remove every mod that depends on it
+        #[arg(long)]
+        cascade: bool,
+    },
+    /// List installed mods, or every mod in the index with --available
+    List {
+        #[arg(long)]
+        available: bool,
+    },
+    /// Show a mod's index entry, including its changelog
+    Info { name: String },
+    /// Search the index by title, description and publisher, tolerating typos
+    Search {
+        #[arg(default_value = "")]
+        text: String,
+        /// Only mods in this category; repeat for several
+        #[arg(long = "category")]
+        categories: Vec<String>,
+        /// Only mods that need Steamodded
+        #[arg(long)]
+        steamodded: bool,
+        /// Only mods that need Talisman
+        #[arg(long)]
+        talisman: bool,
+        /// Only installed mods
+        #[arg(long, conflicts_with = "not_installed")]
+        installed: bool,
+        /// Only mods that aren't installed
+        #[arg(long)]
+        not_installed: bool,
+        /// relevance, name_asc, name_desc, updated_asc, updated_desc or installed
+        #[arg(long, default_value = "relevance")]
+        sort: String,
+        #[arg(long)]
+        limit: Option<usize>,
+    },
+    /// Enable an installed mod
+    Enable { name: String },
+    /// Disable an installed mod without removing it
+    Disable { name: String },
+    /// Reinstall a mod if the index has a newer version
+    Update {
+        #[arg(required_unless_present = "all")]
+        name: Option<String>,
+        /// Update every installed mod that has a newer version
+        #[arg(long, conflicts_with = "name")]
+        all: bool,
+    },
+    /// Keep a mod on its current version when updating
+    Pin { name: String },
+    /// Let a pinned mod be updated again
+    Unpin { name: String },
+    /// Reinstall the version a mod had before its last update
+    Rollback { name: String },
+    /// Check an installed mod's files against what was installed
+    Verify {
+        name: String,
+        /// Re-extract the mod from its cached archive if anything changed
+        #[arg(long)]
+        repair: bool,
+    },
+    /// Write a lockfile with the exact archive behind every installed mod
+    Lock {
+        #[arg(long, default_value = LOCKFILE_NAME)]
+        output: PathBuf,
+    },
+    /// Make the mods folder match a lockfile exactly
+    Sync {
+        #[arg(default_value = LOCKFILE_NAME)]
+        path: PathBuf,
+    },
+    /// Show or change settings; values are JSON, bare words are taken as strings
+    Config {
+        key: Option<String>,
+        value: Option<String>,
+    },
+    /// Start Balatro with lovely injected
+    Launch,
+    /// Manage the local copy of the mod index
+    Index {
+        #[command(subcommand)]
+        command: IndexCommands,
+    },
+    /// Manage cached mod archives
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommands,
+    },
+    /// Manage named sets of enabled mods
+    Profile {
+        #[command(subcommand)]
+        command: ProfileCommands,
+    },
+    /// Share exact mod setups as collection files
+    Collection {
+        #[command(subcommand)]
+        command: CollectionCommands,
+    },
+}
+
+#[derive(Subcommand)]
+enum IndexCommands {
+    /// Download or refresh every configured mod index
+    Pull,
+    /// List the configured index sources, highest priority first
+    Sources,
+    /// Look up GitHub releases for mods that take their version from them
+    Releases {
+        /// Also check mods that were checked recently
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum ProfileCommands {
+    /// List saved profiles
+    List,
+    /// Show the mods in a profile
+    Show { name: String },
+    /// Save the current mods and their enabled state as a profile
+    Save { name: String },
+    /// Delete a profile
+    Delete { name: String },
+    /// Enable exactly the mods in a profile, installing any that are missing
+    Activate { name: String },
+}
+
+#[derive(Subcommand)]
+enum CollectionCommands {
+    /// List saved collections
+    List,
+    /// Show the mods in a collection
+    Show { id: String },
+    /// Save the installed mods with their exact versions as a collection
+    Create {
+        name: String,
+        #[arg(long)]
+        description: Option<String>,
+    },
+    /// Delete a collection
+    Delete { id: String },
+    /// Write a collection to a .json or .toml file
+    Export { id: String, path: PathBuf },
+    /// Read a collection from a .json or .toml file
+    Import {
+        path: PathBuf,
+        /// Install its mods right away
+        #[arg(long)]
+        install: bool,
+    },
+    /// Install every mod in a collection at its recorded version
+    Install { id: String },
+}
+
+#[derive(Subcommand)]
+enum CacheCommands {
+    /// List cached archives, most recently used first
+    List,
+    /// Evict least recently used archives not backing an installed mod
+    Prune {
+        /// Size to shrink the cache to
+        #[arg(long, default_value_t = archive_store::DEFAULT_MAX_BYTES / MIB)]
+        max_size_mb: u64,
+    },
+}
+
+struct Context {
+    manager: ModManager,
+    installation_path: Option<String>,
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn")).init();
+
+    let cli = Cli::parse();
+    match run(cli.command).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(command: Commands) -> Result<(), AppError> {
+    let db = Database::new()?;
+    let settings = db.settings()?;
+    github_api::shared().set_token(settings.github_token);
+    if let Err(e) = download::shared().set_proxy(settings.download_proxy) {
+        log::warn!("Ignoring download proxy: {}", e);
+    }
+    let installation_path = settings.installation_path;
+    let mods_dir = get_lovely_mods_dir(installation_path.as_ref());
+    let ctx = Context {
+        manager: ModManager::new(db, mods_dir),
+        installation_path,
+    };
+
+    match command {
+        Commands::Install {
+            name,
+            version,
+            folder_name,
+            sha256,
+        } => install(&ctx, &name, version, folder_name, sha256).await,
+        Commands::Uninstall { name, cascade } => uninstall(&ctx, &name, cascade),
+        Commands::List { available } => {
+            if available {
+                list_available(&ctx).await
+            } else {
+                list_installed(&ctx)
+            }
+        }
+        Commands::Info { name } => info(&ctx, &name).await,
+        Commands::Search {
+            text,
+            categories,
+            steamodded,
+            talisman,
+            installed,
+            not_installed,
+            sort,
+            limit,
+        } => {
+            let installed = match (installed, not_installed) {
+                (true, _) => Some(true),
+                (_, true) => Some(false),
+                _ => None,
+            };
+            search_catalog(
+                &ctx,
+                SearchQuery {
+                    text,
+                    requires_steamodded: steamodded.then_some(true),
+                    requires_talisman: talisman.then_some(true),
+                    installed,
+                    limit,
+                    ..Default::default()
+                },
+                &categories,
+                &sort,
+            )
+            .await
+        }
+        Commands::Enable { name } => set_enabled(&ctx, &name, true),
+        Commands::Disable { name } => set_enabled(&ctx, &name, false),
+        Commands::Update {
+            name: Some(name), ..
+        } => update(&ctx, &name).await,
+        Commands::Update { name: None, .. } => update_all(&ctx).await,
+        Commands::Pin { name } => set_pinned(&ctx, &name, true),
+        Commands::Unpin { name } => set_pinned(&ctx, &name, false),
+        Commands::Rollback { name } => rollback(&ctx, &name).await,
+        Commands::Verify { name, repair } => verify(&ctx, &name, repair),
+        Commands::Lock { output } => lock(&ctx, &output).await,
+        Commands::Sync { path } => sync(&ctx, &path).await,
+        Commands::Config { key, value } => config(&ctx, key, value),
+        Commands::Launch => launch(&ctx).await,
+        Commands::Index {
+            command: IndexCommands::Pull,
+        } => pull_index(&ctx).await,
+        Commands::Index {
+            command: IndexCommands::Sources,
+        } => list_index_sources(&ctx),
+        Commands::Index {
+            command: IndexCommands::Releases { force },
+        } => check_releases(&ctx, force).await,
+        Commands::Profile { command } => profile(&ctx, command).await,
+        Commands::Collection { command } => collection(&ctx, command).await,
+        Commands::Cache {
+            command: CacheCommands::List,
+        } => list_archives(&ctx),
+        Commands::Cache {
+            command: CacheCommands::Prune { max_size_mb },
+        } => prune_archives(&ctx, max_size_mb),
+    }
+}
+
+async fn install(
+    ctx: &Context,
+    name: &str,
+    version: Option<String>,
+    folder_name: Option<String>,
+    sha256: Option<String>,
+) -> Result<(), AppError> {
+    if name.starts_with("https://") || name.starts_with("http://") {
+        let archive = installer::install_mod(
+            ctx.installation_path.as_ref(),
+            name.to_string(),
+            folder_name,
+            sha256.as_deref(),
+        )
+        .await?;
+        let mod_name = archive
+            .path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .ok_or(AppError::PathConversionError)?
+            .to_string();
+        ctx.manager
+            .record_install(&mod_name, &archive.path, &[], None, Some(&archive))?;
+        println!("Installed {} to {}", mod_name, archive.path.display());
+        return Ok(());
+    }
+
+    let catalog = load_catalog(ctx).await?;
+    let requested = find_in_catalog(&catalog, name)?;
+
+    // Settle dependencies and conflicts before anything is downloaded
+    let installed = resolver::installed_packages(ctx.manager.db(), &catalog)?;
+    let plan = resolver::plan_install(&[requested.title.as_str()], &installed, &catalog)?;
+    if !plan.is_ok() {
+        return Err(AppError::InvalidState(format!(
+            "Cannot install {}:\n{}",
+            requested.title,
+            plan.explain()
+        )));
+    }
+
+    for planned in &plan.install {
+        let catalog_mod = find_in_catalog(&catalog, &planned.name)?;
+        if catalog_mod.title == requested.title {
+            install_catalog_mod(ctx, catalog_mod, version.clone()).await?;
+        } else {
+            println!("Installing {} ({})", planned.name, planned.reason);
+            install_catalog_mod(ctx, catalog_mod, None).await?;
+        }
+    }
+    Ok(())
+}
+
+async fn install_catalog_mod(
+    ctx: &Context,
+    catalog_mod: &Mod,
+    version: Option<String>,
+) -> Result<(), AppError> {
+    let dependencies = installer::catalog_dependencies(catalog_mod);
+    for dependency in &dependencies {
+        if ctx.manager.db().get_mod_details(dependency).is_err() {
+            eprintln!(
+                "warning: {} requires {}, which is not installed",
+                catalog_mod.title, dependency
+            );
+        }
+    }
+
+    let (archive, installed_version) = installer::install_from_catalog(
+        ctx.installation_path.as_ref(),
+        catalog_mod,
+        version.as_deref(),
+    )
+    .await?;
+
+    ctx.manager.record_install(
+        &catalog_mod.title,
+        &archive.path,
+        &dependencies,
+        installed_version,
+        Some(&archive),
+    )?;
+    println!(
+        "Installed {} to {}",
+        catalog_mod.title,
+        archive.path.display()
+    );
+    Ok(())
+}
+
+fn uninstall(ctx: &Context, name: &str, cascade: bool) -> Result<(), AppError> {
+    if cascade {
+        for removed in ctx.manager.cascade_uninstall(name)? {
+            println!("Uninstalled {}", removed);
+        }
+        return Ok(());
+    }
+
+    let dependents = ctx.manager.get_dependents(name)?;
+    if !dependents.is_empty() {
+        return Err(AppError::InvalidState(format!(
+            "{} is required by: {} (pass --cascade to remove them too)",
+            name,
+            dependents.join(", ")
+        )));
+    }
+
+    let mod_details = ctx.manager.db().get_mod_details(name)?;
+    ctx.manager
+        .force_remove_mod(name, Path::new(&mod_details.path))?;
+    println!("Uninstalled {}", name);
+    Ok(())
+}
+
+fn list_installed(ctx: &Context) -> Result<(), AppError> {
+    let installed_mods = ctx.manager.db().get_installed_mods()?;
+    if installed_mods.is_empty() {
+        println!("No mods installed");
+        return Ok(());
+    }
+
+    for installed in installed_mods {
+        let state = match installer::is_mod_enabled(Path::new(&installed.path)) {
+            Ok(true) => "enabled",
+            Ok(false) => "disabled",
+            Err(_) => "missing",
+        };
+        let state = if installed.pinned {
+            format!("{}, pinned", state)
+        } else {
+            state.to_string()
+        };
+        println!(
+            "{}\t{}\t{}\t{}",
+            installed.name,
+            installed
+                .current_version
+                .filter(|v| !v.is_empty())
+                .unwrap_or_else(|| "-".to_string()),
+            state,
+            installed.path
+        );
+    }
+    Ok(())
+}
+
+async fn list_available(ctx: &Context) -> Result<(), AppError> {
+    let catalog = load_catalog(ctx).await?;
+    let installed_mods = ctx.manager.db().get_installed_mods()?;
+
+    for catalog_mod in &catalog {
+        let installed = installed_mods.iter().any(|m| m.name == catalog_mod.title);
+        println!(
+            "{}\t{}\t{}\t{}",
+            catalog_mod.title,
+            catalog_mod.version.as_deref().unwrap_or("-"),
+            catalog_mod.publisher,
+            if installed { "installed" } else { "" }
+        );
+    }
+    Ok(())
+}
+
+async fn info(ctx: &Context, name: &str) -> Result<(), AppError> {
+    let catalog = load_catalog(ctx).await?;
+    let entry = find_in_catalog(&catalog, name)?;
+    let details = &entry.details;
+
+    println!("{}", entry.title);
+    println!("  publisher: {}", entry.publisher);
+    println!("  version:   {}", entry.version.as_deref().unwrap_or("-"));
+    if let Some(source) = &entry.source {
+        println!("  source:    {}", source);
+    }
+    if !entry.repo.is_empty() {
+        println!("  repo:      {}", entry.repo);
+    }
+    if let Some(updated) = details.last_updated {
+        println!("  updated:   {}", format_age(updated));
+    }
+    if let Some(size) = details.archive_size {
+        println!("  size:      {:.1} MiB", size as f64 / MIB as f64);
+    }
+    if let Some(smods) = &details.min_steamodded_version {
+        println!("  needs:     Steamodded {} or newer", smods);
+    }
+    if !details.game_versions.is_empty() {
+        println!("  game:      {}", details.game_versions.join(", "));
+    }
+    for screenshot in &details.screenshots {
+        println!("  screenshot: {}", screenshot);
+    }
+    if let Some(changelog) = &details.changelog {
+        println!();
+        println!("{}", changelog.trim_end());
+    }
+    Ok(())
+}
+
+async fn search_catalog(
+    ctx: &Context,
+    mut query: SearchQuery,
+    categories: &[String],
+    sort: &str,
+) -> Result<(), AppError> {
+    for name in categories {
+        let category = index::parse_category(name)
+            .ok_or_else(|| AppError::InvalidState(format!("Unknown category '{}'", name)))?;
+        query.categories.push(category);
+    }
+    query.sort = serde_json::from_value(serde_json::Value::String(sort.to_string()))
+        .map_err(|_| AppError::InvalidState(format!("Unknown sort order '{}'", sort)))?;
+
+    // Refreshes the stored catalog the index is built from
+    load_catalog(ctx).await?;
+    let search_index = SearchIndex::load(&CatalogStore::open()?, ctx.manager.db())?;
+    let results = search_index.search(&query);
+
+    for found in &results.mods {
+        println!(
+            "{}\t{}\t{}\t{}",
+            found.title,
+            found.version.as_deref().unwrap_or("-"),
+            found.publisher,
+            if found.installed { "installed" } else { "" }
+        );
+    }
+    if results.mods.len() < results.total {
+        println!("({} of {} matches)", results.mods.len(), results.total);
+    }
+    Ok(())
+}
+
+fn set_enabled(ctx: &Context, name: &str, enabled: bool) -> Result<(), AppError> {
+    ctx.manager.set_mod_enabled(name, enabled)?;
+    println!("{} {}", if enabled { "Enabled" } else { "Disabled" }, name);
+    Ok(())
+}
+
+async fn update(ctx: &Context, name: &str) -> Result<(), AppError> {
+    let installed = ctx.manager.db().get_mod_details(name)?;
+    let catalog = load_catalog(ctx).await?;
+    run_updates(ctx, vec![installed], &catalog).await
+}
+
+async fn update_all(ctx: &Context) -> Result<(), AppError> {
+    let catalog = load_catalog(ctx).await?;
+    let installed_mods = ctx.manager.db().get_installed_mods()?;
+    run_updates(ctx, installed_mods, &catalog).await
+}
+
+// Single and bulk updates both go through the updater, so they pick versions,
+// keep disabled mods disabled and record installs the same way
+async fn run_updates(
+    ctx: &Context,
+    installed_mods: Vec<InstalledMod>,
+    catalog: &[Mod],
+) -> Result<(), AppError> {
+    let report = updater::update_all(
+        ctx.installation_path.as_ref(),
+        installed_mods,
+        catalog,
+        |installed, archive, version| {
+            ctx.manager.record_install(
+                &installed.name,
+                &archive.path,
+                &installed.dependencies,
+                Some(version.to_string()),
+                Some(archive),
+            )
+        },
+    )
+    .await;
+
+    for updated in &report.updated {
+        println!(
+            "Updated {} from {} to {} ({:?} update)",
+            updated.name, updated.from_version, updated.to_version, updated.kind
+        );
+    }
+    for skipped in &report.skipped {
+        println!("Skipped {}: {}", skipped.name, skipped.reason);
+    }
+    for failed in &report.failed {
+        eprintln!("Failed to update {}: {}", failed.name, failed.error);
+    }
+
+    if report.failed.is_empty() {
+        Ok(())
+    } else {
+        Err(AppError::InvalidState(format!(
+            "{} updates failed",
+            report.failed.len()
+        )))
+    }
+}
+
+fn set_pinned(ctx: &Context, name: &str, pinned: bool) -> Result<(), AppError> {
+    ctx.manager.db().set_mod_pinned(name, pinned)?;
+    println!("{} {}", if pinned { "Pinned" } else { "Unpinned" }, name);
+    Ok(())
+}
+
+async fn rollback(ctx: &Context, name: &str) -> Result<(), AppError> {
+    let target = ctx.manager.rollback_target(name)?;
+    let (data, _) = installer::fetch_recorded_archive(
+        target.download_url.as_deref(),
+        target.archive_sha256.as_deref(),
+    )
+    .await?;
+    ctx.manager.rollback_mod(name, &target, data)?;
+    println!(
+        "Rolled {} back to {}",
+        name,
+        target
+            .version
+            .as_deref()
+            .filter(|v| !v.is_empty())
+            .unwrap_or("its previous version")
+    );
+    Ok(())
+}
+
+fn verify(ctx: &Context, name: &str, repair: bool) -> Result<(), AppError> {
+    let mut report = ctx.manager.verify_mod(name)?;
+    if report.is_clean() {
+        println!("{} is intact", name);
+        return Ok(());
+    }
+
+    for path in &report.modified {
+        println!("modified\t{}", path);
+    }
+    for path in &report.missing {
+        println!("missing\t{}", path);
+    }
+    for path in &report.extra {
+        println!("extra\t{}", path);
+    }
+
+    if repair {
+        report = ctx.manager.repair_mod(name)?;
+        if report.is_clean() {
+            println!("Repaired {}", name);
+            return Ok(());
+        }
+    }
+
+    Err(AppError::InvalidState(format!(
+        "{} does not match its installed files",
+        name
+    )))
+}
+
+fn config(ctx: &Context, key: Option<String>, value: Option<String>) -> Result<(), AppError> {
+    let db = ctx.manager.db();
+    match (key, value) {
+        (None, _) => {
+            for (key, value) in db.settings()?.to_display()? {
+                println!("{} = {}", key, value);
+            }
+        }
+        (Some(key), None) => println!("{}", db.settings()?.get_display(&key)?),
+        (Some(key), Some(value)) => {
+            let value = serde_json::from_str(&value).unwrap_or(serde_json::Value::String(value));
+            let settings = db.update_settings(|s| s.set(&key, value))?;
+            println!("{} = {}", key, settings.get_display(&key)?);
+        }
+    }
+    Ok(())
+}
+
+async fn launch(ctx: &Context) -> Result<(), AppError> {
+    let path = ctx
+        .installation_path
+        .as_ref()
+        .ok_or_else(|| AppError::InvalidState("No installation path set".to_string()))?;
+    launcher::launch_balatro(Path::new(path), ctx.manager.db().settings()?.lovely_console).await
+}
+
+async fn pull_index(ctx: &Context) -> Result<(), AppError> {
+    let sources = ctx.manager.db().settings()?.index_sources;
+    let mut failed = 0;
+    for (name, result) in index::sync_sources(&sources).await {
+        match result {
+            Ok(summary) if summary.up_to_date => {
+                println!("{}: already up to date", name);
+            }
+            Ok(summary) => println!(
+                "{}: {} added, {} changed, {} removed",
+                name, summary.added, summary.changed, summary.removed
+            ),
+            Err(e) => {
+                eprintln!("{}: {}", name, e);
+                failed += 1;
+            }
+        }
+    }
+    if failed == sources.len() {
+        return Err(AppError::GitOperation(
+            "No index source could be synced".to_string(),
+        ));
+    }
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    ctx.manager.db().update_settings(|s| {
+        s.last_fetched = now;
+        Ok(())
+    })?;
+    Ok(())
+}
+
+async fn check_releases(ctx: &Context, force: bool) -> Result<(), AppError> {
+    // Makes sure the store has the current index to check against
+    load_catalog(ctx).await?;
+    let summary = releases::check_releases(&CatalogStore::open()?, force).await?;
+    println!(
+        "Checked {} repositories: {} new releases, {} unchanged, {} failed, {} checked recently",
+        summary.checked, summary.updated, summary.not_modified, summary.failed, summary.skipped
+    );
+    if let Some(reset) = summary.rate_limited_until {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        println!(
+            "GitHub's rate limit was reached; try again in {} minutes",
+            reset.saturating_sub(now).div_ceil(60)
+        );
+        if !github_api::shared().has_token() {
+            println!("Set GITHUB_TOKEN or the github_token setting for a higher limit");
+        }
+    } else if let Some(limit) = github_api::shared().rate_limit() {
+        println!(
+            "{} of {} GitHub requests left",
+            limit.remaining, limit.limit
+        );
+    }
+    Ok(())
+}
+
+fn list_index_sources(ctx: &Context) -> Result<(), AppError> {
+    for source in ctx.manager.db().settings()?.index_sources {
+        let location = match &source {
+            IndexSource::GitHub {
+                url,
+                branch: Some(branch),
+                ..
+            } => format!("{}@{}", url, branch),
+            IndexSource::GitHub { url, .. } | IndexSource::Zip { url, .. } => url.clone(),
+            IndexSource::Local { path, .. } => path.display().to_string(),
+        };
+        println!(
+            "{}\t{}\t{}",
+            source.name(),
+            location,
+            source.local_dir()?.display()
+        );
+    }
+    Ok(())
+}
+
+async fn profile(ctx: &Context, command: ProfileCommands) -> Result<(), AppError> {
+    let db = ctx.manager.db();
+    match command {
+        ProfileCommands::List => {
+            let active = db.settings()?.active_profile;
+            for name in db.list_profiles()? {
+                let marker = if active.as_ref() == Some(&name) {
+                    "*"
+                } else {
+                    " "
+                };
+                println!("{} {}", marker, name);
+            }
+        }
+        ProfileCommands::Show { name } => {
+            for entry in db.get_profile(&name)?.mods {
+                println!(
+                    "{}\t{}\t{}",
+                    entry.name,
+                    entry.version.as_deref().unwrap_or("-"),
+                    if entry.enabled { "enabled" } else { "disabled" }
+                );
+            }
+        }
+        ProfileCommands::Save { name } => {
+            let profile = ctx.manager.snapshot_profile(&name)?;
+            db.save_profile(&profile)?;
+            println!("Saved {} mods to profile {}", profile.mods.len(), name);
+        }
+        ProfileCommands::Delete { name } => {
+            db.delete_profile(&name)?;
+            println!("Deleted profile {}", name);
+        }
+        ProfileCommands::Activate { name } => return activate_profile(ctx, &name).await,
+    }
+    Ok(())
+}
+
+async fn activate_profile(ctx: &Context, name: &str) -> Result<(), AppError> {
+    let profile = ctx.manager.db().get_profile(name)?;
+    let plan = profiles::plan_activation(&profile, &ctx.manager.db().get_installed_mods()?);
+
+    let mut report = ProfileReport::new(&profile.name);
+    if !plan.install.is_empty() {
+        let catalog = load_catalog(ctx).await?;
+        profiles::install_missing(
+            ctx.installation_path.as_ref(),
+            &plan,
+            &catalog,
+            &mut report,
+            |entry, catalog_mod, archive, version| {
+                ctx.manager.record_install(
+                    &entry.name,
+                    &archive.path,
+                    &installer::catalog_dependencies(catalog_mod),
+                    version,
+                    Some(archive),
+                )
+            },
+        )
+        .await;
+    }
+    profiles::apply_states(&ctx.manager, &plan, &mut report);
+    ctx.manager.db().update_settings(|s| {
+        s.active_profile = Some(profile.name.clone());
+        Ok(())
+    })?;
+
+    for installed in &report.installed {
+        println!("Installed {}", installed);
+    }
+    for enabled in &report.enabled {
+        println!("Enabled {}", enabled);
+    }
+    for disabled in &report.disabled {
+        println!("Disabled {}", disabled);
+    }
+    for failed in &report.failed {
+        eprintln!("Failed {}: {}", failed.name, failed.error);
+    }
+
+    if report.failed.is_empty() {
+        println!("Profile {} is active", profile.name);
+        Ok(())
+    } else {
+        Err(AppError::InvalidState(format!(
+            "{} mods in profile {} could not be set up",
+            report.failed.len(),
+            profile.name
+        )))
+    }
+}
+
+async fn lock(ctx: &Context, output: &Path) -> Result<(), AppError> {
+    let catalog = load_catalog(ctx).await.unwrap_or_else(|e| {
+        log::warn!(
+            "Mod index unavailable, locking recorded sources only: {}",
+            e
+        );
+        Vec::new()
+    });
+    let lockfile = Lockfile::generate(&ctx.manager, &catalog)?;
+    lockfile.write(output)?;
+
+    for locked in lockfile.mods.iter().filter(|m| m.source.is_none()) {
+        eprintln!(
+            "warning: {} has no download source and can only be synced from the archive cache",
+            locked.name
+        );
+    }
+    println!(
+        "Locked {} mods to {}",
+        lockfile.mods.len(),
+        output.display()
+    );
+    Ok(())
+}
+
+async fn sync(ctx: &Context, path: &Path) -> Result<(), AppError> {
+    let lockfile = Lockfile::read(path)?;
+    let plan = lockfile::plan_sync(&lockfile, &ctx.manager.db().get_installed_mods()?, |m| {
+        ctx.manager
+            .verify_mod(&m.name)
+            .is_ok_and(|report| report.is_clean())
+    });
+
+    let mut report = SyncReport::default();
+    let downloads = lockfile::fetch_locked(&plan, &mut report).await;
+    lockfile::apply_sync(&ctx.manager, &lockfile, &plan, downloads, &mut report);
+
+    for installed in &report.installed {
+        println!("Installed {}", installed);
+    }
+    for removed in &report.removed {
+        println!("Removed {}", removed);
+    }
+    for warning in &report.warnings {
+        eprintln!("warning: {}", warning);
+    }
+    for failed in &report.failed {
+        eprintln!("Failed {}: {}", failed.name, failed.error);
+    }
+
+    if report.failed.is_empty() {
+        println!(
+            "Mods folder matches {} ({} already up to date)",
+            path.display(),
+            report.unchanged.len()
+        );
+        Ok(())
+    } else {
+        Err(AppError::InvalidState(format!(
+            "{} mods could not be synced",
+            report.failed.len()
+        )))
+    }
+}
+
+async fn collection(ctx: &Context, command: CollectionCommands) -> Result<(), AppError> {
+    let db = ctx.manager.db();
+    match command {
+        CollectionCommands::List => {
+            for collection in db.list_collections()? {
+                println!(
+                    "{}\t{}\t{} mods",
+                    collection.id,
+                    collection.name,
+                    collection.mods.len()
+                );
+            }
+        }
+        CollectionCommands::Show { id } => {
+            for entry in db.get_collection(&id)?.mods {
+                println!(
+                    "{}\t{}\t{}",
+                    entry.name,
+                    entry.version.as_deref().unwrap_or("-"),
+                    entry.download_url.as_deref().unwrap_or("-")
+                );
+            }
+        }
+        CollectionCommands::Create { name, description } => {
+            let catalog = load_catalog(ctx).await.unwrap_or_else(|e| {
+                log::warn!(
+                    "Mod index unavailable, collection will not link to it: {}",
+                    e
+                );
+                Vec::new()
+            });
+            let mut collection =
+                ModCollection::from_installed(name, &db.get_installed_mods()?, &catalog);
+            collection.description = description;
+            db.save_collection(&collection)?;
+            println!(
+                "Saved {} mods to collection {} ({})",
+                collection.mods.len(),
+                collection.name,
+                collection.id
+            );
+        }
+        CollectionCommands::Delete { id } => {
+            db.delete_collection(&id)?;
+            println!("Deleted collection {}", id);
+        }
+        CollectionCommands::Export { id, path } => {
+            let collection = db.get_collection(&id)?;
+            let content = collection.export(CollectionFormat::from_path(&path))?;
+            fs::write(&path, content).map_err(|e| AppError::FileWrite {
+                path: path.clone(),
+                source: e.to_string(),
+            })?;
+            println!("Exported {} to {}", collection.name, path.display());
+        }
+        CollectionCommands::Import { path, install } => {
+            let content = fs::read_to_string(&path).map_err(|e| AppError::FileRead {
+                path: path.clone(),
+                source: e.to_string(),
+            })?;
+            let parsed = ModCollection::import(&content, CollectionFormat::from_path(&path))?;
+            let original_id = parsed.id.clone();
+            let collection = db.import_collection(parsed)?;
+            println!(
+                "Imported collection {} ({})",
+                collection.name, collection.id
+            );
+            if collection.id != original_id {
+                println!(
+                    "A collection with id {} already exists, so this one got a new id",
+                    original_id
+                );
+            }
+            if install {
+                return install_collection(ctx, &collection.id).await;
+            }
+        }
+        CollectionCommands::Install { id } => return install_collection(ctx, &id).await,
+    }
+    Ok(())
+}
+
+async fn install_collection(ctx: &Context, id: &str) -> Result<(), AppError> {
+    let collection = ctx.manager.db().get_collection(id)?;
+    let installed_mods = ctx.manager.db().get_installed_mods()?;
+    let catalog = load_catalog(ctx).await.unwrap_or_else(|e| {
+        log::warn!(
+            "Mod index unavailable, using recorded downloads only: {}",
+            e
+        );
+        Vec::new()
+    });
+
+    let report = mod_collections::install_collection(
+        ctx.installation_path.as_ref(),
+        &collection,
+        &installed_mods,
+        &catalog,
+        |entry, archive, version, dependencies| {
+            ctx.manager.record_install(
+                &entry.name,
+                &archive.path,
+                dependencies,
+                version,
+                Some(archive),
+            )
+        },
+    )
+    .await;
+
+    for installed in &report.installed {
+        println!("Installed {}", installed);
+    }
+    for failed in &report.failed {
+        eprintln!("Failed {}: {}", failed.name, failed.error);
+    }
+
+    if report.failed.is_empty() {
+        println!(
+            "Collection {} is installed ({} already up to date)",
+            collection.name,
+            report.unchanged.len()
+        );
+        Ok(())
+    } else {
+        Err(AppError::InvalidState(format!(
+            "{} mods in collection {} could not be installed",
+            report.failed.len(),
+            collection.name
+        )))
+    }
+}
+
+fn list_archives(ctx: &Context) -> Result<(), AppError> {
+    let archives = ctx.manager.list_archives()?;
+    if archives.is_empty() {
+        println!("No cached archives");
+        return Ok(());
+    }
+
+    for archive in &archives {
+        println!(
+            "{}\t{:.1} MiB\t{}",
+            archive.sha256,
+            archive.size as f64 / MIB as f64,
+            archive.urls.join(", ")
+        );
+    }
+    Ok(())
+}
+
+fn prune_archives(ctx: &Context, max_size_mb: u64) -> Result<(), AppError> {
+    let removed = ctx.manager.prune_archives(max_size_mb * MIB)?;
+    let freed: u64 = removed.iter().map(|a| a.size).sum();
+    println!(
+        "Removed {} archives, freed {:.1} MiB",
+        removed.len(),
+        freed as f64 / MIB as f64
+    );
+    Ok(())
+}
+
+// Loads the configured index sources from disk, fetching any that have never
+// been pulled. Falls back to the last stored catalog when nothing can be read.
+async fn load_catalog(ctx: &Context) -> Result<Vec<Mod>, AppError> {
+    let sources = ctx.manager.db().settings()?.index_sources;
+    let loaded = async {
+        for source in &sources {
+            if !source.local_dir()?.join("mods").exists() {
+                if let Err(e) = source.sync().await {
+                    log::warn!("Failed to fetch index source {}: {}", source.name(), e);
+                }
+            }
+        }
+        let mods = index::load_sources(&sources)?;
+        if mods.is_empty() {
+            return Err(AppError::InvalidState(
+                "None of the index sources has any mods".to_string(),
+            ));
+        }
+        Ok(mods)
+    }
+    .await;
+
+    match loaded {
+        // Read back from the store so looked-up releases are applied
+        Ok(mods) => match cache::save_cache(&mods).and_then(|_| CatalogStore::open()?.mods()) {
+            Ok(stored) => Ok(stored),
+            Err(e) => {
+                log::warn!("Failed to store the catalog: {}", e);
+                Ok(mods)
+            }
+        },
+        Err(e) => match cache::load_cache()? {
+            Some((mods, refreshed_at)) => {
+                eprintln!(
+                    "warning: could not read the mod index ({}); using the catalog from {}",
+                    e,
+                    format_age(refreshed_at)
+                );
+                Ok(mods)
+            }
+            None => Err(e),
+        },
+    }
+}
+
+fn format_age(timestamp: u64) -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let age = now.saturating_sub(timestamp);
+    match age {
+        0..=119 => "just now".to_string(),
+        120..=7199 => format!("{} minutes ago", age / 60),
+        7200..=172_799 => format!("{} hours ago", age / 3600),
+        _ => format!("{} days ago", age / 86_400),
+    }
+}
+
+fn find_in_catalog<'a>(catalog: &'a [Mod], name: &str) -> Result<&'a Mod, AppError> {
+    index::find_catalog_mod(catalog, name).ok_or_else(|| AppError::ModNotFound {
+        mod_name: name.to_string(),
+        version: String::new(),
+    })
+}
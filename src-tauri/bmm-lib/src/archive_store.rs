@@ -0,0 +1,289 @@
+use crate::cache;
+use crate::checksum;
+use crate::errors::AppError;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Upper bound for the store before least recently used archives are evicted.
+pub const DEFAULT_MAX_BYTES: u64 = 1024 * 1024 * 1024;
+
+const INDEX_FILE: &str = "index.json";
+
+lazy_static! {
+    // Installs can run concurrently; serialize every read-modify-write of the index
+    static ref INDEX_LOCK: Mutex<()> = Mutex::new(());
+}
+
+/// An archive in the store and the URLs it has been downloaded from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredArchive {
+    pub sha256: String,
+    pub urls: Vec<String>,
+    pub size: u64,
+    pub last_used: u64,
+}
+
+/// Downloaded mod archives kept on disk under their SHA-256, so mods can be
+/// reinstalled, repaired or rolled back without going back to the network.
+pub struct ArchiveStore {
+    root: PathBuf,
+    max_bytes: u64,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+impl ArchiveStore {
+    pub fn new(root: PathBuf) -> Self {
+        Self {
+            root,
+            max_bytes: DEFAULT_MAX_BYTES,
+        }
+    }
+
+    pub fn open_default() -> Result<Self, AppError> {
+        Ok(Self::new(cache::get_cache_dir()?.join("archives")))
+    }
+
+    pub fn with_max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    // Only well-formed hashes map to a file, so a bad value can't escape the store
+    fn path_for(&self, sha256: &str) -> Option<PathBuf> {
+        checksum::is_sha256_hex(sha256).then(|| self.root.join(sha256.to_ascii_lowercase()))
+    }
+
+    fn load_index(&self) -> Vec<StoredArchive> {
+        let path = self.root.join(INDEX_FILE);
+        match fs::read(&path) {
+            Ok(data) => serde_json::from_slice(&data).unwrap_or_else(|e| {
+                log::warn!(
+                    "Ignoring unreadable archive index {}: {}",
+                    path.display(),
+                    e
+                );
+                Vec::new()
+            }),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    fn save_index(&self, index: &[StoredArchive]) -> Result<(), AppError> {
+        fs::create_dir_all(&self.root).map_err(|e| AppError::DirCreate {
+            path: self.root.clone(),
+            source: e.to_string(),
+        })?;
+        let path = self.root.join(INDEX_FILE);
+        fs::write(&path, serde_json::to_vec_pretty(index)?).map_err(|e| AppError::FileWrite {
+            path,
+            source: e.to_string(),
+        })
+    }
+
+    pub fn contains(&self, sha256: &str) -> bool {
+        self.path_for(sha256).is_some_and(|p| p.exists())
+    }
+
+    /// Store `data`, remembering `url` as one of its sources, and return its
+    /// hash. Storing the same bytes twice only refreshes the entry. Older
+    /// archives are evicted afterwards if the store grew past its limit.
+    pub fn put(&self, url: Option<&str>, data: &[u8]) -> Result<String, AppError> {
+        let sha256 = checksum::sha256_hex(data);
+        let path = self.root.join(&sha256);
+
+        let _guard = INDEX_LOCK.lock()?;
+        if !path.exists() {
+            fs::create_dir_all(&self.root).map_err(|e| AppError::DirCreate {
+                path: self.root.clone(),
+                source: e.to_string(),
+            })?;
+
+            // Write under a temporary name so a crash never leaves a truncated archive
+            let partial = self.root.join(format!("{}.partial", sha256));
+            fs::write(&partial, data).map_err(|e| AppError::FileWrite {
+                path: partial.clone(),
+                source: e.to_string(),
+            })?;
+            fs::rename(&partial, &path).map_err(|e| AppError::FileWrite {
+                path: path.clone(),
+                source: e.to_string(),
+            })?;
+        }
+
+        let mut index = self.load_index();
+        let entry = match index.iter().position(|a| a.sha256 == sha256) {
+            Some(pos) => &mut index[pos],
+            None => {
+                index.push(StoredArchive {
+                    sha256: sha256.clone(),
+                    urls: Vec::new(),
+                    size: data.len() as u64,
+                    last_used: 0,
+                });
+                index.last_mut().expect("just pushed")
+            }
+        };
+        entry.last_used = now();
+        if let Some(url) = url {
+            if !entry.urls.iter().any(|u| u == url) {
+                entry.urls.push(url.to_string());
+            }
+        }
+
+        let mut keep = HashSet::new();
+        keep.insert(sha256.clone());
+        self.evict(&mut index, self.max_bytes, &keep);
+        self.save_index(&index)?;
+
+        Ok(sha256)
+    }
+
+    /// Read a stored archive back, discarding it if it no longer matches its hash.
+    pub fn get(&self, sha256: &str) -> Result<Option<Vec<u8>>, AppError> {
+        let Some(path) = self.path_for(sha256) else {
+            return Ok(None);
+        };
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let data = fs::read(&path).map_err(|e| AppError::FileRead {
+            path: path.clone(),
+            source: e.to_string(),
+        })?;
+        let sha256 = sha256.to_ascii_lowercase();
+
+        let _guard = INDEX_LOCK.lock()?;
+        let mut index = self.load_index();
+        if checksum::sha256_hex(&data) != sha256 {
+            log::warn!("Discarding corrupt cached archive {}", path.display());
+            let _ = fs::remove_file(&path);
+            index.retain(|a| a.sha256 != sha256);
+            self.save_index(&index)?;
+            return Ok(None);
+        }
+
+        if let Some(entry) = index.iter_mut().find(|a| a.sha256 == sha256) {
+            entry.last_used = now();
+            self.save_index(&index)?;
+        }
+        Ok(Some(data))
+    }
+
+    /// The most recently used archive downloaded from `url`, if any.
+    pub fn find_by_url(&self, url: &str) -> Result<Option<(String, Vec<u8>)>, AppError> {
+        let candidate = {
+            let _guard = INDEX_LOCK.lock()?;
+            self.load_index()
+                .into_iter()
+                .filter(|a| a.urls.iter().any(|u| u == url))
+                .max_by_key(|a| a.last_used)
+        };
+
+        match candidate {
+            Some(archive) => Ok(self.get(&archive.sha256)?.map(|d| (archive.sha256, d))),
+            None => Ok(None),
+        }
+    }
+
+    /// Every stored archive, most recently used first.
+    pub fn list(&self) -> Result<Vec<StoredArchive>, AppError> {
+        let _guard = INDEX_LOCK.lock()?;
+        let mut index = self.load_index();
+        index.retain(|a| self.contains(&a.sha256));
+        index.sort_by_key(|a| Reverse(a.last_used));
+        Ok(index)
+    }
+
+    /// Evict least recently used archives until the store fits in `max_bytes`,
+    /// never touching the hashes in `keep`. Returns what was removed.
+    pub fn prune(
+        &self,
+        max_bytes: u64,
+        keep: &HashSet<String>,
+    ) -> Result<Vec<StoredArchive>, AppError> {
+        let _guard = INDEX_LOCK.lock()?;
+        let mut index = self.load_index();
+        let removed = self.evict(&mut index, max_bytes, keep);
+        self.save_index(&index)?;
+        Ok(removed)
+    }
+
+    fn evict(
+        &self,
+        index: &mut Vec<StoredArchive>,
+        max_bytes: u64,
+        keep: &HashSet<String>,
+    ) -> Vec<StoredArchive> {
+        // Entries whose file was deleted behind our back take no space
+        index.retain(|a| self.contains(&a.sha256));
+
+        let mut total: u64 = index.iter().map(|a| a.size).sum();
+        let mut candidates: Vec<StoredArchive> = index
+            .iter()
+            .filter(|a| !keep.contains(&a.sha256))
+            .cloned()
+            .collect();
+        candidates.sort_by_key(|a| a.last_used);
+
+        let mut removed = Vec::new();
+        for archive in candidates {
+            if total <= max_bytes {
+                break;
+            }
+            if let Some(path) = self.path_for(&archive.sha256) {
+                if let Err(e) = fs::remove_file(&path) {
+                    log::warn!("Failed to evict {}: {}", path.display(), e);
+                    continue;
+                }
+            }
+            log::info!("Evicted cached archive {}", archive.sha256);
+            total -= archive.size;
+            index.retain(|a| a.sha256 != archive.sha256);
+            removed.push(archive);
+        }
+
+        removed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_lookup_by_url_and_lru_eviction() -> Result<(), AppError> {
+        let temp = tempdir()?;
+        let store = ArchiveStore::new(temp.path().to_path_buf()).with_max_bytes(10);
+
+        let first = store.put(Some("https://example.com/a.zip"), b"aaaaaa")?;
+        let (found, data) = store.find_by_url("https://example.com/a.zip")?.unwrap();
+        assert_eq!(found, first);
+        assert_eq!(data, b"aaaaaa");
+
+        // Pushes the store past 10 bytes, so the older archive has to go
+        let second = store.put(Some("https://example.com/b.zip"), b"bbbbbb")?;
+        assert!(!store.contains(&first));
+        assert!(store.contains(&second));
+        assert!(store.find_by_url("https://example.com/a.zip")?.is_none());
+
+        let keep = HashSet::from([second.clone()]);
+        assert!(store.prune(0, &keep)?.is_empty());
+        assert_eq!(store.prune(0, &HashSet::new())?.len(), 1);
+        assert!(store.list()?.is_empty());
+        Ok(())
+    }
+}
@@ -1,4 +1,6 @@
+use crate::catalog::CatalogStore;
 use crate::errors::AppError;
+use crate::releases::LatestRelease;
 use flate2::read::GzDecoder;
 use flate2::write::GzEncoder;
 use flate2::Compression;
@@ -17,12 +19,6 @@ struct CacheHeader {
     timestamp: u64,
 }
 
-#[derive(Serialize, Deserialize)]
-struct ModCache {
-    header: CacheHeader,
-    mods: Vec<Mod>,
-}
-
 #[allow(non_snake_case)]
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Mod {
@@ -44,6 +40,71 @@ pub struct Mod {
     pub download_url: String,
     pub folderName: Option<String>,
     pub version: Option<String>,
+    #[serde(default)]
+    pub sha256: Option<String>,
+    /// Dependency constraints such as `Steamodded (>=1.0.0~ALPHA-1304a)`.
+    #[serde(default)]
+    pub dependencies: Vec<String>,
+    #[serde(default)]
+    pub conflicts: Vec<String>,
+    /// Name of the index source the entry came from.
+    #[serde(default)]
+    pub source: Option<String>,
+    #[serde(default)]
+    pub details: ModDetails,
+}
+
+#[cfg(test)]
+impl Mod {
+    /// A minimal catalog entry for tests to adjust with struct update syntax.
+    pub(crate) fn for_test(title: &str) -> Self {
+        Mod {
+            title: title.into(),
+            description: String::new(),
+            image: String::new(),
+            categories: Vec::new(),
+            colors: ColorPair {
+                color1: "#fff".into(),
+                color2: "#000".into(),
+            },
+            installed: false,
+            requires_steamodded: false,
+            requires_talisman: false,
+            publisher: String::new(),
+            repo: String::new(),
+            download_url: format!("https://example.com/{}.zip", title),
+            folderName: None,
+            version: None,
+            sha256: None,
+            dependencies: Vec::new(),
+            conflicts: Vec::new(),
+            source: None,
+            details: ModDetails::default(),
+        }
+    }
+}
+
+/// Optional extras an index entry can carry. Entries written before these
+/// existed simply have none of them.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct ModDetails {
+    /// Markdown from the entry's `changelog.md`.
+    pub changelog: Option<String>,
+    /// Unix time of the latest release.
+    pub last_updated: Option<u64>,
+    /// Size of the download in bytes.
+    pub archive_size: Option<u64>,
+    pub min_steamodded_version: Option<String>,
+    /// Game versions the mod is known to work with, such as `1.0.1o`.
+    pub game_versions: Vec<String>,
+    /// Image URLs, or absolute paths for images shipped with the entry.
+    pub screenshots: Vec<String>,
+    /// The index asks for the version to be taken from the repository's
+    /// latest GitHub release.
+    pub automatic_version_check: bool,
+    /// That release, once it has been looked up.
+    pub latest_release: Option<LatestRelease>,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -99,17 +160,13 @@ pub fn clear_cache() -> Result<(), AppError> {
         .ok_or_else(|| AppError::DirNotFound(PathBuf::from("cache directory")))?
         .join("balatro-mod-manager");
 
-    // Delete mods cache
-    let mods_cache = cache_dir.join("mods.cache.bin.gz");
-    if mods_cache.exists() {
-        std::fs::remove_file(&mods_cache).map_err(|e| AppError::FileWrite {
-            path: mods_cache,
-            source: e.to_string(),
-        })?;
-    }
-
-    // Delete version caches
+    // Delete the catalog store, its WAL files, the old bincode mods cache
+    // and the version caches
     [
+        "catalog.db",
+        "catalog.db-wal",
+        "catalog.db-shm",
+        "mods.cache.bin.gz",
         "versions-steamodded.cache.bin.gz",
         "versions-talisman.cache.bin.gz",
     ]
@@ -231,8 +288,8 @@ struct VersionCache {
     versions: Vec<String>,
 }
 
-pub fn get_cache_path() -> Result<PathBuf, AppError> {
-    let mut path = dirs::cache_dir()
+pub fn get_cache_dir() -> Result<PathBuf, AppError> {
+    let path = dirs::cache_dir()
         .ok_or_else(|| AppError::DirNotFound(PathBuf::from("cache directory")))?
         .join("balatro-mod-manager");
 
@@ -241,95 +298,30 @@ pub fn get_cache_path() -> Result<PathBuf, AppError> {
         source: e.to_string(),
     })?;
 
-    path.push("mods.cache.bin.gz");
     Ok(path)
 }
 
+/// Store a freshly read index in the catalog store.
 pub fn save_cache(mods: &[Mod]) -> Result<(), AppError> {
-    let path = get_cache_path()?;
-    let file = File::create(&path).map_err(|e| AppError::FileWrite {
-        path: path.clone(),
-        source: e.to_string(),
-    })?;
-
-    let mut encoder = GzEncoder::new(file, Compression::default());
-    let timestamp = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .map_err(|e| AppError::SystemTime(e.to_string()))?
-        .as_secs();
-
-    let cache = ModCache {
-        header: CacheHeader {
-            version: 1,
-            timestamp,
-        },
-        mods: mods.to_vec(),
-    };
-
-    // Use bincode 2.0 for serialization
-    let config = bincode::config::standard();
-    let encoded =
-        bincode::serde::encode_to_vec(&cache, config).map_err(|e| AppError::Serialization {
-            format: "bincode".into(),
-            source: e.to_string(),
-        })?;
-
-    encoder
-        .write_all(&encoded)
-        .map_err(|e| AppError::FileWrite {
-            path,
-            source: e.to_string(),
-        })?;
-
+    let summary = CatalogStore::open()?.refresh(mods)?;
+    log::debug!(
+        "Catalog refreshed: {} added, {} updated, {} removed",
+        summary.added,
+        summary.updated,
+        summary.removed
+    );
     Ok(())
 }
 
+/// The stored catalog and the time it was last refreshed, however long ago
+/// that was, so the mod list still works offline. `None` until the index
+/// has been read once.
 pub fn load_cache() -> Result<Option<(Vec<Mod>, u64)>, AppError> {
-    let path = get_cache_path()?;
-    let mut file = match File::open(&path) {
-        Ok(f) => f,
-        Err(_) => return Ok(None),
-    };
-
-    let mut buffer = Vec::new();
-    file.read_to_end(&mut buffer)
-        .map_err(|e| AppError::FileRead {
-            path: path.clone(),
-            source: e.to_string(),
-        })?;
-
-    let mut decoder = GzDecoder::new(buffer.as_slice());
-    let mut decompressed = Vec::new();
-
-    // Decompress the data
-    if let Err(e) = decoder.read_to_end(&mut decompressed) {
-        return Err(AppError::FileRead {
-            path: path.clone(),
-            source: e.to_string(),
-        });
-    }
-
-    // Deserialize using bincode 2.0
-    let config = bincode::config::standard();
-    let (cache, _): (ModCache, _) = match bincode::serde::decode_from_slice(&decompressed, config) {
-        Ok(result) => result,
-        Err(_) => return Ok(None),
-    };
-
-    if cache.header.version != 1 {
-        return Ok(None);
+    let store = CatalogStore::open()?;
+    match store.refreshed_at()? {
+        Some(refreshed_at) => Ok(Some((store.mods()?, refreshed_at))),
+        None => Ok(None),
     }
-
-    let current_time = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .map_err(|e| AppError::SystemTime(e.to_string()))?
-        .as_secs();
-
-    if current_time - cache.header.timestamp > CACHE_DURATION {
-        return Ok(None);
-    }
-
-    Ok(Some((cache.mods, cache.header.timestamp)))
 }
 
 #[cfg(test)]
@@ -373,6 +365,11 @@ mod tests {
                 download_url: "https://test.com/mod.zip".into(),
                 folderName: None,
                 version: None,
+                sha256: None,
+                dependencies: Vec::new(),
+                conflicts: Vec::new(),
+                source: None,
+                details: ModDetails::default(),
             };
 
             save_cache(&[test_mod.clone()])?;
@@ -384,4 +381,3 @@ mod tests {
         })
     }
 }
-
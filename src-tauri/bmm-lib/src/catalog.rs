@@ -0,0 +1,505 @@
+use crate::cache::{self, Category, Mod};
+use crate::checksum::sha256_hex;
+use crate::connection_pool::ConnectionPool;
+use crate::errors::AppError;
+use crate::releases::{self, LatestRelease};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A catalog older than this is still served, but callers should try to
+/// refresh it.
+pub const CATALOG_MAX_AGE: u64 = 15 * 60;
+
+// The store is only a cache of the index, so a schema change just rebuilds it
+const CATALOG_SCHEMA_VERSION: u32 = 3;
+
+/// The mod index kept in `catalog.db` in the cache directory. Each mod
+/// remembers when it was first and last seen in the index and when its
+/// entry last changed.
+pub struct CatalogStore {
+    pool: ConnectionPool,
+}
+
+/// What a refresh changed.
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct RefreshSummary {
+    pub added: usize,
+    pub updated: usize,
+    pub removed: usize,
+    pub unchanged: usize,
+}
+
+/// When the stored catalog was last refreshed and whether that is too long ago.
+#[derive(Debug, Clone, Serialize)]
+pub struct CatalogStatus {
+    pub refreshed_at: Option<u64>,
+    pub mod_count: usize,
+    pub stale: bool,
+}
+
+/// The last release lookup for one mod.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReleaseCheck {
+    /// Repository the lookup was for; a new one in the index starts over.
+    pub repo: String,
+    pub checked_at: u64,
+    /// `None` when the repository had no release.
+    pub release: Option<LatestRelease>,
+}
+
+/// Filters for `CatalogStore::query`. Empty fields match everything.
+#[derive(Debug, Default, Clone)]
+pub struct CatalogQuery {
+    /// Matched case-insensitively against title, description and publisher.
+    pub text: Option<String>,
+    pub category: Option<Category>,
+    /// Case-insensitive publisher name.
+    pub author: Option<String>,
+}
+
+fn now() -> Result<u64, AppError> {
+    Ok(SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|e| AppError::SystemTime(e.to_string()))?
+        .as_secs())
+}
+
+// Colors are picked at random each time the frontend reads the index and
+// `installed` is per-user, so neither counts as a change to the entry
+fn content_hash(m: &Mod) -> Result<String, AppError> {
+    let mut value = serde_json::to_value(m)?;
+    if let Some(fields) = value.as_object_mut() {
+        fields.remove("colors");
+        fields.remove("installed");
+    }
+    Ok(sha256_hex(value.to_string().as_bytes()))
+}
+
+// Escape LIKE wildcards so a search for "100%" means what it says
+fn like_pattern(text: &str) -> String {
+    let escaped = text
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{}%", escaped)
+}
+
+fn initialize(conn: &Connection) -> Result<(), AppError> {
+    let version: u32 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    if version != CATALOG_SCHEMA_VERSION {
+        if version != 0 {
+            log::info!(
+                "Rebuilding catalog store (schema {} -> {})",
+                version,
+                CATALOG_SCHEMA_VERSION
+            );
+        }
+        conn.execute_batch(&format!(
+            "BEGIN;
+            DROP TABLE IF EXISTS catalog_releases;
+            DROP TABLE IF EXISTS catalog_categories;
+            DROP TABLE IF EXISTS catalog_mods;
+            DROP TABLE IF EXISTS catalog_meta;
+            CREATE TABLE catalog_mods (
+                title TEXT PRIMARY KEY,
+                publisher TEXT NOT NULL,
+                description TEXT NOT NULL,
+                data TEXT NOT NULL,
+                content_hash TEXT NOT NULL,
+                first_seen INTEGER NOT NULL,
+                last_seen INTEGER NOT NULL,
+                changed_at INTEGER NOT NULL
+            );
+            CREATE INDEX catalog_mods_publisher ON catalog_mods (publisher COLLATE NOCASE);
+            CREATE TABLE catalog_categories (
+                title TEXT NOT NULL,
+                category INTEGER NOT NULL,
+                PRIMARY KEY (title, category)
+            );
+            CREATE INDEX catalog_categories_category ON catalog_categories (category);
+            CREATE TABLE catalog_releases (
+                title TEXT PRIMARY KEY,
+                repo TEXT NOT NULL,
+                checked_at INTEGER NOT NULL,
+                release TEXT
+            );
+            CREATE TABLE catalog_meta (
+                key TEXT PRIMARY KEY,
+                value INTEGER NOT NULL
+            );
+            PRAGMA user_version = {};
+            COMMIT;",
+            CATALOG_SCHEMA_VERSION
+        ))?;
+    }
+    Ok(())
+}
+
+// Rows of (data, release); a looked-up release is applied to its entry
+fn mods_from_rows(
+    stmt: &mut rusqlite::Statement,
+    params: &[&dyn rusqlite::ToSql],
+) -> Result<Vec<Mod>, AppError> {
+    let rows = stmt
+        .query_map(params, |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    rows.iter()
+        .map(|(data, release)| -> Result<Mod, AppError> {
+            let mut entry: Mod = serde_json::from_str(data)?;
+            if let Some(release) = release {
+                releases::apply_release(&mut entry, serde_json::from_str(release)?);
+            }
+            Ok(entry)
+        })
+        .collect()
+}
+
+impl CatalogStore {
+    pub fn path() -> Result<PathBuf, AppError> {
+        Ok(cache::get_cache_dir()?.join("catalog.db"))
+    }
+
+    pub fn open() -> Result<Self, AppError> {
+        let pool = ConnectionPool::open(Self::path()?)?;
+        initialize(&*pool.get()?)?;
+        Ok(Self { pool })
+    }
+
+    pub fn open_in_memory() -> Result<Self, AppError> {
+        let pool = ConnectionPool::open_in_memory()?;
+        initialize(&*pool.get()?)?;
+        Ok(Self { pool })
+    }
+
+    /// Bring the store in line with a freshly read index. Entries whose
+    /// content is the same keep their stored data and only get their
+    /// last-seen time bumped; mods that left the index are dropped.
+    pub fn refresh(&self, mods: &[Mod]) -> Result<RefreshSummary, AppError> {
+        self.refresh_at(mods, now()?)
+    }
+
+    fn refresh_at(&self, mods: &[Mod], now: u64) -> Result<RefreshSummary, AppError> {
+        let now = now as i64;
+        let conn = self.pool.get()?;
+        let tx = conn
+            .unchecked_transaction()
+            .map_err(|e| AppError::DatabaseTransaction(e.to_string()))?;
+
+        let known: HashMap<String, String> = {
+            let mut stmt = tx.prepare("SELECT title, content_hash FROM catalog_mods")?;
+            let rows = stmt
+                .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+                .collect::<Result<_, _>>()?;
+            rows
+        };
+
+        let mut summary = RefreshSummary::default();
+        let mut seen = HashSet::new();
+        for m in mods {
+            if !seen.insert(m.title.as_str()) {
+                log::warn!("Skipping duplicate catalog entry {}", m.title);
+                continue;
+            }
+            let hash = content_hash(m)?;
+            match known.get(&m.title) {
+                Some(stored) if *stored == hash => {
+                    tx.execute(
+                        "UPDATE catalog_mods SET last_seen = ?1 WHERE title = ?2",
+                        params![now, m.title],
+                    )?;
+                    summary.unchanged += 1;
+                    continue;
+                }
+                Some(_) => {
+                    tx.execute(
+                        "UPDATE catalog_mods SET publisher = ?1, description = ?2, data = ?3,
+                            content_hash = ?4, last_seen = ?5, changed_at = ?5
+                        WHERE title = ?6",
+                        params![
+                            m.publisher,
+                            m.description,
+                            serde_json::to_string(m)?,
+                            hash,
+                            now,
+                            m.title
+                        ],
+                    )?;
+                    tx.execute(
+                        "DELETE FROM catalog_categories WHERE title = ?1",
+                        [&m.title],
+                    )?;
+                    summary.updated += 1;
+                }
+                None => {
+                    tx.execute(
+                        "INSERT INTO catalog_mods (title, publisher, description, data,
+                            content_hash, first_seen, last_seen, changed_at)
+                        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?6, ?6)",
+                        params![
+                            m.title,
+                            m.publisher,
+                            m.description,
+                            serde_json::to_string(m)?,
+                            hash,
+                            now
+                        ],
+                    )?;
+                    summary.added += 1;
+                }
+            }
+            for category in &m.categories {
+                tx.execute(
+                    "INSERT OR IGNORE INTO catalog_categories (title, category) VALUES (?1, ?2)",
+                    params![m.title, *category as u32],
+                )?;
+            }
+        }
+
+        // Going by the titles rather than last_seen, which can't tell two
+        // refreshes within the same second apart
+        for title in known.keys().filter(|title| !seen.contains(title.as_str())) {
+            tx.execute("DELETE FROM catalog_categories WHERE title = ?1", [title])?;
+            summary.removed += tx.execute("DELETE FROM catalog_mods WHERE title = ?1", [title])?;
+        }
+        tx.execute(
+            "DELETE FROM catalog_releases WHERE title NOT IN (SELECT title FROM catalog_mods)",
+            [],
+        )?;
+        tx.execute(
+            "INSERT OR REPLACE INTO catalog_meta (key, value) VALUES ('refreshed_at', ?1)",
+            [now],
+        )?;
+        tx.commit()
+            .map_err(|e| AppError::DatabaseTransaction(e.to_string()))?;
+        Ok(summary)
+    }
+
+    /// Every stored mod, sorted by title.
+    pub fn mods(&self) -> Result<Vec<Mod>, AppError> {
+        self.query(&CatalogQuery::default())
+    }
+
+    /// The stored entry with this title, ignoring case.
+    pub fn get(&self, title: &str) -> Result<Option<Mod>, AppError> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(
+            "SELECT m.data, r.release FROM catalog_mods m
+            LEFT JOIN catalog_releases r ON r.title = m.title
+            WHERE m.title = ?1 COLLATE NOCASE",
+        )?;
+        Ok(mods_from_rows(&mut stmt, params![title])?.pop())
+    }
+
+    pub fn query(&self, query: &CatalogQuery) -> Result<Vec<Mod>, AppError> {
+        let text = query
+            .text
+            .as_deref()
+            .map(str::trim)
+            .filter(|t| !t.is_empty());
+        let author = query
+            .author
+            .as_deref()
+            .map(str::trim)
+            .filter(|a| !a.is_empty());
+        let text_pattern = text.map(like_pattern);
+        let category = query.category.map(|c| c as u32);
+
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(
+            "SELECT m.data, r.release FROM catalog_mods m
+            LEFT JOIN catalog_releases r ON r.title = m.title
+            WHERE (?1 IS NULL OR m.title LIKE ?1 ESCAPE '\\'
+                OR m.description LIKE ?1 ESCAPE '\\'
+                OR m.publisher LIKE ?1 ESCAPE '\\')
+            AND (?2 IS NULL OR m.publisher = ?2 COLLATE NOCASE)
+            AND (?3 IS NULL OR EXISTS (
+                SELECT 1 FROM catalog_categories c WHERE c.title = m.title AND c.category = ?3))
+            ORDER BY m.title COLLATE NOCASE",
+        )?;
+        mods_from_rows(&mut stmt, params![text_pattern, author, category])
+    }
+
+    pub fn refreshed_at(&self) -> Result<Option<u64>, AppError> {
+        let conn = self.pool.get()?;
+        Ok(conn
+            .query_row(
+                "SELECT value FROM catalog_meta WHERE key = 'refreshed_at'",
+                [],
+                |row| row.get::<_, i64>(0),
+            )
+            .optional()?
+            .map(|at| at as u64))
+    }
+
+    pub fn status(&self) -> Result<CatalogStatus, AppError> {
+        let refreshed_at = self.refreshed_at()?;
+        let conn = self.pool.get()?;
+        let mod_count: i64 =
+            conn.query_row("SELECT COUNT(*) FROM catalog_mods", [], |row| row.get(0))?;
+        let now = now()?;
+        Ok(CatalogStatus {
+            refreshed_at,
+            mod_count: mod_count as usize,
+            stale: refreshed_at.is_none_or(|at| now.saturating_sub(at) > CATALOG_MAX_AGE),
+        })
+    }
+
+    /// When each stored entry last changed, by title.
+    pub fn changed_times(&self) -> Result<HashMap<String, u64>, AppError> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare("SELECT title, changed_at FROM catalog_mods")?;
+        let times = stmt
+            .query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as u64))
+            })?
+            .collect::<Result<_, _>>()?;
+        Ok(times)
+    }
+
+    pub fn release_check(&self, title: &str) -> Result<Option<ReleaseCheck>, AppError> {
+        let conn = self.pool.get()?;
+        let row = conn
+            .query_row(
+                "SELECT repo, checked_at, release FROM catalog_releases WHERE title = ?1",
+                [title],
+                |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, i64>(1)?,
+                        row.get::<_, Option<String>>(2)?,
+                    ))
+                },
+            )
+            .optional()?;
+        row.map(|(repo, checked_at, release)| -> Result<_, AppError> {
+            Ok(ReleaseCheck {
+                repo,
+                checked_at: checked_at as u64,
+                release: release.map(|r| serde_json::from_str(&r)).transpose()?,
+            })
+        })
+        .transpose()
+    }
+
+    /// Store the result of a release lookup.
+    pub fn record_release(
+        &self,
+        title: &str,
+        repo: &str,
+        checked_at: u64,
+        release: Option<&LatestRelease>,
+    ) -> Result<(), AppError> {
+        let release = release.map(serde_json::to_string).transpose()?;
+        let conn = self.pool.get()?;
+        conn.execute(
+            "INSERT OR REPLACE INTO catalog_releases (title, repo, checked_at, release)
+            VALUES (?1, ?2, ?3, ?4)",
+            params![title, repo, checked_at as i64, release],
+        )?;
+        Ok(())
+    }
+
+    /// When a mod's entry was first seen, last seen and last changed.
+    pub fn timestamps(&self, title: &str) -> Result<Option<(u64, u64, u64)>, AppError> {
+        let conn = self.pool.get()?;
+        Ok(conn
+            .query_row(
+                "SELECT first_seen, last_seen, changed_at FROM catalog_mods WHERE title = ?1",
+                [title],
+                |row| {
+                    Ok((
+                        row.get::<_, i64>(0)? as u64,
+                        row.get::<_, i64>(1)? as u64,
+                        row.get::<_, i64>(2)? as u64,
+                    ))
+                },
+            )
+            .optional()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn catalog_mod(title: &str, publisher: &str, categories: Vec<Category>) -> Mod {
+        Mod {
+            description: format!("{} adds more jokers", title),
+            image: "images/cover.jpg".into(),
+            categories,
+            requires_steamodded: true,
+            publisher: publisher.into(),
+            repo: format!("https://github.com/{}/{}", publisher, title),
+            version: Some("1.0.0".into()),
+            ..Mod::for_test(title)
+        }
+    }
+
+    #[test]
+    fn test_refresh_only_rewrites_changed_entries() -> Result<(), AppError> {
+        let store = CatalogStore::open_in_memory()?;
+        assert!(store.status()?.stale);
+
+        let mut cryptid = catalog_mod("Cryptid", "MathIsFun", vec![Category::Content]);
+        let talisman = catalog_mod("Talisman", "MathIsFun", vec![Category::API]);
+        let jokers = catalog_mod("Extra_Jokers", "someone", vec![Category::Joker]);
+        let summary = store.refresh_at(&[cryptid.clone(), talisman.clone(), jokers], 100)?;
+        assert_eq!(summary.added, 3);
+
+        // New colors alone are not a change; a new version is
+        cryptid.colors.color1 = "#123".into();
+        let mut talisman = talisman;
+        talisman.version = Some("2.0.0".into());
+        let summary = store.refresh_at(&[cryptid, talisman], 200)?;
+        assert_eq!(
+            summary,
+            RefreshSummary {
+                added: 0,
+                updated: 1,
+                removed: 1,
+                unchanged: 1,
+            }
+        );
+        assert_eq!(store.timestamps("Cryptid")?, Some((100, 200, 100)));
+        assert_eq!(store.timestamps("Talisman")?, Some((100, 200, 200)));
+        assert_eq!(store.refreshed_at()?, Some(200));
+
+        let by_author = store.query(&CatalogQuery {
+            author: Some("mathisfun".into()),
+            ..Default::default()
+        })?;
+        assert_eq!(by_author.len(), 2);
+        let by_category = store.query(&CatalogQuery {
+            category: Some(Category::API),
+            ..Default::default()
+        })?;
+        assert_eq!(by_category[0].version.as_deref(), Some("2.0.0"));
+        let by_text = store.query(&CatalogQuery {
+            text: Some("cRYPT".into()),
+            ..Default::default()
+        })?;
+        assert_eq!(by_text.len(), 1);
+        assert_eq!(by_text[0].colors.color1, "#fff");
+        // "_" is not a wildcard
+        let literal = store.query(&CatalogQuery {
+            text: Some("y_".into()),
+            ..Default::default()
+        })?;
+        assert!(literal.is_empty());
+
+        let stored = store.get("talisman")?.and_then(|m| m.version);
+        assert_eq!(stored.as_deref(), Some("2.0.0"));
+        assert!(store.get("Extra_Jokers")?.is_none());
+
+        // A second refresh within the same second still drops what left
+        let cryptid = store.get("Cryptid")?.expect("Cryptid is stored");
+        assert_eq!(store.refresh_at(&[cryptid], 200)?.removed, 1);
+        assert!(store.get("Talisman")?.is_none());
+        Ok(())
+    }
+}
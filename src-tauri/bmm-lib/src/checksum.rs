@@ -0,0 +1,79 @@
+use crate::errors::AppError;
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+/// Lowercase hex SHA-256 of `data`.
+pub fn sha256_hex(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+/// Streaming SHA-256 of a file on disk.
+pub fn sha256_file(path: &Path) -> Result<String, AppError> {
+    let mut file = File::open(path).map_err(|e| AppError::FileRead {
+        path: path.to_path_buf(),
+        source: e.to_string(),
+    })?;
+    let mut hasher = Sha256::new();
+    io::copy(&mut file, &mut hasher).map_err(|e| AppError::FileRead {
+        path: path.to_path_buf(),
+        source: e.to_string(),
+    })?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+pub fn is_sha256_hex(value: &str) -> bool {
+    value.len() == 64 && value.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Accepts bare hex as well as GitHub's "sha256:<hex>" digest format.
+pub fn normalize_digest(expected: &str) -> String {
+    let expected = expected.trim();
+    expected
+        .strip_prefix("sha256:")
+        .unwrap_or(expected)
+        .to_ascii_lowercase()
+}
+
+/// Hash a downloaded archive and compare it against `expected` when one is
+/// known. Returns the computed hash so it can be stored with the mod.
+pub fn verify_sha256(url: &str, data: &[u8], expected: Option<&str>) -> Result<String, AppError> {
+    let actual = sha256_hex(data);
+
+    if let Some(expected) = expected.map(normalize_digest).filter(|e| !e.is_empty()) {
+        if expected != actual {
+            return Err(AppError::ChecksumMismatch {
+                url: url.to_string(),
+                expected,
+                actual,
+            });
+        }
+        log::debug!("Checksum verified for {}", url);
+    }
+
+    Ok(actual)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HELLO_SHA256: &str = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+
+    #[test]
+    fn test_verify_sha256() {
+        assert_eq!(verify_sha256("u", b"hello", None).unwrap(), HELLO_SHA256);
+        assert!(verify_sha256("u", b"hello", Some(HELLO_SHA256)).is_ok());
+        assert!(verify_sha256(
+            "u",
+            b"hello",
+            Some(&format!("sha256:{}", HELLO_SHA256.to_uppercase()))
+        )
+        .is_ok());
+        assert!(matches!(
+            verify_sha256("u", b"tampered", Some(HELLO_SHA256)),
+            Err(AppError::ChecksumMismatch { .. })
+        ));
+    }
+}
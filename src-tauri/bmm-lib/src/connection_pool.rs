@@ -0,0 +1,176 @@
+use crate::errors::AppError;
+use rusqlite::{Connection, OpenFlags};
+use std::ops::{Deref, DerefMut};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Connections kept open between uses. More can be open at once; the extras
+/// are closed when handed back.
+pub const DEFAULT_MAX_IDLE: usize = 4;
+
+// Writers wait this long for each other instead of failing with SQLITE_BUSY
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+static MEMORY_DB_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+enum Target {
+    File(PathBuf),
+    // Shared-cache URI, so every connection sees the same in-memory database
+    Memory(String),
+}
+
+/// A small set of SQLite connections to one database. Handing out a
+/// connection never blocks: if none is idle a new one is opened, so a caller
+/// that already holds one can safely ask for another.
+pub struct ConnectionPool {
+    target: Target,
+    idle: Mutex<Vec<Connection>>,
+    max_idle: usize,
+    // An in-memory database disappears with its last connection
+    _keep_alive: Option<Mutex<Connection>>,
+}
+
+/// A connection borrowed from the pool. It goes back when dropped.
+pub struct PooledConnection<'a> {
+    pool: &'a ConnectionPool,
+    conn: Option<Connection>,
+}
+
+impl ConnectionPool {
+    /// Pool for a database file, switched to WAL so readers don't wait for writers.
+    pub fn open(path: PathBuf) -> Result<Self, AppError> {
+        let pool = Self {
+            target: Target::File(path),
+            idle: Mutex::new(Vec::new()),
+            max_idle: DEFAULT_MAX_IDLE,
+            _keep_alive: None,
+        };
+        let conn = pool.connect()?;
+        // The journal mode sticks to the file, so setting it once is enough
+        conn.query_row("PRAGMA journal_mode = WAL", [], |_| Ok(()))?;
+        pool.idle.lock()?.push(conn);
+        Ok(pool)
+    }
+
+    /// Pool for a private in-memory database that lives as long as the pool.
+    pub fn open_in_memory() -> Result<Self, AppError> {
+        let name = format!(
+            "file:bmm-memdb-{}-{}?mode=memory&cache=shared",
+            std::process::id(),
+            MEMORY_DB_COUNTER.fetch_add(1, Ordering::Relaxed)
+        );
+        let mut pool = Self {
+            target: Target::Memory(name),
+            idle: Mutex::new(Vec::new()),
+            max_idle: DEFAULT_MAX_IDLE,
+            _keep_alive: None,
+        };
+        pool._keep_alive = Some(Mutex::new(pool.connect()?));
+        Ok(pool)
+    }
+
+    pub fn with_max_idle(mut self, max_idle: usize) -> Self {
+        self.max_idle = max_idle;
+        self
+    }
+
+    fn connect(&self) -> Result<Connection, AppError> {
+        let conn = match &self.target {
+            Target::File(path) => Connection::open(path),
+            Target::Memory(uri) => Connection::open_with_flags(
+                uri,
+                OpenFlags::SQLITE_OPEN_READ_WRITE
+                    | OpenFlags::SQLITE_OPEN_CREATE
+                    | OpenFlags::SQLITE_OPEN_URI
+                    | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+            ),
+        }
+        .map_err(|e| AppError::DatabaseInit(e.to_string()))?;
+
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        if let Target::File(_) = self.target {
+            // Safe with WAL; only the last transactions can be lost on power failure
+            conn.execute_batch("PRAGMA synchronous = NORMAL;")?;
+        }
+        Ok(conn)
+    }
+
+    pub fn get(&self) -> Result<PooledConnection<'_>, AppError> {
+        let idle = self.idle.lock()?.pop();
+        let conn = match idle {
+            Some(conn) => conn,
+            None => self.connect()?,
+        };
+        Ok(PooledConnection {
+            pool: self,
+            conn: Some(conn),
+        })
+    }
+}
+
+impl Deref for PooledConnection<'_> {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        self.conn
+            .as_ref()
+            .expect("connection is only taken on drop")
+    }
+}
+
+impl DerefMut for PooledConnection<'_> {
+    fn deref_mut(&mut self) -> &mut Connection {
+        self.conn
+            .as_mut()
+            .expect("connection is only taken on drop")
+    }
+}
+
+impl Drop for PooledConnection<'_> {
+    fn drop(&mut self) {
+        let Some(conn) = self.conn.take() else {
+            return;
+        };
+        // Never hand out a connection stuck in a transaction someone forgot to finish
+        if !conn.is_autocommit() {
+            return;
+        }
+        if let Ok(mut idle) = self.pool.idle.lock() {
+            if idle.len() < self.pool.max_idle {
+                idle.push(conn);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_connections_share_one_database() -> Result<(), AppError> {
+        let memory = ConnectionPool::open_in_memory()?;
+        let temp = tempdir()?;
+        let file = ConnectionPool::open(temp.path().join("test.db"))?.with_max_idle(1);
+
+        for pool in [&memory, &file] {
+            let first = pool.get()?;
+            first
+                .execute_batch("CREATE TABLE items (id INTEGER); INSERT INTO items VALUES (1);")?;
+            // Asking again while holding a connection opens a second one
+            let second = pool.get()?;
+            let count: i64 = second.query_row("SELECT COUNT(*) FROM items", [], |r| r.get(0))?;
+            assert_eq!(count, 1);
+        }
+
+        let mode: String = file
+            .get()?
+            .query_row("PRAGMA journal_mode", [], |r| r.get(0))?;
+        assert_eq!(mode, "wal");
+        assert_eq!(file.idle.lock()?.len(), 1);
+        Ok(())
+    }
+}
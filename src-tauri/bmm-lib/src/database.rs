@@ -1,11 +1,22 @@
 // use crate::cache::Mod;
+use crate::connection_pool::{ConnectionPool, PooledConnection};
 use crate::errors::AppError;
-use rusqlite::Connection;
+use crate::manifest::ManifestEntry;
+use crate::migrations;
+use crate::mod_collections::{ModCollection, ModCollectionManager};
+use crate::profiles::{Profile, ProfileMod};
+use crate::settings::Settings;
+use rusqlite::{Connection, Transaction, TransactionBehavior};
 use serde::Serialize;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
+/// Handle to `bmm_storage.db`. Cloning is cheap and every clone shares the
+/// same connection pool, so each caller takes a connection only for as long
+/// as one query or transaction needs it.
+#[derive(Clone)]
 pub struct Database {
-    conn: Connection,
+    pool: Arc<ConnectionPool>,
 }
 
 #[derive(Serialize)]
@@ -14,73 +25,64 @@ pub struct InstalledMod {
     pub path: String,
     pub dependencies: Vec<String>,
     pub current_version: Option<String>,
+    pub archive_sha256: Option<String>,
+    pub download_url: Option<String>,
+    pub pinned: bool,
 }
 
-impl Database {
-    const CURRENT_DB_VERSION: &'static str = "1.1"; // Update this when schema changes
+/// A version of a mod that was installed before the current one.
+#[derive(Debug, Clone, Serialize)]
+pub struct VersionRecord {
+    pub id: i64,
+    pub version: Option<String>,
+    pub download_url: Option<String>,
+    pub archive_sha256: Option<String>,
+    pub installed_at: u64,
+}
 
+// Only the most recent versions of each mod are worth going back to
+const MAX_VERSION_HISTORY: i64 = 10;
+
+const INSTALLED_MOD_COLUMNS: &str =
+    "name, path, dependencies, current_version, archive_sha256, download_url, pinned";
+
+fn installed_mod_from_row(row: &rusqlite::Row) -> Result<InstalledMod, AppError> {
+    Ok(InstalledMod {
+        name: row.get(0)?,
+        path: row.get(1)?,
+        dependencies: serde_json::from_str(&row.get::<_, String>(2)?)?,
+        current_version: row.get(3)?,
+        archive_sha256: row.get(4)?,
+        download_url: row.get(5)?,
+        pinned: row.get(6)?,
+    })
+}
+
+impl Database {
     pub fn new() -> Result<Self, AppError> {
         let config_dir = dirs::config_dir()
             .ok_or_else(|| AppError::DirNotFound(PathBuf::from("config directory")))?;
         let balatro_dir = config_dir.join("Balatro");
 
-        // Create directory if it doesn't exist
-        if !balatro_dir.exists() {
-            std::fs::create_dir_all(&balatro_dir).map_err(|e| {
-                AppError::DirNotFound(format!("Failed to create config directory: {}", e).into())
-            })?
-        };
-
         // Create the Balatro config directory if it doesn't exist
-        let balatro_dir = config_dir.join("Balatro");
         std::fs::create_dir_all(&balatro_dir).map_err(|e| AppError::DirCreate {
             path: balatro_dir.clone(),
             source: e.to_string(),
         })?;
 
         let storage_path = balatro_dir.join("bmm_storage.db");
-        // Now check if the database exists and open it
-        let db_exists = storage_path.exists();
-        let conn =
-            Connection::open(&storage_path).map_err(|e| AppError::DatabaseInit(e.to_string()))?;
-
-        if !db_exists {
-            Self::initialize_database(&conn)?;
-        }
 
         // Try to open the database with a retry mechanism
         let mut retry_count = 0;
         let max_retries = 3;
 
         while retry_count < max_retries {
-            // Try to open or create the database
-            let conn_result = if storage_path.exists() {
-                Connection::open(&storage_path)
-            } else {
-                // Create a new database
-                let conn = Connection::open(&storage_path)
-                    .map_err(|e| AppError::DatabaseInit(e.to_string()))?;
-                Self::initialize_database(&conn)?;
-                Ok(conn)
-            };
-
-            match conn_result {
-                Ok(conn) => {
-                    // Check if database needs migration
-                    if Self::needs_migration(&conn)? {
-                        // Close the connection before migration
-                        drop(conn);
-
-                        // Perform migration
-                        Self::migrate_database(&storage_path)?;
-
-                        // Reopen the database after migration
-                        let conn = Connection::open(&storage_path)
-                            .map_err(|e| AppError::DatabaseInit(e.to_string()))?;
-                        return Ok(Database { conn });
-                    }
-
-                    return Ok(Database { conn });
+            match ConnectionPool::open(storage_path.clone()) {
+                Ok(pool) => {
+                    Self::initialize_database(&mut *pool.get()?, Some(&storage_path))?;
+                    return Ok(Database {
+                        pool: Arc::new(pool),
+                    });
                 }
                 Err(e) => {
                     if retry_count == max_retries - 1 {
@@ -107,358 +109,277 @@ impl Database {
         ))
     }
 
-    // Check if database needs migration
-    fn needs_migration(conn: &Connection) -> Result<bool, AppError> {
-        // First check if the version table exists
-        let has_version_setting = match conn.query_row(
-            "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name='settings'",
-            [],
-            |row| row.get::<_, i64>(0),
-        ) {
-            Ok(count) if count > 0 => {
-                // Now check if the version setting exists
-                match conn.query_row(
-                    "SELECT COUNT(*) FROM settings WHERE setting = 'db_version'",
-                    [],
-                    |row| row.get::<_, i64>(0),
-                ) {
-                    Ok(count) => count > 0,
-                    Err(_) => false,
-                }
-            }
-            _ => false,
-        };
-
-        // If no version in settings, this is an old database needing migration
-        if !has_version_setting {
-            return Ok(true);
+    /// Fresh database that lives only as long as the handle, for tests and
+    /// throwaway tooling.
+    pub fn open_in_memory() -> Result<Self, AppError> {
+        let pool = ConnectionPool::open_in_memory()?;
+        Self::initialize_database(&mut *pool.get()?, None)?;
+        Ok(Database {
+            pool: Arc::new(pool),
+        })
+    }
+
+    fn conn(&self) -> Result<PooledConnection<'_>, AppError> {
+        self.pool.get()
+    }
+
+    /// Run `write` on one connection inside an IMMEDIATE transaction, so the
+    /// changes it makes through the `_in` methods are stored together or not
+    /// at all.
+    pub fn transaction<T, F>(&self, write: F) -> Result<T, AppError>
+    where
+        F: FnOnce(&Connection) -> Result<T, AppError>,
+    {
+        let conn = self.conn()?;
+        let tx = Transaction::new_unchecked(&conn, TransactionBehavior::Immediate)
+            .map_err(|e| AppError::DatabaseTransaction(e.to_string()))?;
+        let result = write(&tx)?;
+        tx.commit()
+            .map_err(|e| AppError::DatabaseTransaction(e.to_string()))?;
+        Ok(result)
+    }
+
+    // Bring the schema up to date. A database file that already holds data is
+    // copied aside first so a failed migration can be undone by hand.
+    fn initialize_database(conn: &mut Connection, path: Option<&Path>) -> Result<(), AppError> {
+        if !migrations::has_pending(conn)? {
+            return Ok(());
         }
 
-        // Check if the version matches
-        match conn.query_row(
-            "SELECT value FROM settings WHERE setting = 'db_version'",
-            [],
-            |row| row.get::<_, String>(0),
-        ) {
-            Ok(version) => Ok(version != Self::CURRENT_DB_VERSION),
-            Err(_) => Ok(true), // If we can't get the version, assume migration is needed
-        }
-    }
-
-    fn migrate_database(db_path: &PathBuf) -> Result<(), AppError> {
-        // Create a temporary database path
-        let temp_db_path = db_path.with_file_name("bmm_storage_new.db");
-
-        // If the old database exists but we can't access it, try with a retry mechanism
-        let max_retries = 3;
-        let mut retry_count = 0;
-
-        while retry_count < max_retries {
-            // Open connections to both databases
-            let old_conn_result = Connection::open(db_path);
-
-            if let Err(e) = old_conn_result {
-                if retry_count == max_retries - 1 {
-                    return Err(AppError::DatabaseInit(format!(
-                        "Failed to open old database after {} retries: {}",
-                        max_retries, e
-                    )));
-                }
-
-                // Wait before retrying
-                std::thread::sleep(std::time::Duration::from_millis(500));
-                retry_count += 1;
-                continue;
+        let has_tables = conn.query_row("SELECT COUNT(*) FROM sqlite_master", [], |row| {
+            row.get::<_, i64>(0)
+        })? > 0;
+        if let (Some(path), true) = (path, has_tables) {
+            match migrations::backup(path, conn) {
+                Ok(backup_path) => log::info!("Backed up database to {}", backup_path.display()),
+                Err(e) => log::warn!("Failed to back up database, migrating anyway: {}", e),
             }
+        }
 
-            let old_conn = old_conn_result.unwrap();
-            let new_conn = Connection::open(&temp_db_path).map_err(|e| {
-                AppError::DatabaseInit(format!("Failed to create new database: {}", e))
-            })?;
+        migrations::migrate(conn)?;
+        Ok(())
+    }
 
-            // Initialize the new database with current schema
-            Self::initialize_database(&new_conn)?;
+    pub fn settings(&self) -> Result<Settings, AppError> {
+        let conn = self.conn()?;
+        Settings::load(&conn)
+    }
 
-            // Migrate data
-            Self::migrate_settings(&old_conn, &new_conn)?;
-            Self::migrate_installed_mods(&old_conn, &new_conn)?;
+    pub fn save_settings(&self, settings: &Settings) -> Result<(), AppError> {
+        let conn = self.conn()?;
+        settings.save(&conn)
+    }
 
-            // IMPORTANT: Explicitly close connections before file operations
-            drop(old_conn);
-            drop(new_conn);
+    /// Change settings atomically and return the result.
+    pub fn update_settings<F>(&self, change: F) -> Result<Settings, AppError>
+    where
+        F: FnOnce(&mut Settings) -> Result<(), AppError>,
+    {
+        let conn = self.conn()?;
+        Settings::update(&conn, change)
+    }
 
-            // Add a small delay to ensure all handles are released
-            std::thread::sleep(std::time::Duration::from_millis(100));
+    pub fn get_mod_details(&self, mod_name: &str) -> Result<InstalledMod, AppError> {
+        Self::get_mod_details_in(&*self.conn()?, mod_name)
+    }
 
-            // Try to backup the old database
-            let backup_path = db_path.with_extension("db.bak");
+    pub fn get_mod_details_in(conn: &Connection, mod_name: &str) -> Result<InstalledMod, AppError> {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM installed_mods WHERE name = ?1",
+            INSTALLED_MOD_COLUMNS
+        ))?;
 
-            // If backup fails, just log a warning but continue
-            match std::fs::rename(db_path, &backup_path) {
-                Ok(_) => {}
-                Err(e) => {
-                    log::warn!("Failed to backup old database, continuing anyway: {}", e);
-                    // Try to directly remove the old file if we can't rename it
-                    if let Err(e) = std::fs::remove_file(db_path) {
-                        log::warn!("Failed to remove old database: {}", e);
-                    }
-                }
-            }
+        let mut rows = stmt.query([mod_name])?;
 
-            // Replace with the new one
-            match std::fs::rename(&temp_db_path, db_path) {
-                Ok(_) => return Ok(()),
-                Err(e) => {
-                    return Err(AppError::DatabaseInit(format!(
-                        "Failed to install new database: {}",
-                        e
-                    )));
-                }
-            }
+        if let Some(row) = rows.next()? {
+            installed_mod_from_row(row)
+        } else {
+            Err(AppError::InvalidState(format!(
+                "Mod {} not found",
+                mod_name
+            )))
         }
-
-        Err(AppError::DatabaseInit(
-            "Failed to access database after maximum retries".to_string(),
-        ))
     }
 
-    fn migrate_settings(old_conn: &Connection, new_conn: &Connection) -> Result<(), AppError> {
-        // Check if settings table exists in old database
-        let has_settings = match old_conn.query_row(
-            "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name='settings'",
-            [],
-            |row| row.get::<_, i64>(0),
-        ) {
-            Ok(count) => count > 0,
-            Err(_) => false,
-        };
+    pub fn get_installed_mods(&self) -> Result<Vec<InstalledMod>, AppError> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM installed_mods",
+            INSTALLED_MOD_COLUMNS
+        ))?;
+        let mut mods = Vec::new();
+        let mut rows = stmt.query([])?;
 
-        if !has_settings {
-            return Ok(()); // No settings to migrate
+        while let Some(row) = rows.next()? {
+            mods.push(installed_mod_from_row(row)?);
         }
 
-        // Get all settings except db_version (which will be set by initialize_database)
-        let mut stmt = match old_conn
-            .prepare("SELECT setting, value FROM settings WHERE setting != 'db_version'")
-        {
-            Ok(stmt) => stmt,
-            Err(_) => return Ok(()), // If query fails, just continue
-        };
+        Ok(mods)
+    }
 
-        for (setting, value) in stmt
-            .query_map([], |row| {
-                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
-            })?
-            .flatten()
-        {
-            new_conn.execute(
-                "INSERT OR REPLACE INTO settings (setting, value) VALUES (?1, ?2)",
-                [&setting, &value],
-            )?;
-        }
+    pub fn add_installed_mod(
+        &self,
+        name: &str,
+        path: &str,
+        dependencies: &[String],
+        current_version: Option<String>,
+    ) -> Result<(), AppError> {
+        Self::add_installed_mod_in(&*self.conn()?, name, path, dependencies, current_version)
+    }
 
-        if let Ok(value) = old_conn.query_row(
-            "SELECT value FROM settings WHERE setting = 'security_warning_acknowledged'",
-            [],
-            |row| row.get::<_, String>(0),
-        ) {
-            new_conn.execute(
-            "INSERT OR REPLACE INTO settings (setting, value) VALUES ('security_warning_acknowledged', ?1)",
-            [&value],
+    pub fn add_installed_mod_in(
+        conn: &Connection,
+        name: &str,
+        path: &str,
+        dependencies: &[String],
+        current_version: Option<String>,
+    ) -> Result<(), AppError> {
+        let deps_json = serde_json::to_string(dependencies)?;
+        // Reinstalling replaces the archive details but keeps the pin
+        conn.execute(
+            "INSERT INTO installed_mods (name, path, dependencies, current_version) VALUES (?1, ?2, ?3, ?4)
+            ON CONFLICT(name) DO UPDATE SET
+                path = excluded.path,
+                dependencies = excluded.dependencies,
+                current_version = excluded.current_version,
+                archive_sha256 = NULL,
+                download_url = NULL",
+            [name, path, &deps_json, &current_version.unwrap_or_default()],
         )?;
-        }
-
         Ok(())
     }
-    // Migrate installed mods from old database to new one
-    fn migrate_installed_mods(
-        old_conn: &Connection,
-        new_conn: &Connection,
-    ) -> Result<(), AppError> {
-        // Check if installed_mods table exists in old database
-        let has_installed_mods = match old_conn.query_row(
-            "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name='installed_mods'",
-            [],
-            |row| row.get::<_, i64>(0),
-        ) {
-            Ok(count) => count > 0,
-            Err(_) => false,
-        };
-
-        if !has_installed_mods {
-            return Ok(()); // No mods to migrate
-        }
 
-        // Get all installed mods
-        let mut stmt = match old_conn
-            .prepare("SELECT name, path, dependencies, current_version FROM installed_mods")
-        {
-            Ok(stmt) => stmt,
-            Err(_) => return Ok(()), // If query fails, just continue
-        };
+    /// Record the SHA-256 of the archive a mod was installed from.
+    pub fn set_mod_checksum(&self, name: &str, sha256: &str) -> Result<(), AppError> {
+        Self::set_mod_checksum_in(&*self.conn()?, name, sha256)
+    }
 
-        for (name, path, dependencies, current_version) in stmt
-            .query_map([], |row| {
-                Ok((
-                    row.get::<_, String>(0)?,
-                    row.get::<_, String>(1)?,
-                    row.get::<_, String>(2)?,
-                    row.get::<_, Option<String>>(3)?,
-                ))
-            })?
-            .flatten()
-        {
-            new_conn.execute(
-            "INSERT INTO installed_mods (name, path, dependencies, current_version) VALUES (?1, ?2, ?3, ?4)",
-            [&name, &path, &dependencies, &current_version.unwrap_or_default()],
+    pub fn set_mod_checksum_in(
+        conn: &Connection,
+        name: &str,
+        sha256: &str,
+    ) -> Result<(), AppError> {
+        conn.execute(
+            "UPDATE installed_mods SET archive_sha256 = ?1 WHERE name = ?2",
+            [sha256, name],
         )?;
-        }
-
         Ok(())
     }
 
-    fn initialize_database(conn: &Connection) -> Result<(), AppError> {
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS settings (
-                setting TEXT PRIMARY KEY,
-                value TEXT NOT NULL
-            )",
-            [],
-        )
-        .map_err(|e| AppError::DatabaseInit(e.to_string()))?;
-
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS installed_mods (
-                name TEXT PRIMARY KEY,
-                path TEXT NOT NULL,
-                dependencies TEXT NOT NULL DEFAULT '[]',
-                current_version TEXT
-            )",
-            [],
-        )
-        .map_err(|e| AppError::DatabaseInit(e.to_string()))?;
-
-        // Set the database version
-        conn.execute(
-            "INSERT OR REPLACE INTO settings (setting, value) VALUES ('db_version', ?1)",
-            [Self::CURRENT_DB_VERSION],
-        )
-        .map_err(|e| AppError::DatabaseInit(e.to_string()))?;
+    pub fn set_mod_download_url(&self, name: &str, url: &str) -> Result<(), AppError> {
+        Self::set_mod_download_url_in(&*self.conn()?, name, url)
+    }
 
+    pub fn set_mod_download_url_in(
+        conn: &Connection,
+        name: &str,
+        url: &str,
+    ) -> Result<(), AppError> {
         conn.execute(
-            "INSERT OR REPLACE INTO settings (setting, value) VALUES ('security_warning_acknowledged', ?1)",
-            ["no"],
-        )
-        .map_err(|e| AppError::DatabaseInit(e.to_string()))?;
-
+            "UPDATE installed_mods SET download_url = ?1 WHERE name = ?2",
+            [url, name],
+        )?;
         Ok(())
     }
 
-    pub fn get_mod_details(&self, mod_name: &str) -> Result<InstalledMod, AppError> {
-        let mut stmt = self.conn.prepare(
-            "SELECT name, path, dependencies, current_version FROM installed_mods WHERE name = ?1",
+    pub fn set_mod_pinned(&self, name: &str, pinned: bool) -> Result<(), AppError> {
+        let conn = self.conn()?;
+        let updated = conn.execute(
+            "UPDATE installed_mods SET pinned = ?1 WHERE name = ?2",
+            rusqlite::params![pinned, name],
         )?;
-
-        let mut rows = stmt.query([mod_name])?;
-
-        if let Some(row) = rows.next()? {
-            Ok(InstalledMod {
-                name: row.get(0)?,
-                path: row.get(1)?,
-                dependencies: serde_json::from_str(&row.get::<_, String>(2)?)?,
-                current_version: row.get(3)?,
-            })
-        } else {
-            Err(AppError::InvalidState(format!(
-                "Mod {} not found",
-                mod_name
-            )))
+        if updated == 0 {
+            return Err(AppError::InvalidState(format!("Mod {} not found", name)));
         }
-    }
-
-    pub fn set_discord_rpc_enabled(&self, enabled: bool) -> Result<(), AppError> {
-        let value = if enabled { "enabled" } else { "disabled" };
-        self.conn.execute(
-            "INSERT OR REPLACE INTO settings (setting, value) VALUES ('discord_rpc', ?1)",
-            [value],
-        )?;
         Ok(())
     }
 
-    pub fn is_discord_rpc_enabled(&self) -> Result<bool, AppError> {
-        let mut stmt = self
-            .conn
-            .prepare("SELECT value FROM settings WHERE setting = 'discord_rpc'")?;
-        let mut rows = stmt.query([])?;
+    /// Whether updates are held back for a mod. Unknown mods are not pinned.
+    pub fn is_mod_pinned(&self, name: &str) -> Result<bool, AppError> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare("SELECT pinned FROM installed_mods WHERE name = ?1")?;
+        let mut rows = stmt.query([name])?;
 
         if let Some(row) = rows.next()? {
-            Ok(row.get::<_, String>(0)? == "enabled")
+            Ok(row.get(0)?)
         } else {
-            // Default to enabled if setting doesn't exist yet
-            self.set_discord_rpc_enabled(true)?;
-            Ok(true)
+            Ok(false)
         }
     }
 
-    pub fn set_last_fetched(&self, timestamp: u64) -> Result<(), AppError> {
-        self.conn.execute(
-            "INSERT OR REPLACE INTO settings (setting, value) VALUES ('last_fetched', ?1)",
-            [timestamp.to_string()],
+    /// Remember the version a mod had before it was replaced, dropping the
+    /// oldest entries beyond the history limit.
+    pub fn push_version_history(
+        &self,
+        name: &str,
+        version: Option<&str>,
+        download_url: Option<&str>,
+        archive_sha256: Option<&str>,
+        installed_at: u64,
+    ) -> Result<(), AppError> {
+        Self::push_version_history_in(
+            &*self.conn()?,
+            name,
+            version,
+            download_url,
+            archive_sha256,
+            installed_at,
+        )
+    }
+
+    pub fn push_version_history_in(
+        conn: &Connection,
+        name: &str,
+        version: Option<&str>,
+        download_url: Option<&str>,
+        archive_sha256: Option<&str>,
+        installed_at: u64,
+    ) -> Result<(), AppError> {
+        conn.execute(
+            "INSERT INTO mod_version_history (mod_name, version, download_url, archive_sha256, installed_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+            rusqlite::params![name, version, download_url, archive_sha256, installed_at as i64],
+        )?;
+        conn.execute(
+            "DELETE FROM mod_version_history WHERE mod_name = ?1 AND id NOT IN (
+                SELECT id FROM mod_version_history WHERE mod_name = ?1 ORDER BY id DESC LIMIT ?2
+            )",
+            rusqlite::params![name, MAX_VERSION_HISTORY],
         )?;
         Ok(())
     }
 
-    pub fn get_last_fetched(&self) -> Result<u64, AppError> {
-        let mut stmt = self
-            .conn
-            .prepare("SELECT value FROM settings WHERE setting = 'last_fetched'")?;
-
-        let mut rows = stmt.query([])?;
-        if let Some(row) = rows.next()? {
-            let val: String = row.get(0)?;
-            val.parse()
-                .map_err(|_| AppError::InvalidState("Invalid timestamp format".to_string()))
-        } else {
-            Ok(0)
-        }
+    /// Previously installed versions of a mod, most recent first.
+    pub fn get_version_history(&self, name: &str) -> Result<Vec<VersionRecord>, AppError> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, version, download_url, archive_sha256, installed_at FROM mod_version_history WHERE mod_name = ?1 ORDER BY id DESC",
+        )?;
+        let records = stmt
+            .query_map([name], |row| {
+                Ok(VersionRecord {
+                    id: row.get(0)?,
+                    version: row.get(1)?,
+                    download_url: row.get(2)?,
+                    archive_sha256: row.get(3)?,
+                    installed_at: row.get::<_, i64>(4)? as u64,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(records)
     }
 
-    pub fn get_installed_mods(&self) -> Result<Vec<InstalledMod>, AppError> {
-        let mut stmt = self
-            .conn
-            .prepare("SELECT name, path, dependencies, current_version FROM installed_mods")?;
-        let mut mods = Vec::new();
-        let mut rows = stmt.query([])?;
-
-        while let Some(row) = rows.next()? {
-            mods.push(InstalledMod {
-                name: row.get(0)?,
-                path: row.get(1)?,
-                dependencies: serde_json::from_str(&row.get::<_, String>(2)?)?,
-                current_version: row.get(3)?,
-            });
-        }
-
-        Ok(mods)
+    pub fn remove_version_history_entry(&self, id: i64) -> Result<(), AppError> {
+        Self::remove_version_history_entry_in(&*self.conn()?, id)
     }
 
-    pub fn add_installed_mod(
-        &self,
-        name: &str,
-        path: &str,
-        dependencies: &[String],
-        current_version: Option<String>,
-    ) -> Result<(), AppError> {
-        let deps_json = serde_json::to_string(dependencies)?;
-        self.conn.execute(
-            "INSERT OR REPLACE INTO installed_mods (name, path, dependencies, current_version) VALUES (?1, ?2, ?3, ?4)",
-            [name, path, &deps_json, &current_version.unwrap_or_default()],
-        )?;
+    pub fn remove_version_history_entry_in(conn: &Connection, id: i64) -> Result<(), AppError> {
+        conn.execute("DELETE FROM mod_version_history WHERE id = ?1", [id])?;
         Ok(())
     }
 
     pub fn get_dependents(&self, mod_name: &str) -> Result<Vec<String>, AppError> {
-        let mut stmt = self.conn.prepare(
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare(
             "SELECT name FROM installed_mods
             WHERE EXISTS (
                 SELECT 1 FROM json_each(dependencies)
@@ -477,158 +398,251 @@ impl Database {
     }
 
     pub fn remove_installed_mod(&self, name: &str) -> Result<(), AppError> {
-        self.conn
-            .execute("DELETE FROM installed_mods WHERE name = ?1", [name])?;
+        let conn = self.conn()?;
+        conn.execute("DELETE FROM installed_mods WHERE name = ?1", [name])?;
+        conn.execute("DELETE FROM mod_manifests WHERE mod_name = ?1", [name])?;
+        conn.execute(
+            "DELETE FROM mod_version_history WHERE mod_name = ?1",
+            [name],
+        )?;
         Ok(())
     }
 
-    pub fn get_installation_path(&self) -> Result<Option<String>, AppError> {
-        let mut stmt = self
-            .conn
-            .prepare("SELECT value FROM settings WHERE setting = 'installation_path'")?;
-        let mut rows = stmt.query([])?;
-
-        if let Some(row) = rows.next()? {
-            Ok(Some(row.get(0)?))
-        } else {
-            Ok(None)
-        }
+    /// Replace the recorded file manifest of a mod.
+    pub fn set_mod_manifest(&self, name: &str, entries: &[ManifestEntry]) -> Result<(), AppError> {
+        self.transaction(|conn| Self::set_mod_manifest_in(conn, name, entries))
     }
 
-    pub fn set_installation_path(&self, path: &str) -> Result<(), AppError> {
-        self.conn.execute(
-            "INSERT OR REPLACE INTO settings (setting, value) VALUES (?1, ?2)",
-            ["installation_path", path],
+    pub fn set_mod_manifest_in(
+        conn: &Connection,
+        name: &str,
+        entries: &[ManifestEntry],
+    ) -> Result<(), AppError> {
+        conn.execute("DELETE FROM mod_manifests WHERE mod_name = ?1", [name])?;
+        let mut stmt = conn.prepare(
+            "INSERT INTO mod_manifests (mod_name, rel_path, size, sha256) VALUES (?1, ?2, ?3, ?4)",
         )?;
+        for entry in entries {
+            stmt.execute(rusqlite::params![
+                name,
+                entry.path,
+                entry.size as i64,
+                entry.sha256
+            ])?;
+        }
         Ok(())
     }
 
-    pub fn remove_installation_path(&self) -> Result<(), AppError> {
-        self.conn.execute(
-            "DELETE FROM settings WHERE setting = 'installation_path'",
-            [],
+    pub fn get_mod_manifest(&self, name: &str) -> Result<Vec<ManifestEntry>, AppError> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT rel_path, size, sha256 FROM mod_manifests WHERE mod_name = ?1 ORDER BY rel_path",
         )?;
-        Ok(())
+        let entries = stmt
+            .query_map([name], |row| {
+                Ok(ManifestEntry {
+                    path: row.get(0)?,
+                    size: row.get::<_, i64>(1)? as u64,
+                    sha256: row.get(2)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(entries)
+    }
+
+    /// Create or replace a profile.
+    pub fn save_profile(&self, profile: &Profile) -> Result<(), AppError> {
+        let conn = self.conn()?;
+        let created_at = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_secs() as i64;
+        let tx = conn
+            .unchecked_transaction()
+            .map_err(|e| AppError::DatabaseTransaction(e.to_string()))?;
+        tx.execute(
+            "INSERT OR IGNORE INTO profiles (name, created_at) VALUES (?1, ?2)",
+            rusqlite::params![profile.name, created_at],
+        )?;
+        tx.execute(
+            "DELETE FROM profile_mods WHERE profile_name = ?1",
+            [&profile.name],
+        )?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT OR REPLACE INTO profile_mods (profile_name, mod_name, enabled, version, position) VALUES (?1, ?2, ?3, ?4, ?5)",
+            )?;
+            for (position, entry) in profile.mods.iter().enumerate() {
+                stmt.execute(rusqlite::params![
+                    profile.name,
+                    entry.name,
+                    entry.enabled,
+                    entry.version,
+                    position as i64
+                ])?;
+            }
+        }
+        tx.commit()
+            .map_err(|e| AppError::DatabaseTransaction(e.to_string()))
     }
 
-    pub fn get_last_installed_version(&self, mod_name: &str) -> Result<String, AppError> {
-        let mut stmt = self
-            .conn
-            .prepare("SELECT current_version FROM installed_mods WHERE name = ?1")?;
-        let mut rows = stmt.query([mod_name])?;
-
-        if let Some(row) = rows.next()? {
-            Ok(row.get(0)?)
-        } else {
-            Ok(String::new())
+    pub fn get_profile(&self, name: &str) -> Result<Profile, AppError> {
+        let conn = self.conn()?;
+        let exists = conn.query_row(
+            "SELECT COUNT(*) FROM profiles WHERE name = ?1",
+            [name],
+            |row| row.get::<_, i64>(0),
+        )? > 0;
+        if !exists {
+            return Err(AppError::InvalidState(format!(
+                "Profile {} not found",
+                name
+            )));
         }
-    }
 
-    pub fn set_last_installed_version(
-        &self,
-        mod_name: &str,
-        version: &str,
-    ) -> Result<(), AppError> {
-        self.conn.execute(
-            "UPDATE installed_mods SET current_version = ?1 WHERE name = ?2",
-            [version, mod_name],
+        let mut stmt = conn.prepare(
+            "SELECT mod_name, enabled, version FROM profile_mods WHERE profile_name = ?1 ORDER BY position",
         )?;
+        let mods = stmt
+            .query_map([name], |row| {
+                Ok(ProfileMod {
+                    name: row.get(0)?,
+                    enabled: row.get(1)?,
+                    version: row.get(2)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Profile {
+            name: name.to_string(),
+            mods,
+        })
+    }
+
+    pub fn list_profiles(&self) -> Result<Vec<String>, AppError> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare("SELECT name FROM profiles ORDER BY name COLLATE NOCASE")?;
+        let names = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(names)
+    }
+
+    pub fn delete_profile(&self, name: &str) -> Result<(), AppError> {
+        let conn = self.conn()?;
+        conn.execute("DELETE FROM profile_mods WHERE profile_name = ?1", [name])?;
+        conn.execute("DELETE FROM profiles WHERE name = ?1", [name])?;
+        self.update_settings(|settings| {
+            if settings.active_profile.as_deref() == Some(name) {
+                settings.active_profile = None;
+            }
+            Ok(())
+        })?;
         Ok(())
     }
 
-    pub fn set_background_enabled(&self, enabled: bool) -> Result<(), AppError> {
-        let enabled: &str = if enabled { "enabled" } else { "disabled" };
-        self.conn.execute(
-            "INSERT OR REPLACE INTO settings (setting, value) VALUES ('background_enabled', ?1)",
-            [enabled],
-        )?;
-        Ok(())
+    pub fn save_collection(&self, collection: &ModCollection) -> Result<(), AppError> {
+        let conn = self.conn()?;
+        ModCollectionManager::save_collection(&conn, collection)
+            .map_err(|e| AppError::DatabaseTransaction(e.to_string()))
     }
 
-    pub fn get_background_enabled(&self) -> Result<bool, AppError> {
-        let mut stmt = self
-            .conn
-            .prepare("SELECT value FROM settings WHERE setting = 'background_enabled'")?;
-        let mut rows = stmt.query([])?;
-
-        if let Some(row) = rows.next()? {
-            Ok(row.get::<_, String>(0)? == "enabled")
-        } else {
-            Ok(false)
+    /// Store an imported collection without replacing an existing one. When
+    /// its id is already taken it gets a fresh one, which the returned
+    /// collection carries.
+    pub fn import_collection(
+        &self,
+        mut collection: ModCollection,
+    ) -> Result<ModCollection, AppError> {
+        let conn = self.conn()?;
+        if ModCollectionManager::get_collection(&conn, &collection.id)?.is_some() {
+            collection.id = ModCollection::new_id(&collection.name);
         }
+        ModCollectionManager::save_collection(&conn, &collection)
+            .map_err(|e| AppError::DatabaseTransaction(e.to_string()))?;
+        Ok(collection)
     }
 
-    fn enable_lovely_console(&self) -> Result<(), AppError> {
-        self.conn.execute(
-            "INSERT OR REPLACE INTO settings (setting, value) VALUES ('lovely_console', 'enabled')",
-            [],
-        )?;
-        Ok(())
+    pub fn get_collection(&self, id: &str) -> Result<ModCollection, AppError> {
+        let conn = self.conn()?;
+        ModCollectionManager::get_collection(&conn, id)?
+            .ok_or_else(|| AppError::InvalidState(format!("Collection {} not found", id)))
     }
 
-    fn disable_lovely_console(&self) -> Result<(), AppError> {
-        self.conn.execute(
-            "INSERT OR REPLACE INTO settings (setting, value) VALUES ('lovely_console', 'disabled')",
-            [],
-        )?;
-        Ok(())
+    pub fn list_collections(&self) -> Result<Vec<ModCollection>, AppError> {
+        let conn = self.conn()?;
+        Ok(ModCollectionManager::get_all_collections(&conn)?)
     }
 
-    pub fn set_lovely_console_status(&self, enabled: bool) -> Result<(), AppError> {
-        if enabled {
-            self.enable_lovely_console()
-        } else {
-            self.disable_lovely_console()
-        }
+    pub fn delete_collection(&self, id: &str) -> Result<(), AppError> {
+        let conn = self.conn()?;
+        Ok(ModCollectionManager::remove_collection(&conn, id)?)
     }
 
-    pub fn is_lovely_console_enabled(&self) -> Result<bool, AppError> {
-        let mut stmt = self
-            .conn
-            .prepare("SELECT value FROM settings WHERE setting = 'lovely_console'")?;
-        let mut rows = stmt.query([])?;
+    pub fn get_last_installed_version(&self, mod_name: &str) -> Result<String, AppError> {
+        let conn = self.conn()?;
+        let mut stmt =
+            conn.prepare("SELECT current_version FROM installed_mods WHERE name = ?1")?;
+        let mut rows = stmt.query([mod_name])?;
 
         if let Some(row) = rows.next()? {
-            Ok(row.get::<_, String>(0)? == "enabled")
+            Ok(row.get(0)?)
         } else {
-            Ok(false)
+            Ok(String::new())
         }
     }
 
-    pub fn set_security_warning_acknowledged(&self, acknowledged: bool) -> Result<(), AppError> {
-        let value = if acknowledged { "yes" } else { "no" };
-        self.conn.execute(
-        "INSERT OR REPLACE INTO settings (setting, value) VALUES ('security_warning_acknowledged', ?1)",
-        [value],
-    )?;
-        Ok(())
-    }
-
-    pub fn is_security_warning_acknowledged(&self) -> Result<bool, AppError> {
-        let mut stmt = self.conn.prepare(
-            "SELECT value FROM settings WHERE setting = 'security_warning_acknowledged'",
+    pub fn set_last_installed_version(
+        &self,
+        mod_name: &str,
+        version: &str,
+    ) -> Result<(), AppError> {
+        let conn = self.conn()?;
+        conn.execute(
+            "UPDATE installed_mods SET current_version = ?1 WHERE name = ?2",
+            [version, mod_name],
         )?;
-
-        let mut rows = stmt.query([])?;
-        if let Some(row) = rows.next()? {
-            Ok(row.get::<_, String>(0)? == "yes")
-        } else {
-            // Default to not acknowledged
-            Ok(false)
-        }
+        Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use rusqlite::Connection;
 
     fn create_memory_db() -> Result<Database, AppError> {
-        let conn =
-            Connection::open_in_memory().map_err(|e| AppError::DatabaseInit(e.to_string()))?;
-        Database::initialize_database(&conn)?;
-        Ok(Database { conn })
+        Database::open_in_memory()
+    }
+
+    #[test]
+    fn test_import_never_replaces_a_collection() -> Result<(), AppError> {
+        let db = create_memory_db()?;
+        let mut mine = ModCollection::new("Friday Run".into(), Vec::new());
+        db.save_collection(&mine)?;
+
+        mine.name = "Someone else's run".into();
+        let imported = db.import_collection(mine.clone())?;
+        assert_ne!(imported.id, mine.id);
+        assert_eq!(db.get_collection(&mine.id)?.name, "Friday Run");
+        assert_eq!(db.get_collection(&imported.id)?.name, "Someone else's run");
+        Ok(())
+    }
+
+    #[test]
+    fn test_transaction_is_all_or_nothing() -> Result<(), AppError> {
+        let db = create_memory_db()?;
+        db.add_installed_mod("Kept", "/kept", &[], Some("1.0.0".into()))?;
+
+        let result = db.transaction(|conn| {
+            Database::add_installed_mod_in(conn, "Kept", "/kept", &[], Some("2.0.0".into()))?;
+            Database::set_mod_checksum_in(conn, "Kept", &"ab".repeat(32))?;
+            Err::<(), _>(AppError::InvalidState("failed halfway".into()))
+        });
+        assert!(result.is_err());
+
+        let kept = db.get_mod_details("Kept")?;
+        assert_eq!(kept.current_version.as_deref(), Some("1.0.0"));
+        assert_eq!(kept.archive_sha256, None);
+        Ok(())
     }
 
     #[test]
@@ -658,13 +672,20 @@ mod tests {
     #[test]
     fn test_installation_path_management() -> Result<(), AppError> {
         let db = create_memory_db()?;
+        let game_dir = std::env::temp_dir().to_string_lossy().into_owned();
 
-        assert!(db.get_installation_path()?.is_none());
-        db.set_installation_path("/games/balatro")?;
-        assert_eq!(db.get_installation_path()?, Some("/games/balatro".into()));
+        assert!(db.settings()?.installation_path.is_none());
+        db.update_settings(|s| {
+            s.installation_path = Some(game_dir.clone());
+            Ok(())
+        })?;
+        assert_eq!(db.settings()?.installation_path, Some(game_dir));
 
-        db.remove_installation_path()?;
-        assert!(db.get_installation_path()?.is_none());
+        db.update_settings(|s| {
+            s.installation_path = None;
+            Ok(())
+        })?;
+        assert!(db.settings()?.installation_path.is_none());
 
         Ok(())
     }
@@ -680,7 +701,76 @@ mod tests {
         assert_eq!(details.name, "TestMod");
         assert_eq!(details.path, "/path/to/mod");
         assert_eq!(details.dependencies, deps);
+        assert!(details.archive_sha256.is_none());
+
+        db.set_mod_checksum("TestMod", "abc123")?;
+        let details = db.get_mod_details("TestMod")?;
+        assert_eq!(details.archive_sha256.as_deref(), Some("abc123"));
+
+        Ok(())
+    }
 
+    #[test]
+    fn test_pin_survives_reinstall_and_history_is_capped() -> Result<(), AppError> {
+        let db = create_memory_db()?;
+
+        db.add_installed_mod("TestMod", "/path/to/mod", &[], Some("1.0.0".into()))?;
+        db.set_mod_pinned("TestMod", true)?;
+        db.add_installed_mod("TestMod", "/path/to/mod", &[], Some("1.1.0".into()))?;
+        assert!(db.is_mod_pinned("TestMod")?);
+        assert!(!db.is_mod_pinned("Unknown")?);
+
+        for i in 0..(MAX_VERSION_HISTORY + 2) {
+            let version = format!("1.0.{}", i);
+            db.push_version_history("TestMod", Some(&version), None, None, i as u64)?;
+        }
+        let history = db.get_version_history("TestMod")?;
+        assert_eq!(history.len(), MAX_VERSION_HISTORY as usize);
+        let latest = format!("1.0.{}", MAX_VERSION_HISTORY + 1);
+        assert_eq!(history[0].version.as_deref(), Some(latest.as_str()));
+
+        db.remove_version_history_entry(history[0].id)?;
+        assert_eq!(db.get_version_history("TestMod")?.len(), history.len() - 1);
+
+        db.remove_installed_mod("TestMod")?;
+        assert!(db.get_version_history("TestMod")?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_profiles_round_trip() -> Result<(), AppError> {
+        let db = create_memory_db()?;
+        let profile = Profile {
+            name: "Vanilla+".to_string(),
+            mods: vec![
+                ProfileMod {
+                    name: "Steamodded".to_string(),
+                    enabled: true,
+                    version: Some("1.0.0~BETA-0301a".to_string()),
+                },
+                ProfileMod {
+                    name: "Cryptid".to_string(),
+                    enabled: false,
+                    version: None,
+                },
+            ],
+        };
+
+        db.save_profile(&profile)?;
+        db.update_settings(|s| {
+            s.active_profile = Some("Vanilla+".to_string());
+            Ok(())
+        })?;
+        let loaded = db.get_profile("Vanilla+")?;
+        assert_eq!(loaded.mods.len(), 2);
+        assert_eq!(loaded.mods[0].name, "Steamodded");
+        assert!(!loaded.mods[1].enabled);
+        assert_eq!(db.list_profiles()?, vec!["Vanilla+"]);
+
+        db.delete_profile("Vanilla+")?;
+        assert!(db.get_profile("Vanilla+").is_err());
+        assert!(db.settings()?.active_profile.is_none());
         Ok(())
     }
 }
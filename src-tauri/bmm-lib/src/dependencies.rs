@@ -21,13 +21,12 @@ fn get_tar_file_name(linux_native: bool) -> String {
     format!("{}.tar.gz", tar_file)
 }
 
-pub fn download_tar(tag: Option<String>, linux_native: bool) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+pub async fn download_tar(tag: Option<String>, linux_native: bool) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
     let url = match tag {
         Some(tag) => format!("https://github.com/balamod/balamod_lua/releases/download/{}/{}", tag, get_tar_file_name(linux_native)),
         None => format!("https://github.com/balamod/balamod_lua/releases/latest/download/{}", get_tar_file_name(linux_native))
     };
-    let response = reqwest::blocking::get(&url)?;
-    let body = response.bytes()?;
+    let body = crate::download::shared().get(&url).await?;
     Ok(body.to_vec())
 }
 
@@ -52,7 +51,7 @@ pub fn get_balalib_name(linux_native: bool) -> String {
     lib_file
 }
 
-pub fn download_balalib(tag: Option<String>, linux_native: bool) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+pub async fn download_balalib(tag: Option<String>, linux_native: bool) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
     let lib_file = get_balalib_name(linux_native);
 
     let url = match tag {
@@ -60,8 +59,7 @@ pub fn download_balalib(tag: Option<String>, linux_native: bool) -> Result<Vec<u
         None => format!("https://github.com/balamod/balalib/releases/latest/download/{}", lib_file)
     };
 
-    let response = reqwest::blocking::get(&url)?;
-    let body = response.bytes()?;
+    let body = crate::download::shared().get(&url).await?;
     Ok(body.to_vec())
 }
 
@@ -77,10 +75,9 @@ pub fn unpack_tar(dir: &str, tar: Vec<u8>, linux_native: bool) -> Result<(), Box
     Ok(())
 }
 
-pub fn download_patched_main() -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+pub async fn download_patched_main() -> Result<Vec<u8>, Box<dyn std::error::Error>> {
     let timestamp = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
     let url = format!("https://raw.githubusercontent.com/balamod/balamod_lua/main/main.patch.lua?t={}", timestamp); // cache buster
-    let response = reqwest::blocking::get(&url)?;
-    let body = response.bytes()?;
+    let body = crate::download::shared().get(&url).await?;
     Ok(body.to_vec())
 }
@@ -0,0 +1,316 @@
+use crate::errors::AppError;
+use crate::progress::{NoProgress, Progress, ProgressSink};
+use bytes::Bytes;
+use lazy_static::lazy_static;
+use reqwest::header::RANGE;
+use reqwest::StatusCode;
+use std::sync::RwLock;
+use std::time::Duration;
+
+pub const USER_AGENT: &str = "Balatro-Mod-Manager/1.0";
+
+lazy_static! {
+    static ref SHARED: Downloader = Downloader::new(DownloadConfig::default())
+        .expect("the default download configuration is valid");
+}
+
+/// The downloader every HTTP request goes through, so they share one
+/// connection pool, proxy and set of limits.
+pub fn shared() -> &'static Downloader {
+    &SHARED
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DownloadConfig {
+    /// Proxy for all requests, e.g. `http://proxy:3128`.
+    /// Without one, the system proxy (`HTTPS_PROXY` and friends) is used.
+    pub proxy: Option<String>,
+    pub connect_timeout: Duration,
+    /// How long a transfer may stall before it is abandoned.
+    pub read_timeout: Duration,
+    /// Attempts after the first one for failures that may go away.
+    pub retries: u32,
+    /// Wait before the first retry; doubled for each one after it.
+    pub retry_delay: Duration,
+    /// Largest body in bytes that will be downloaded.
+    pub max_size: u64,
+}
+
+impl Default for DownloadConfig {
+    fn default() -> Self {
+        Self {
+            proxy: None,
+            connect_timeout: Duration::from_secs(15),
+            read_timeout: Duration::from_secs(30),
+            retries: 3,
+            retry_delay: Duration::from_millis(500),
+            max_size: 1024 * 1024 * 1024,
+        }
+    }
+}
+
+fn build_client(config: &DownloadConfig) -> Result<reqwest::Client, AppError> {
+    let mut builder = reqwest::Client::builder()
+        .user_agent(USER_AGENT)
+        .connect_timeout(config.connect_timeout)
+        .read_timeout(config.read_timeout);
+    if let Some(proxy) = &config.proxy {
+        let proxy = reqwest::Proxy::all(proxy)
+            .map_err(|e| AppError::config_error("download_proxy", e.to_string()))?;
+        builder = builder.proxy(proxy);
+    }
+    Ok(builder.build()?)
+}
+
+// Whether trying the same request again might work
+enum Failure {
+    Transient(AppError),
+    Fatal(AppError),
+}
+
+fn network_error(url: &str, source: impl ToString) -> AppError {
+    AppError::NetworkRequest {
+        url: url.to_string(),
+        source: source.to_string(),
+    }
+}
+
+/// An HTTP client with timeouts, retries with exponential backoff, resuming
+/// of interrupted transfers, a size cap and byte progress.
+pub struct Downloader {
+    state: RwLock<(DownloadConfig, reqwest::Client)>,
+}
+
+impl Downloader {
+    pub fn new(config: DownloadConfig) -> Result<Self, AppError> {
+        let client = build_client(&config)?;
+        Ok(Self {
+            state: RwLock::new((config, client)),
+        })
+    }
+
+    pub fn config(&self) -> DownloadConfig {
+        self.current().0
+    }
+
+    /// Replace the configuration. Requests already running keep the old one.
+    pub fn configure(&self, config: DownloadConfig) -> Result<(), AppError> {
+        let client = build_client(&config)?;
+        let mut state = self
+            .state
+            .write()
+            .map_err(|_| AppError::LockPoisoned("Downloader lock poisoned".to_string()))?;
+        *state = (config, client);
+        Ok(())
+    }
+
+    /// Send everything through `proxy`, or through the system proxy when `None`.
+    pub fn set_proxy(&self, proxy: Option<String>) -> Result<(), AppError> {
+        self.configure(DownloadConfig {
+            proxy: proxy.filter(|p| !p.trim().is_empty()),
+            ..self.config()
+        })
+    }
+
+    /// The underlying client, for requests that need more than a plain GET.
+    pub fn client(&self) -> reqwest::Client {
+        self.current().1
+    }
+
+    fn current(&self) -> (DownloadConfig, reqwest::Client) {
+        match self.state.read() {
+            Ok(state) => state.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        }
+    }
+
+    /// Download `url` into memory.
+    pub async fn get(&self, url: &str) -> Result<Bytes, AppError> {
+        self.get_with_progress(url, &NoProgress).await
+    }
+
+    /// Download `url` into memory, reporting the bytes received so far to
+    /// `progress` and stopping when it is cancelled. Connection failures,
+    /// timeouts, 429 and 5xx answers are retried, and a transfer that broke
+    /// off resumes where it stopped if the server supports ranges.
+    pub async fn get_with_progress(
+        &self,
+        url: &str,
+        progress: &dyn ProgressSink,
+    ) -> Result<Bytes, AppError> {
+        let (config, client) = self.current();
+        let mut data = Vec::new();
+        let mut attempt = 0;
+        loop {
+            match fetch_into(&client, &config, url, &mut data, progress).await {
+                Ok(()) => return Ok(data.into()),
+                Err(Failure::Transient(e)) if attempt < config.retries => {
+                    let delay = config.retry_delay.saturating_mul(1u32 << attempt.min(16));
+                    attempt += 1;
+                    log::warn!(
+                        "Download of {} failed ({}), retry {} of {} in {:?}",
+                        url,
+                        e,
+                        attempt,
+                        config.retries,
+                        delay
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+                Err(Failure::Transient(e) | Failure::Fatal(e)) => return Err(e),
+            }
+        }
+    }
+}
+
+// One attempt at the rest of `url`, appending to whatever `data` already has
+async fn fetch_into(
+    client: &reqwest::Client,
+    config: &DownloadConfig,
+    url: &str,
+    data: &mut Vec<u8>,
+    progress: &dyn ProgressSink,
+) -> Result<(), Failure> {
+    let downloading = |received: usize, total| {
+        progress.report(Progress::Downloading {
+            received: received as u64,
+            total,
+        })
+    };
+
+    progress.check_cancelled().map_err(Failure::Fatal)?;
+    let mut request = client.get(url);
+    if !data.is_empty() {
+        request = request.header(RANGE, format!("bytes={}-", data.len()));
+    }
+    let mut response = request
+        .send()
+        .await
+        .map_err(|e| Failure::Transient(network_error(url, e)))?;
+
+    let status = response.status();
+    if status == StatusCode::PARTIAL_CONTENT && !data.is_empty() {
+        log::info!("Resuming download of {} at {} bytes", url, data.len());
+    } else if status.is_success() {
+        // The server ignored the range, so the body starts over
+        data.clear();
+    } else {
+        let error = network_error(url, format!("server returned {}", status));
+        return Err(
+            if status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS {
+                Failure::Transient(error)
+            } else {
+                Failure::Fatal(error)
+            },
+        );
+    }
+
+    let too_large = || {
+        Failure::Fatal(AppError::DownloadTooLarge {
+            url: url.to_string(),
+            limit: config.max_size,
+        })
+    };
+    let total = response
+        .content_length()
+        .map(|length| length + data.len() as u64);
+    if total.is_some_and(|total| total > config.max_size) {
+        return Err(too_large());
+    }
+
+    downloading(data.len(), total);
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| Failure::Transient(network_error(url, e)))?
+    {
+        if (data.len() + chunk.len()) as u64 > config.max_size {
+            return Err(too_large());
+        }
+        progress.check_cancelled().map_err(Failure::Fatal)?;
+        data.extend_from_slice(&chunk);
+        downloading(data.len(), total);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_config() -> DownloadConfig {
+        DownloadConfig {
+            retry_delay: Duration::from_millis(1),
+            max_size: 64,
+            ..Default::default()
+        }
+    }
+
+    #[derive(Default)]
+    struct Recorder(std::sync::Mutex<Vec<Progress>>);
+
+    impl ProgressSink for Recorder {
+        fn report(&self, progress: Progress) {
+            self.0.lock().expect("recorder lock").push(progress);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retries_server_errors_and_caps_size() -> Result<(), AppError> {
+        let mut server = mockito::Server::new_async().await;
+        let downloader = Downloader::new(test_config())?;
+
+        // The first attempt and three retries
+        let failing = server
+            .mock("GET", "/mod.zip")
+            .with_status(503)
+            .expect(4)
+            .create_async()
+            .await;
+        let url = format!("{}/mod.zip", server.url());
+        assert!(matches!(
+            downloader.get(&url).await,
+            Err(AppError::NetworkRequest { .. })
+        ));
+        failing.assert_async().await;
+        failing.remove_async().await;
+
+        server
+            .mock("GET", "/mod.zip")
+            .with_body("archive")
+            .create_async()
+            .await;
+        let recorder = Recorder::default();
+        let data = downloader.get_with_progress(&url, &recorder).await?;
+        assert_eq!(&data[..], b"archive");
+        assert_eq!(
+            recorder.0.lock().expect("recorder lock").last(),
+            Some(&Progress::Downloading {
+                received: 7,
+                total: Some(7)
+            })
+        );
+
+        let missing = server
+            .mock("GET", "/missing.zip")
+            .with_status(404)
+            .expect(1)
+            .create_async()
+            .await;
+        let url = format!("{}/missing.zip", server.url());
+        assert!(downloader.get(&url).await.is_err());
+        missing.assert_async().await;
+
+        server
+            .mock("GET", "/big.zip")
+            .with_body(vec![0u8; 65])
+            .create_async()
+            .await;
+        let url = format!("{}/big.zip", server.url());
+        assert!(matches!(
+            downloader.get(&url).await,
+            Err(AppError::DownloadTooLarge { limit: 64, .. })
+        ));
+        Ok(())
+    }
+}
@@ -66,8 +66,23 @@ pub enum AppError {
         url: String,
         source: String,
     },
-    ApiLimitExceeded,
+    /// GitHub's rate limit is used up until `reset` (Unix time).
+    ApiLimitExceeded {
+        reset: u64,
+    },
     InvalidApiResponse(String),
+    /// The user cancelled the operation through its progress sink.
+    Cancelled,
+    /// A download is bigger than the downloader's `limit` in bytes.
+    DownloadTooLarge {
+        url: String,
+        limit: u64,
+    },
+    ChecksumMismatch {
+        url: String,
+        expected: String,
+        actual: String,
+    },
 
     // Platform specific
     MacOsLibrary {
@@ -119,6 +134,14 @@ impl fmt::Display for AppError {
                 write!(f, "Failed to read file '{}': {}", path.display(), source)
             }
 
+            AppError::FileWrite { path, source } => {
+                write!(f, "Failed to write '{}': {}", path.display(), source)
+            }
+
+            AppError::DirNotFound(path) => write!(f, "Directory not found: {}", path.display()),
+
+            AppError::InvalidState(msg) => write!(f, "{}", msg),
+
             AppError::ModInstall { mod_name, source } => {
                 write!(f, "Failed to install mod '{}': {}", mod_name, source)
             }
@@ -127,6 +150,38 @@ impl fmt::Display for AppError {
                 write!(f, "Network request to '{}' failed: {}", url, source)
             }
 
+            AppError::Cancelled => write!(f, "Operation was cancelled"),
+
+            AppError::DownloadTooLarge { url, limit } => {
+                write!(
+                    f,
+                    "Download of '{}' is larger than the {} MB limit",
+                    url,
+                    limit / (1024 * 1024)
+                )
+            }
+
+            AppError::ApiLimitExceeded { reset } => {
+                let now = std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map_or(0, |d| d.as_secs());
+                write!(
+                    f,
+                    "GitHub API rate limit exceeded, try again in {} minutes or set a GitHub token",
+                    reset.saturating_sub(now).div_ceil(60)
+                )
+            }
+
+            AppError::ChecksumMismatch {
+                url,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "Checksum mismatch for '{}': expected {}, got {}",
+                url, expected, actual
+            ),
+
             AppError::MacOsLibrary { lib_name, source } => {
                 write!(f, "MacOS library '{}' error: {}", lib_name, source)
             }
@@ -135,6 +190,10 @@ impl fmt::Display for AppError {
                 write!(f, "Invalid path '{}': {}", path.display(), reason)
             }
 
+            AppError::InvalidConfig { key, value } => {
+                write!(f, "Invalid value for setting '{}': {}", key, value)
+            }
+
             // Handle all variants similarly
             _ => write!(f, "{:?}", self),
         }
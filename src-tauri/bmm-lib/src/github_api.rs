@@ -0,0 +1,333 @@
+use crate::cache;
+use crate::checksum::sha256_hex;
+use crate::download;
+use crate::errors::AppError;
+use lazy_static::lazy_static;
+use reqwest::header::{ACCEPT, AUTHORIZATION, ETAG, IF_NONE_MATCH};
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::{Mutex, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const API_ROOT: &str = "https://api.github.com";
+pub const JSON: &str = "application/vnd.github.v3+json";
+
+lazy_static! {
+    static ref SHARED: GitHubClient =
+        GitHubClient::new(cache::get_cache_dir().ok().map(|dir| dir.join("github")));
+}
+
+/// The client every GitHub API call goes through, so they share one token,
+/// one view of the rate limit and one response cache.
+pub fn shared() -> &'static GitHubClient {
+    &SHARED
+}
+
+/// The request quota as GitHub last reported it.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct RateLimit {
+    pub limit: u64,
+    pub remaining: u64,
+    /// Unix time the quota is refilled.
+    pub reset: u64,
+}
+
+/// Where the body of an [`ApiResponse`] came from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Freshness {
+    Fresh,
+    /// GitHub answered 304 to the cached ETag; the cached body is current.
+    NotModified,
+    /// The rate limit is used up, so the cached body was served unchecked.
+    Stale,
+}
+
+#[derive(Debug, Clone)]
+pub struct ApiResponse {
+    pub status: StatusCode,
+    pub body: String,
+    pub freshness: Freshness,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedResponse {
+    etag: Option<String>,
+    body: String,
+}
+
+/// A GitHub REST client that authenticates with the user's token when there
+/// is one, keeps track of `X-RateLimit-*` headers and caches successful
+/// responses on disk. Cached responses are revalidated with `If-None-Match`,
+/// which doesn't use up the quota, and served as they are while the quota
+/// is exhausted.
+pub struct GitHubClient {
+    api_root: String,
+    token: RwLock<Option<String>>,
+    rate_limit: Mutex<Option<RateLimit>>,
+    cache_dir: Option<PathBuf>,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+fn env_token() -> Option<String> {
+    std::env::var("GITHUB_TOKEN")
+        .ok()
+        .filter(|token| !token.trim().is_empty())
+}
+
+fn header_u64(response: &reqwest::Response, name: &str) -> Option<u64> {
+    response.headers().get(name)?.to_str().ok()?.parse().ok()
+}
+
+impl GitHubClient {
+    /// A client caching responses in `cache_dir`, or nowhere when `None`.
+    /// It starts with the `GITHUB_TOKEN` environment variable as its token.
+    pub fn new(cache_dir: Option<PathBuf>) -> Self {
+        Self {
+            api_root: API_ROOT.to_string(),
+            token: RwLock::new(env_token()),
+            rate_limit: Mutex::new(None),
+            cache_dir,
+        }
+    }
+
+    pub fn with_api_root(mut self, api_root: impl Into<String>) -> Self {
+        self.api_root = api_root.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Use `token` for every request from now on. Without one, requests fall
+    /// back to `GITHUB_TOKEN` and then to unauthenticated access.
+    pub fn set_token(&self, token: Option<String>) {
+        let token = token
+            .map(|t| t.trim().to_string())
+            .filter(|t| !t.is_empty())
+            .or_else(env_token);
+        if let Ok(mut current) = self.token.write() {
+            *current = token;
+        }
+    }
+
+    pub fn has_token(&self) -> bool {
+        self.token.read().is_ok_and(|token| token.is_some())
+    }
+
+    pub fn rate_limit(&self) -> Option<RateLimit> {
+        self.rate_limit.lock().ok().and_then(|limit| *limit)
+    }
+
+    // Reset time while the quota is known to be used up
+    fn exhausted_until(&self) -> Option<u64> {
+        self.rate_limit()
+            .filter(|limit| limit.remaining == 0 && limit.reset > now())
+            .map(|limit| limit.reset)
+    }
+
+    fn record_rate_limit(&self, response: &reqwest::Response) {
+        let (Some(limit), Some(remaining), Some(reset)) = (
+            header_u64(response, "x-ratelimit-limit"),
+            header_u64(response, "x-ratelimit-remaining"),
+            header_u64(response, "x-ratelimit-reset"),
+        ) else {
+            return;
+        };
+        if let Ok(mut current) = self.rate_limit.lock() {
+            *current = Some(RateLimit {
+                limit,
+                remaining,
+                reset,
+            });
+        }
+    }
+
+    fn cache_path(&self, url: &str, accept: &str) -> Option<PathBuf> {
+        let key = sha256_hex(format!("{} {}", accept, url).as_bytes());
+        Some(self.cache_dir.as_ref()?.join(format!("{}.json", key)))
+    }
+
+    fn read_cache(&self, url: &str, accept: &str) -> Option<CachedResponse> {
+        let data = std::fs::read(self.cache_path(url, accept)?).ok()?;
+        serde_json::from_slice(&data).ok()
+    }
+
+    fn write_cache(&self, url: &str, accept: &str, cached: &CachedResponse) {
+        let Some(path) = self.cache_path(url, accept) else {
+            return;
+        };
+        let written = path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| std::fs::write(&path, serde_json::to_vec(cached)?));
+        if let Err(e) = written {
+            log::warn!("Failed to cache GitHub response for {}: {}", url, e);
+        }
+    }
+
+    /// `GET` an API path such as `repos/owner/name/releases`, asking for
+    /// `accept`. Error statuses other than the rate limit are returned as
+    /// they are; an exhausted rate limit without a cached copy is
+    /// [`AppError::ApiLimitExceeded`].
+    pub async fn get(&self, path: &str, accept: &str) -> Result<ApiResponse, AppError> {
+        let url = format!("{}/{}", self.api_root, path.trim_start_matches('/'));
+        let cached = self.read_cache(&url, accept);
+
+        if let Some(reset) = self.exhausted_until() {
+            return match cached {
+                Some(cached) => Ok(ApiResponse {
+                    status: StatusCode::OK,
+                    body: cached.body,
+                    freshness: Freshness::Stale,
+                }),
+                None => Err(AppError::ApiLimitExceeded { reset }),
+            };
+        }
+
+        // Shares the downloader's proxy and timeouts
+        let mut request = download::shared().client().get(&url).header(ACCEPT, accept);
+        let token = self.token.read().ok().and_then(|token| token.clone());
+        if let Some(token) = token {
+            request = request.header(AUTHORIZATION, format!("Bearer {}", token));
+        }
+        if let Some(etag) = cached.as_ref().and_then(|c| c.etag.as_deref()) {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        let response = request.send().await?;
+        self.record_rate_limit(&response);
+
+        let status = response.status();
+        if status == StatusCode::FORBIDDEN || status == StatusCode::TOO_MANY_REQUESTS {
+            if let Some(reset) = self.exhausted_until() {
+                log::warn!("GitHub rate limit reached, it resets at {}", reset);
+                return match cached {
+                    Some(cached) => Ok(ApiResponse {
+                        status: StatusCode::OK,
+                        body: cached.body,
+                        freshness: Freshness::Stale,
+                    }),
+                    None => Err(AppError::ApiLimitExceeded { reset }),
+                };
+            }
+        }
+
+        if status == StatusCode::NOT_MODIFIED {
+            if let Some(cached) = cached {
+                return Ok(ApiResponse {
+                    status: StatusCode::OK,
+                    body: cached.body,
+                    freshness: Freshness::NotModified,
+                });
+            }
+        }
+
+        let etag = response
+            .headers()
+            .get(ETAG)
+            .and_then(|e| e.to_str().ok())
+            .map(str::to_string);
+        let body = response.text().await?;
+        if status.is_success() {
+            self.write_cache(
+                &url,
+                accept,
+                &CachedResponse {
+                    etag,
+                    body: body.clone(),
+                },
+            );
+        }
+        Ok(ApiResponse {
+            status,
+            body,
+            freshness: Freshness::Fresh,
+        })
+    }
+
+    /// `GET` an API path and decode the JSON answer. `None` on 404.
+    pub async fn get_json<T: DeserializeOwned>(&self, path: &str) -> Result<Option<T>, AppError> {
+        let response = self.get(path, JSON).await?;
+        if response.status == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !response.status.is_success() {
+            return Err(AppError::NetworkRequest {
+                url: format!("{}/{}", self.api_root, path.trim_start_matches('/')),
+                source: format!("GitHub returned {}: {}", response.status, response.body),
+            });
+        }
+        serde_json::from_str(&response.body)
+            .map(Some)
+            .map_err(|e| AppError::InvalidApiResponse(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::Matcher;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_revalidates_and_falls_back_to_cache_when_limited() -> Result<(), AppError> {
+        let mut server = mockito::Server::new_async().await;
+        let cache_dir = tempdir()?;
+        let client =
+            GitHubClient::new(Some(cache_dir.path().to_path_buf())).with_api_root(server.url());
+        let reset = now() + 600;
+
+        let first = server
+            .mock("GET", "/repos/owner/mod/releases/latest")
+            .match_header("if-none-match", Matcher::Missing)
+            .with_header("etag", "\"v1\"")
+            .with_header("x-ratelimit-limit", "60")
+            .with_header("x-ratelimit-remaining", "59")
+            .with_header("x-ratelimit-reset", &reset.to_string())
+            .with_body(r#"{"tag_name":"v1"}"#)
+            .create_async()
+            .await;
+        let revalidated = server
+            .mock("GET", "/repos/owner/mod/releases/latest")
+            .match_header("if-none-match", "\"v1\"")
+            .with_status(304)
+            .expect(1)
+            .create_async()
+            .await;
+        let limited = server
+            .mock("GET", "/repos/owner/other/releases/latest")
+            .with_status(403)
+            .with_header("x-ratelimit-limit", "60")
+            .with_header("x-ratelimit-remaining", "0")
+            .with_header("x-ratelimit-reset", &reset.to_string())
+            .create_async()
+            .await;
+
+        let path = "repos/owner/mod/releases/latest";
+        let response = client.get(path, JSON).await?;
+        assert_eq!(response.freshness, Freshness::Fresh);
+        assert_eq!(client.rate_limit().map(|l| l.remaining), Some(59));
+
+        let response = client.get(path, JSON).await?;
+        assert_eq!(response.freshness, Freshness::NotModified);
+        assert_eq!(response.body, r#"{"tag_name":"v1"}"#);
+
+        let error = client
+            .get("repos/owner/other/releases/latest", JSON)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, AppError::ApiLimitExceeded { reset: r } if r == reset));
+
+        // With the quota gone, GitHub isn't asked again
+        let response = client.get(path, JSON).await?;
+        assert_eq!(response.freshness, Freshness::Stale);
+
+        first.assert_async().await;
+        revalidated.assert_async().await;
+        limited.assert_async().await;
+        Ok(())
+    }
+}
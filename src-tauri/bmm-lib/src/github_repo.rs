@@ -0,0 +1,588 @@
+// use chrono::DateTime;
+// use serde::Deserialize;
+use crate::checksum::sha256_file;
+use crate::download;
+use crate::github_api::{self, Freshness};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+//
+
+// const CURRENT_BRANCH: &str = "main";
+const CURRENT_BRANCH: &str = "main";
+
+const GIT_INFO: &str = ".git_info";
+
+/// What a clone or pull changed in the local copy of a repository.
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct RepoSyncSummary {
+    /// Commit the local copy now matches, when GitHub told us.
+    pub sha: Option<String>,
+    /// The recorded commit was already the latest, so nothing was downloaded.
+    pub up_to_date: bool,
+    pub added: usize,
+    pub changed: usize,
+    pub removed: usize,
+}
+
+/// Contents of the `.git_info` file kept next to a downloaded repository:
+/// the URL on the first line, then `key=value` lines.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GitInfo {
+    pub url: String,
+    pub branch: String,
+    pub sha: Option<String>,
+}
+
+impl GitInfo {
+    pub fn read(repo_path: &Path) -> Result<Self, String> {
+        let content = std::fs::read_to_string(repo_path.join(GIT_INFO))
+            .map_err(|e| format!("Failed to read repository info: {}", e))?;
+        let mut lines = content.lines();
+        let url = lines
+            .next()
+            .map(str::trim)
+            .filter(|url| !url.is_empty())
+            .ok_or_else(|| "Repository info has no URL".to_string())?
+            .to_string();
+
+        let mut info = GitInfo {
+            url,
+            branch: "main".to_string(),
+            sha: None,
+        };
+        for line in lines {
+            match line.split_once('=') {
+                Some(("branch", branch)) => info.branch = branch.trim().to_string(),
+                Some(("sha", sha)) => info.sha = Some(sha.trim().to_string()),
+                _ => {}
+            }
+        }
+        Ok(info)
+    }
+
+    pub fn write(&self, repo_path: &Path) -> Result<(), String> {
+        let mut content = format!("{}\nbranch={}", self.url, self.branch);
+        if let Some(sha) = &self.sha {
+            content.push_str(&format!("\nsha={}", sha));
+        }
+        std::fs::write(repo_path.join(GIT_INFO), content)
+            .map_err(|e| format!("Failed to write repository info: {}", e))
+    }
+}
+
+// Helper function to extract repo owner and name from URL
+pub fn parse_github_url(url: &str) -> Option<(String, String)> {
+    let url = url.trim_end_matches(".git");
+
+    // Match patterns like:
+    // https://github.com/owner/repo
+    // git@github.com:owner/repo
+    if url.contains("github.com") {
+        let parts: Vec<&str> = if url.contains("github.com/") {
+            url.split("github.com/").collect()
+        } else if url.contains("github.com:") {
+            url.split("github.com:").collect()
+        } else {
+            return None;
+        };
+
+        if parts.len() < 2 {
+            return None;
+        }
+
+        let repo_parts: Vec<&str> = parts[1].split('/').collect();
+        if repo_parts.len() < 2 {
+            return None;
+        }
+
+        return Some((repo_parts[0].to_string(), repo_parts[1].to_string()));
+    }
+
+    None
+}
+
+// The commit a branch points at. None when GitHub can't be asked, e.g. when
+// rate limited; the caller then downloads the branch as it is.
+async fn remote_head(owner: &str, repo: &str, branch: &str) -> Option<String> {
+    let path = format!("repos/{}/{}/commits/{}", owner, repo, branch);
+    let response = match github_api::shared()
+        .get(&path, "application/vnd.github.sha")
+        .await
+    {
+        Ok(response) if response.status.is_success() => response,
+        Ok(response) => {
+            log::warn!(
+                "Could not look up the head of {}/{}@{}: {}",
+                owner,
+                repo,
+                branch,
+                response.status
+            );
+            return None;
+        }
+        Err(e) => {
+            log::warn!(
+                "Could not look up the head of {}/{}@{}: {}",
+                owner,
+                repo,
+                branch,
+                e
+            );
+            return None;
+        }
+    };
+    // A head served from the cache while rate limited may be out of date
+    if response.freshness == Freshness::Stale {
+        return None;
+    }
+    let sha = response.body.trim().to_string();
+    Some(sha).filter(|sha| sha.len() == 40 && sha.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+async fn download_snapshot(owner: &str, repo: &str, reference: &str) -> Result<Vec<u8>, String> {
+    // A commit SHA pins the exact tree; a branch name gets whatever is there now
+    let download_url = if reference.len() == 40 {
+        format!(
+            "https://github.com/{}/{}/archive/{}.zip",
+            owner, repo, reference
+        )
+    } else {
+        format!(
+            "https://github.com/{}/{}/archive/refs/heads/{}.zip",
+            owner, repo, reference
+        )
+    };
+    log::info!("Downloading from URL: {}", download_url);
+
+    let bytes = download::shared()
+        .get(&download_url)
+        .await
+        .map_err(|e| format!("Failed to download repository from {}: {}", download_url, e))?;
+    log::info!("Downloaded {} bytes", bytes.len());
+    Ok(bytes.to_vec())
+}
+
+// Unpack an archive into `target`. When every entry is nested in one folder,
+// like the `<repo>-<ref>/` folder of a GitHub archive, that folder is dropped.
+fn extract_snapshot(bytes: &[u8], target: &Path) -> Result<(), String> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(bytes)).map_err(|e| {
+        format!(
+            "Failed to parse zip file: {}. This might mean the downloaded file is not a valid zip archive.",
+            e
+        )
+    })?;
+
+    let mut top_level = std::collections::HashSet::new();
+    let mut nested = true;
+    for name in archive.file_names() {
+        let mut components = Path::new(name).components();
+        top_level.insert(components.next().map(|c| c.as_os_str().to_os_string()));
+        // A file at the root means there is no enclosing folder
+        nested &= components.next().is_some() || name.ends_with('/');
+    }
+    let skip = usize::from(nested && top_level.len() == 1);
+
+    for i in 0..archive.len() {
+        let mut file = archive
+            .by_index(i)
+            .map_err(|e| format!("Failed to access file in zip: {}", e))?;
+
+        let Some(enclosed) = file.enclosed_name() else {
+            continue;
+        };
+        let rel_path: PathBuf = enclosed.components().skip(skip).collect();
+        if rel_path.as_os_str().is_empty() {
+            continue;
+        }
+        let out = target.join(&rel_path);
+
+        if file.is_dir() {
+            std::fs::create_dir_all(&out)
+                .map_err(|e| format!("Failed to create directory: {}", e))?;
+            continue;
+        }
+        if let Some(parent) = out.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create directory: {}", e))?;
+        }
+        let mut outfile =
+            std::fs::File::create(&out).map_err(|e| format!("Failed to create file: {}", e))?;
+        std::io::copy(&mut file, &mut outfile)
+            .map_err(|e| format!("Failed to write file: {}", e))?;
+    }
+    Ok(())
+}
+
+// Hash of every file under `dir` except `.git_info`, keyed by relative path
+fn tree_hashes(dir: &Path) -> Result<HashMap<PathBuf, String>, String> {
+    let mut hashes = HashMap::new();
+    if !dir.exists() {
+        return Ok(hashes);
+    }
+    for entry in WalkDir::new(dir) {
+        let entry = entry.map_err(|e| format!("Failed to read directory: {}", e))?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let rel_path = entry
+            .path()
+            .strip_prefix(dir)
+            .map_err(|e| e.to_string())?
+            .to_path_buf();
+        if rel_path == Path::new(GIT_INFO) {
+            continue;
+        }
+        let hash = sha256_file(entry.path()).map_err(|e| e.to_string())?;
+        hashes.insert(rel_path, hash);
+    }
+    Ok(hashes)
+}
+
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+fn remove_dir_if_exists(path: &Path) -> Result<(), String> {
+    if path.exists() {
+        std::fs::remove_dir_all(path)
+            .map_err(|e| format!("Failed to remove {}: {}", path.display(), e))?;
+    }
+    Ok(())
+}
+
+// Replace `target` with the fully extracted `staging` tree. The old tree is
+// only deleted once the new one is in place, and put back if that fails.
+fn swap_in(staging: &Path, target: &Path, summary: &mut RepoSyncSummary) -> Result<(), String> {
+    let old = tree_hashes(target)?;
+    let new = tree_hashes(staging)?;
+    for (path, hash) in &new {
+        match old.get(path) {
+            None => summary.added += 1,
+            Some(previous) if previous != hash => summary.changed += 1,
+            Some(_) => {}
+        }
+    }
+    summary.removed = old.keys().filter(|path| !new.contains_key(*path)).count();
+
+    if target.exists() && summary.added + summary.changed + summary.removed == 0 {
+        // Same files, only the recorded commit moves on
+        if staging.join(GIT_INFO).exists() {
+            std::fs::copy(staging.join(GIT_INFO), target.join(GIT_INFO))
+                .map_err(|e| format!("Failed to write repository info: {}", e))?;
+        }
+        return remove_dir_if_exists(staging);
+    }
+
+    let backup = sibling(target, ".old");
+    remove_dir_if_exists(&backup)?;
+    if target.exists() {
+        std::fs::rename(target, &backup)
+            .map_err(|e| format!("Failed to move the old copy aside: {}", e))?;
+    }
+    if let Err(e) = std::fs::rename(staging, target) {
+        if backup.exists() {
+            std::fs::rename(&backup, target).ok();
+        }
+        return Err(format!("Failed to move the new copy into place: {}", e));
+    }
+    remove_dir_if_exists(&backup)
+}
+
+/// Download `branch` of `url` into a staging directory next to `path` and
+/// swap it in once it is complete. `path` is left untouched if anything
+/// fails along the way.
+pub async fn sync_repository(
+    url: &str,
+    path: &str,
+    branch: &str,
+) -> Result<RepoSyncSummary, String> {
+    let (owner, repo) =
+        parse_github_url(url).ok_or_else(|| "Invalid GitHub URL format".to_string())?;
+    let target = PathBuf::from(path);
+    let sha = remote_head(&owner, &repo, branch).await;
+    let bytes = download_snapshot(&owner, &repo, sha.as_deref().unwrap_or(branch)).await?;
+
+    let staging = sibling(&target, ".staging");
+    remove_dir_if_exists(&staging)?;
+    std::fs::create_dir_all(&staging).map_err(|e| format!("Failed to create directory: {}", e))?;
+
+    let mut summary = RepoSyncSummary {
+        sha: sha.clone(),
+        ..Default::default()
+    };
+    let staged = extract_snapshot(&bytes, &staging).and_then(|_| {
+        GitInfo {
+            url: url.to_string(),
+            branch: branch.to_string(),
+            sha,
+        }
+        .write(&staging)
+    });
+    if let Err(e) = staged.and_then(|_| swap_in(&staging, &target, &mut summary)) {
+        remove_dir_if_exists(&staging).ok();
+        return Err(e);
+    }
+
+    log::info!(
+        "Synced {} ({}): {} added, {} changed, {} removed",
+        url,
+        branch,
+        summary.added,
+        summary.changed,
+        summary.removed
+    );
+    Ok(summary)
+}
+
+/// Download a zip of a repository from any host and swap it in like
+/// [`sync_repository`]. There is no commit to compare against, so the
+/// archive is fetched every time; only changed files count as changes.
+pub async fn sync_archive(url: &str, path: &str) -> Result<RepoSyncSummary, String> {
+    let bytes = download::shared()
+        .get(url)
+        .await
+        .map_err(|e| format!("Failed to download {}: {}", url, e))?;
+
+    let target = PathBuf::from(path);
+    let staging = sibling(&target, ".staging");
+    remove_dir_if_exists(&staging)?;
+    std::fs::create_dir_all(&staging).map_err(|e| format!("Failed to create directory: {}", e))?;
+
+    let mut summary = RepoSyncSummary::default();
+    let staged = extract_snapshot(&bytes, &staging);
+    if let Err(e) = staged.and_then(|_| swap_in(&staging, &target, &mut summary)) {
+        remove_dir_if_exists(&staging).ok();
+        return Err(e);
+    }
+    summary.up_to_date = summary.added + summary.changed + summary.removed == 0;
+
+    log::info!(
+        "Synced {}: {} added, {} changed, {} removed",
+        url,
+        summary.added,
+        summary.changed,
+        summary.removed
+    );
+    Ok(summary)
+}
+
+// Clone a repository from GitHub
+pub async fn clone_repository(url: &str, path: &str) -> Result<RepoSyncSummary, String> {
+    // Determine which branch to use based on the repository
+    if url.contains("skyline69/balatro-mod-index") {
+        return sync_repository(url, path, CURRENT_BRANCH).await;
+    }
+
+    // Default to "main", falling back to "master" for older repositories
+    match sync_repository(url, path, "main").await {
+        Ok(summary) => Ok(summary),
+        Err(e) => {
+            log::info!("Cloning main failed ({}), trying master", e);
+            sync_repository(url, path, "master").await
+        }
+    }
+}
+
+pub fn is_repository_directory(path: &str) -> bool {
+    let repo_path = PathBuf::from(path);
+    let git_info_path = repo_path.join(GIT_INFO);
+    git_info_path.exists()
+}
+
+// Get mod timestamps from the repository
+// pub async fn get_mod_timestamps(repo_path: &str) -> Result<HashMap<String, i64>, String> {
+//     let client = reqwest::Client::new();
+//     let repo_path = PathBuf::from(repo_path);
+//
+//     // Read repo URL from .git_info file
+//     let git_info_path = repo_path.join(".git_info");
+//     let url = std::fs::read_to_string(git_info_path)
+//         .map_err(|e| format!("Failed to read repository info: {}", e))?;
+//
+//     let (owner, repo) =
+//         parse_github_url(&url).ok_or_else(|| "Invalid GitHub URL format".to_string())?;
+//
+//     // Get commits for the repository
+//     let commits_url = format!("https://api.github.com/repos/{}/{}/commits", owner, repo);
+//
+//     let response = client
+//         .get(&commits_url)
+//         .header("User-Agent", "balatro-mod-manager")
+//         .send()
+//         .await
+//         .map_err(|e| format!("Failed to fetch commits: {}", e))?;
+//
+//     if !response.status().is_success() {
+//         return Err(format!(
+//             "GitHub API error: {} - {}",
+//             response.status().as_u16(),
+//             response.text().await.unwrap_or_default()
+//         ));
+//     }
+//
+//     let commits: Vec<GitHubCommit> = response
+//         .json()
+//         .await
+//         .map_err(|e| format!("Failed to parse commits: {}", e))?;
+//
+//     let mut timestamps = HashMap::new();
+//
+//     for commit in commits {
+//         // Parse commit date to Unix timestamp
+//         let date = DateTime::parse_from_rfc3339(&commit.commit.author.date)
+//             .map_err(|e| format!("Failed to parse date: {}", e))?;
+//         let timestamp = date.timestamp();
+//
+//         // Get the files included in this commit
+//         let commit_detail_url = format!(
+//             "https://api.github.com/repos/{}/{}/commits/{}",
+//             owner, repo, commit.sha
+//         );
+//         let detail_response = client
+//             .get(&commit_detail_url)
+//             .header("User-Agent", "balatro-mod-manager")
+//             .send()
+//             .await
+//             .map_err(|e| format!("Failed to fetch commit details: {}", e))?;
+//
+//         if !detail_response.status().is_success() {
+//             continue; // Skip this commit if we can't get details
+//         }
+//
+//         let commit_detail: GitHubCommit = match detail_response.json().await {
+//             Ok(detail) => detail,
+//             Err(_) => continue, // Skip if we can't parse
+//         };
+//
+//         // Process files in this commit
+//         for file in commit_detail.files {
+//             if file.filename.starts_with("mods/") {
+//                 let parts: Vec<&str> = file
+//                     .filename
+//                     .trim_start_matches("mods/")
+//                     .split('/')
+//                     .collect();
+//                 if let Some(mod_name) = parts.first() {
+//                     timestamps
+//                         .entry(mod_name.to_string())
+//                         .and_modify(|e| {
+//                             if timestamp > *e {
+//                                 *e = timestamp
+//                             }
+//                         })
+//                         .or_insert(timestamp);
+//                 }
+//             }
+//         }
+//     }
+//
+//     Ok(timestamps)
+// }
+//
+/// Bring a cloned repository up to date. Nothing is downloaded when the
+/// commit recorded in `.git_info` is still the head of its branch.
+pub async fn pull_repository(path: &str) -> Result<RepoSyncSummary, String> {
+    let repo_path = PathBuf::from(path);
+
+    // Check if .git_info exists
+    if !repo_path.join(GIT_INFO).exists() {
+        return Err(format!(
+            "Directory at '{}' is not a valid repository. Please clone it first.",
+            path
+        ));
+    }
+
+    let info = GitInfo::read(&repo_path)?;
+    log::info!("URL from git_info: {}", info.url);
+
+    // For balatro-mod-index, always use the specific branch regardless of what's in .git_info
+    let branch = if info.url.contains("skyline69/balatro-mod-index") {
+        CURRENT_BRANCH
+    } else {
+        info.branch.as_str()
+    };
+
+    if let (Some(recorded), Some((owner, repo))) = (&info.sha, parse_github_url(&info.url)) {
+        if remote_head(&owner, &repo, branch).await.as_ref() == Some(recorded) {
+            log::info!("{} is already at {}", info.url, recorded);
+            return Ok(RepoSyncSummary {
+                sha: Some(recorded.clone()),
+                up_to_date: true,
+                ..Default::default()
+            });
+        }
+    }
+
+    sync_repository(&info.url, path, branch).await
+}
+
+pub async fn clone_repository_with_branch(
+    url: &str,
+    path: &str,
+    branch: &str,
+) -> Result<RepoSyncSummary, String> {
+    sync_repository(url, path, branch).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_swap_in_replaces_the_tree_and_counts_changes() -> Result<(), String> {
+        let temp = tempdir().map_err(|e| e.to_string())?;
+        let target = temp.path().join("mod_index");
+        let staging = sibling(&target, ".staging");
+        let write = |dir: &Path, file: &str, content: &str| {
+            let path = dir.join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        };
+
+        write(&target, "mods/A/meta.json", "a");
+        write(&target, "mods/B/meta.json", "b");
+        GitInfo {
+            url: "https://github.com/owner/index".into(),
+            branch: "main".into(),
+            sha: None,
+        }
+        .write(&target)?;
+
+        write(&staging, "mods/A/meta.json", "a");
+        write(&staging, "mods/B/meta.json", "b2");
+        write(&staging, "mods/C/meta.json", "c");
+        let info = GitInfo {
+            url: "https://github.com/owner/index".into(),
+            branch: "main".into(),
+            sha: Some("0123456789abcdef0123456789abcdef01234567".into()),
+        };
+        info.write(&staging)?;
+
+        let mut summary = RepoSyncSummary::default();
+        swap_in(&staging, &target, &mut summary)?;
+        assert_eq!((summary.added, summary.changed, summary.removed), (1, 1, 0));
+        assert!(!staging.exists());
+        assert!(!sibling(&target, ".old").exists());
+        assert_eq!(
+            std::fs::read_to_string(target.join("mods/B/meta.json")).unwrap(),
+            "b2"
+        );
+        assert_eq!(GitInfo::read(&target)?, info);
+
+        // Legacy files without a recorded commit still parse
+        std::fs::write(
+            target.join(GIT_INFO),
+            "https://github.com/owner/index\nbranch=dev",
+        )
+        .unwrap();
+        let legacy = GitInfo::read(&target)?;
+        assert_eq!((legacy.branch.as_str(), legacy.sha), ("dev", None));
+        Ok(())
+    }
+}
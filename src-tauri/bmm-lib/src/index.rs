@@ -0,0 +1,519 @@
+use crate::cache::{Category, ColorPair, Mod, ModDetails};
+use crate::errors::AppError;
+use crate::github_repo::{self, GitInfo, RepoSyncSummary};
+use crate::installer;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+pub const INDEX_REPO_URL: &str = "https://github.com/skyline69/balatro-mod-index";
+
+/// Name of the public index in the default source list.
+pub const OFFICIAL_SOURCE: &str = "official";
+
+/// Where a mod index comes from. Every source has the layout of the public
+/// index: a `mods/<entry>/meta.json` per mod.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum IndexSource {
+    /// A GitHub repository, downloaded as an archive of `branch` (main or
+    /// master when unset).
+    #[serde(rename = "github")]
+    GitHub {
+        name: String,
+        url: String,
+        #[serde(default)]
+        branch: Option<String>,
+    },
+    /// A zip of an index served over HTTP(S), such as a self-hosted Git
+    /// forge's archive link.
+    Zip { name: String, url: String },
+    /// An index folder on disk, read in place.
+    Local { name: String, path: PathBuf },
+}
+
+impl IndexSource {
+    pub fn official() -> Self {
+        IndexSource::GitHub {
+            name: OFFICIAL_SOURCE.to_string(),
+            url: INDEX_REPO_URL.to_string(),
+            branch: None,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            IndexSource::GitHub { name, .. }
+            | IndexSource::Zip { name, .. }
+            | IndexSource::Local { name, .. } => name,
+        }
+    }
+
+    /// Folder the source's index is read from.
+    pub fn local_dir(&self) -> Result<PathBuf, AppError> {
+        match self {
+            IndexSource::GitHub { url, .. } if url == INDEX_REPO_URL => get_repo_path(),
+            IndexSource::GitHub { name, .. } | IndexSource::Zip { name, .. } => {
+                Ok(get_repo_path()?.with_file_name("mod_indexes").join(name))
+            }
+            IndexSource::Local { path, .. } => Ok(path.clone()),
+        }
+    }
+
+    /// Download the source's current contents. Local folders have nothing to
+    /// fetch.
+    pub async fn sync(&self) -> Result<RepoSyncSummary, AppError> {
+        let dir = self.local_dir()?;
+        let path = dir.to_string_lossy();
+        let result = match self {
+            IndexSource::GitHub { url, branch, .. } => {
+                // Start over when the source was pointed somewhere else
+                let same_origin = GitInfo::read(&dir).is_ok_and(|info| {
+                    info.url == *url && branch.as_ref().is_none_or(|b| *b == info.branch)
+                });
+                if same_origin {
+                    github_repo::pull_repository(&path).await
+                } else if let Some(branch) = branch {
+                    github_repo::clone_repository_with_branch(url, &path, branch).await
+                } else {
+                    github_repo::clone_repository(url, &path).await
+                }
+            }
+            IndexSource::Zip { url, .. } => github_repo::sync_archive(url, &path).await,
+            IndexSource::Local { .. } => return Ok(RepoSyncSummary::default()),
+        };
+        result.map_err(AppError::GitOperation)
+    }
+}
+
+/// Check a configured source list: names must be unique and usable as a
+/// folder name, local folders must be absolute.
+pub fn validate_sources(sources: &[IndexSource]) -> Result<(), AppError> {
+    let mut names = HashSet::new();
+    for source in sources {
+        let name = source.name();
+        let usable = !name.trim().is_empty()
+            && name != "."
+            && name != ".."
+            && !name.contains(['/', '\\', ':']);
+        if !usable {
+            return Err(AppError::config_error(
+                "index_sources",
+                format!("'{}' is not a valid source name", name),
+            ));
+        }
+        if !names.insert(name.to_lowercase()) {
+            return Err(AppError::config_error(
+                "index_sources",
+                format!("source '{}' is listed twice", name),
+            ));
+        }
+        if let IndexSource::Local { path, .. } = source {
+            if !path.is_absolute() {
+                return Err(AppError::config_error(
+                    "index_sources",
+                    format!("'{}' is not an absolute path", path.display()),
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ModMeta {
+    #[serde(rename = "requires-steamodded")]
+    pub requires_steamodded: bool,
+    #[serde(rename = "requires-talisman")]
+    pub requires_talisman: bool,
+    pub categories: Vec<String>,
+    pub author: String,
+    pub repo: String,
+    pub title: String,
+    #[serde(rename = "downloadURL")]
+    pub download_url: Option<String>,
+    #[serde(rename = "folderName", default)]
+    pub folder_name: String,
+    #[serde(default)]
+    pub version: String,
+    #[serde(rename = "automatic-version-check", default)]
+    pub automatic_version_check: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dependencies: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub conflicts: Vec<String>,
+    /// Unix time or date (`2025-04-01`, RFC 3339) of the latest release.
+    #[serde(
+        rename = "last-updated",
+        default,
+        deserialize_with = "lenient_timestamp",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub last_updated: Option<u64>,
+    /// Download size in bytes.
+    #[serde(
+        rename = "archive-size",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub archive_size: Option<u64>,
+    #[serde(
+        rename = "min-steamodded-version",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub min_steamodded_version: Option<String>,
+    #[serde(
+        rename = "game-versions",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub game_versions: Vec<String>,
+    /// URLs, or paths relative to the entry's folder.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub screenshots: Vec<String>,
+}
+
+// A date the index can't make sense of is dropped instead of failing the entry
+fn lenient_timestamp<'de, D>(deserializer: D) -> Result<Option<u64>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let value = serde_json::Value::deserialize(deserializer)?;
+    Ok(match &value {
+        serde_json::Value::Number(n) => n.as_u64(),
+        serde_json::Value::String(s) => parse_date(s),
+        _ => None,
+    })
+}
+
+fn parse_date(text: &str) -> Option<u64> {
+    let text = text.trim();
+    let seconds = match chrono::DateTime::parse_from_rfc3339(text) {
+        Ok(date) => date.timestamp(),
+        Err(_) => chrono::NaiveDate::parse_from_str(text, "%Y-%m-%d")
+            .ok()?
+            .and_hms_opt(0, 0, 0)?
+            .and_utc()
+            .timestamp(),
+    };
+    u64::try_from(seconds).ok()
+}
+
+// Screenshots shipped with the entry become absolute paths; links are kept
+// as they are
+fn resolve_screenshots(mod_dir: &Path, screenshots: Vec<String>) -> Vec<String> {
+    screenshots
+        .into_iter()
+        .filter_map(|shot| {
+            if shot.starts_with("https://") || shot.starts_with("http://") {
+                return Some(shot);
+            }
+            let path = mod_dir.join(&shot);
+            let inside = path
+                .canonicalize()
+                .ok()
+                .filter(|p| mod_dir.canonicalize().is_ok_and(|dir| p.starts_with(dir)));
+            match inside {
+                Some(path) if path.is_file() => Some(path.to_string_lossy().into_owned()),
+                _ => {
+                    log::warn!("Ignoring screenshot {} of {}", shot, mod_dir.display());
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+pub fn get_repo_path() -> Result<PathBuf, AppError> {
+    let config_dir = dirs::config_dir()
+        .ok_or_else(|| AppError::DirNotFound(PathBuf::from("config directory")))?;
+    Ok(config_dir.join("Balatro").join("mod_index"))
+}
+
+/// Sync every source, in order. A source that fails doesn't stop the
+/// others; its previous copy stays in place.
+pub async fn sync_sources(
+    sources: &[IndexSource],
+) -> Vec<(String, Result<RepoSyncSummary, AppError>)> {
+    let mut results = Vec::with_capacity(sources.len());
+    for source in sources {
+        results.push((source.name().to_string(), source.sync().await));
+    }
+    results
+}
+
+/// Read every source that has been synced and merge them into one catalog.
+/// Sources earlier in the list win when two have a mod with the same title.
+pub fn load_sources(sources: &[IndexSource]) -> Result<Vec<Mod>, AppError> {
+    let mut seen = HashSet::new();
+    let mut merged = Vec::new();
+    for source in sources {
+        let dir = source.local_dir()?;
+        if !dir.join("mods").is_dir() {
+            log::warn!(
+                "Index source {} has no mods folder at {}",
+                source.name(),
+                dir.display()
+            );
+            continue;
+        }
+        for mut entry in load_catalog(&dir)? {
+            if !seen.insert(entry.title.to_lowercase()) {
+                log::debug!(
+                    "{} from {} is shadowed by an earlier source",
+                    entry.title,
+                    source.name()
+                );
+                continue;
+            }
+            entry.source = Some(source.name().to_string());
+            merged.push(entry);
+        }
+    }
+    merged.sort_by_key(|a| a.title.to_lowercase());
+    Ok(merged)
+}
+
+pub fn read_mod_meta(path: &Path) -> Result<ModMeta, AppError> {
+    let file = File::open(path).map_err(|e| AppError::FileRead {
+        path: path.to_path_buf(),
+        source: e.to_string(),
+    })?;
+
+    serde_json::from_reader(file).map_err(|e| AppError::JsonParse {
+        path: path.to_path_buf(),
+        source: e.to_string(),
+    })
+}
+
+// Same mapping the frontend uses when it builds the catalog
+pub fn parse_category(name: &str) -> Option<Category> {
+    match name.to_lowercase().as_str() {
+        "content" => Some(Category::Content),
+        "joker" => Some(Category::Joker),
+        "quality of life" => Some(Category::QualityOfLife),
+        "technical" => Some(Category::Technical),
+        "miscellaneous" => Some(Category::Miscellaneous),
+        "resource packs" | "resources" => Some(Category::ResourcePacks),
+        "api" => Some(Category::API),
+        _ => None,
+    }
+}
+
+/// Build the catalog straight from a local copy of the mod index. Entries
+/// that fail to parse are logged and skipped.
+pub fn load_catalog(repo_path: &Path) -> Result<Vec<Mod>, AppError> {
+    let mods_dir = repo_path.join("mods");
+    let entries = fs::read_dir(&mods_dir).map_err(|e| AppError::FileRead {
+        path: mods_dir.clone(),
+        source: e.to_string(),
+    })?;
+
+    let mut mods = Vec::new();
+    for entry in entries.filter_map(Result::ok) {
+        let mod_dir = entry.path();
+        if !mod_dir.is_dir() {
+            continue;
+        }
+
+        let meta = match read_mod_meta(&mod_dir.join("meta.json")) {
+            Ok(meta) => meta,
+            Err(e) => {
+                log::warn!("Skipping index entry {}: {}", mod_dir.display(), e);
+                continue;
+            }
+        };
+        let description = fs::read_to_string(mod_dir.join("description.md")).unwrap_or_default();
+        let changelog = fs::read_to_string(mod_dir.join("changelog.md"))
+            .ok()
+            .filter(|c| !c.trim().is_empty());
+        // A local path; the GUI turns it into a data URL before showing it
+        let thumbnail = mod_dir.join("thumbnail.jpg");
+        let image = if thumbnail.is_file() {
+            thumbnail.to_string_lossy().into_owned()
+        } else {
+            "images/cover.jpg".to_string()
+        };
+
+        // Index sources can come from anyone, and this names a directory in the mods folder
+        let folder_name = Some(meta.folder_name).filter(|f| !f.is_empty());
+        let folder_name = match folder_name {
+            Some(f) if !installer::is_plain_folder_name(&f) => {
+                log::warn!(
+                    "Ignoring folder name {:?} of index entry {}",
+                    f,
+                    mod_dir.display()
+                );
+                None
+            }
+            other => other,
+        };
+
+        mods.push(Mod {
+            title: meta.title,
+            description,
+            image,
+            categories: meta
+                .categories
+                .iter()
+                .filter_map(|c| parse_category(c))
+                .collect(),
+            colors: ColorPair {
+                color1: "#4f6367".to_string(),
+                color2: "#425556".to_string(),
+            },
+            installed: false,
+            requires_steamodded: meta.requires_steamodded,
+            requires_talisman: meta.requires_talisman,
+            publisher: meta.author,
+            repo: meta.repo,
+            download_url: meta.download_url.unwrap_or_default(),
+            folderName: folder_name,
+            version: Some(meta.version).filter(|v| !v.is_empty()),
+            sha256: meta.sha256.filter(|h| !h.is_empty()),
+            dependencies: meta.dependencies,
+            conflicts: meta.conflicts,
+            source: None,
+            details: ModDetails {
+                changelog,
+                last_updated: meta.last_updated,
+                archive_size: meta.archive_size,
+                min_steamodded_version: meta.min_steamodded_version.filter(|v| !v.is_empty()),
+                game_versions: meta.game_versions,
+                screenshots: resolve_screenshots(&mod_dir, meta.screenshots),
+                automatic_version_check: meta.automatic_version_check,
+                latest_release: None,
+            },
+        });
+    }
+
+    mods.sort_by_key(|a| a.title.to_lowercase());
+    Ok(mods)
+}
+
+// Catalog lookups match the title or the folder name, ignoring case
+pub fn find_catalog_mod<'a>(catalog: &'a [Mod], name: &str) -> Option<&'a Mod> {
+    catalog.iter().find(|m| {
+        m.title.eq_ignore_ascii_case(name)
+            || m.folderName
+                .as_ref()
+                .is_some_and(|f| f.eq_ignore_ascii_case(name))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn write_entry(index: &Path, folder: &str, title: &str, author: &str) {
+        let dir = index.join("mods").join(folder);
+        fs::create_dir_all(&dir).unwrap();
+        let meta = serde_json::json!({
+            "requires-steamodded": true,
+            "requires-talisman": false,
+            "categories": ["Joker"],
+            "author": author,
+            "repo": "https://example.com/repo",
+            "title": title,
+        });
+        fs::write(dir.join("meta.json"), meta.to_string()).unwrap();
+    }
+
+    #[test]
+    fn test_earlier_sources_win_title_conflicts() -> Result<(), AppError> {
+        let temp = tempdir()?;
+        let first = temp.path().join("first");
+        let second = temp.path().join("second");
+        write_entry(&first, "Cryptid", "Cryptid", "first");
+        write_entry(&second, "cryptid", "CRYPTID", "second");
+        write_entry(&second, "Talisman", "Talisman", "second");
+
+        let sources = vec![
+            IndexSource::Local {
+                name: "first".into(),
+                path: first,
+            },
+            IndexSource::Local {
+                name: "second".into(),
+                path: second,
+            },
+            IndexSource::Local {
+                name: "missing".into(),
+                path: temp.path().join("missing"),
+            },
+        ];
+        validate_sources(&sources)?;
+        let mods = load_sources(&sources)?;
+        let found: Vec<_> = mods
+            .iter()
+            .map(|m| (m.title.as_str(), m.publisher.as_str(), m.source.as_deref()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("Cryptid", "first", Some("first")),
+                ("Talisman", "second", Some("second"))
+            ]
+        );
+
+        let duplicate = vec![IndexSource::official(), IndexSource::official()];
+        assert!(validate_sources(&duplicate).is_err());
+        let relative = vec![IndexSource::Local {
+            name: "relative".into(),
+            path: PathBuf::from("mods"),
+        }];
+        assert!(validate_sources(&relative).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_optional_details_are_read_when_present() -> Result<(), AppError> {
+        let temp = tempdir()?;
+        write_entry(temp.path(), "Plain", "Plain", "someone");
+        let dir = temp.path().join("mods").join("Rich");
+        fs::create_dir_all(dir.join("shots"))?;
+        fs::write(dir.join("shots").join("one.png"), b"png")?;
+        fs::write(dir.join("changelog.md"), "## 1.1\n- Fixed things")?;
+        let meta = serde_json::json!({
+            "requires-steamodded": true,
+            "requires-talisman": false,
+            "categories": ["Content"],
+            "author": "someone",
+            "repo": "https://example.com/repo",
+            "title": "Rich",
+            "folderName": "../Rich",
+            "last-updated": "2025-04-01",
+            "archive-size": 1048576,
+            "min-steamodded-version": "1.0.0~ALPHA-1304a",
+            "game-versions": ["1.0.1o"],
+            "screenshots": ["shots/one.png", "https://example.com/two.png", "../../escape.png"],
+        });
+        fs::write(dir.join("meta.json"), meta.to_string())?;
+
+        let mods = load_catalog(temp.path())?;
+        assert_eq!(mods.len(), 2);
+        assert_eq!(mods[0].details, ModDetails::default());
+
+        // A folder name that isn't a single directory is dropped
+        assert_eq!(mods[1].folderName, None);
+
+        let rich = &mods[1].details;
+        assert_eq!(rich.changelog.as_deref(), Some("## 1.1\n- Fixed things"));
+        assert_eq!(rich.last_updated, Some(1_743_465_600));
+        assert_eq!(rich.archive_size, Some(1_048_576));
+        assert_eq!(rich.game_versions, ["1.0.1o"]);
+        assert_eq!(rich.screenshots.len(), 2);
+        assert!(rich.screenshots[0].ends_with("one.png"));
+        assert_eq!(rich.screenshots[1], "https://example.com/two.png");
+
+        // A date that doesn't parse only loses the date
+        assert_eq!(parse_date("last tuesday"), None);
+        assert_eq!(parse_date("2025-04-01T12:00:00Z"), Some(1_743_508_800));
+        Ok(())
+    }
+}
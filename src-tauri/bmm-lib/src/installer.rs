@@ -1,41 +1,61 @@
+use crate::archive_store::ArchiveStore;
+use crate::cache::Mod;
+use crate::checksum;
+use crate::download;
 use crate::errors::AppError;
 use crate::finder::get_lovely_mods_dir;
+use crate::progress::{NoProgress, Progress, ProgressSink};
+use crate::smods_installer::{ModInstaller, ModType};
+use crate::version::Version;
 use flate2::read::GzDecoder;
-use reqwest::Client;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
 use std::fs;
 use std::io::Read;
 use std::io::{self, Cursor};
-use std::path::Path;
 use std::path::PathBuf;
+use std::path::{Component, Path};
 use tar::Archive;
+use tempfile::TempDir;
 use zip::ZipArchive;
 
+/// Where a downloaded mod ended up, and the URL and SHA-256 of the archive it
+/// came from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstalledArchive {
+    pub path: PathBuf,
+    pub url: String,
+    pub sha256: String,
+}
+
 pub async fn install_mod(
     installation_path: Option<&String>,
     url: String,
     folder_name: Option<String>,
-) -> Result<PathBuf, AppError> {
-    let client = Client::new();
-    let response = client
-        .get(&url)
-        .send()
-        .await
-        .map_err(|e| AppError::NetworkRequest {
-            url: url.clone(),
-            source: e.to_string(),
-        })?;
-
-    let file = response
-        .bytes()
-        .await
-        .map_err(|e| AppError::NetworkRequest {
-            url: url.clone(),
-            source: e.to_string(),
-        })?;
+    expected_sha256: Option<&str>,
+) -> Result<InstalledArchive, AppError> {
+    install_mod_with_progress(
+        installation_path,
+        url,
+        folder_name,
+        expected_sha256,
+        &NoProgress,
+    )
+    .await
+}
 
-    let file_type = infer::get(&file)
-        .ok_or_else(|| AppError::InvalidState("Unknown file type".into()))?
-        .mime_type();
+/// [`install_mod`], reporting each phase to `progress` and stopping before
+/// anything is moved into place if it is cancelled.
+pub async fn install_mod_with_progress(
+    installation_path: Option<&String>,
+    url: String,
+    folder_name: Option<String>,
+    expected_sha256: Option<&str>,
+    progress: &dyn ProgressSink,
+) -> Result<InstalledArchive, AppError> {
+    progress.report(Progress::Resolving);
+    // Nothing on disk is touched until the archive matches its published hash
+    let (file, sha256) = fetch_archive_with_progress(&url, expected_sha256, progress).await?;
 
     let mod_dir = get_lovely_mods_dir(installation_path);
 
@@ -66,19 +86,205 @@ pub async fn install_mod(
         }
     };
 
-    // Uninstall old mod folder if it exists
-    let target_dir = mod_dir.join(&mod_name);
-    if target_dir.exists() {
-        log::info!("Uninstalling existing mod at: {:?}", target_dir);
-        uninstall_mod(installation_path, target_dir.clone())?;
+    log::info!("Installing mod: {}", url);
+    let target_dir = extract_archive_with_progress(&mod_dir, &mod_name, file, progress)?;
+
+    log::info!("Mod installed successfully at: {:?}", target_dir);
+    Ok(InstalledArchive {
+        path: target_dir,
+        url,
+        sha256,
+    })
+}
+
+/// Names of the frameworks a catalog mod declares it needs.
+pub fn catalog_dependencies(catalog_mod: &Mod) -> Vec<String> {
+    let mut dependencies = Vec::new();
+    if catalog_mod.requires_steamodded {
+        dependencies.push("Steamodded".to_string());
     }
+    if catalog_mod.requires_talisman {
+        dependencies.push("Talisman".to_string());
+    }
+    dependencies
+}
 
-    log::info!("Installing mod: {}", url);
+/// Install a mod from the catalog and return the archive along with the
+/// version that ended up installed. Steamodded and Talisman can be installed
+/// at any released `version`; other mods only at the one the index lists.
+pub async fn install_from_catalog(
+    installation_path: Option<&String>,
+    catalog_mod: &Mod,
+    version: Option<&str>,
+) -> Result<(InstalledArchive, Option<String>), AppError> {
+    let framework = match catalog_mod.title.to_lowercase().as_str() {
+        "steamodded" => Some(ModType::Steamodded),
+        "talisman" => Some(ModType::Talisman),
+        _ => None,
+    };
+
+    match (framework, version) {
+        (Some(mod_type), Some(version)) => {
+            let installer = ModInstaller::new(installation_path, mod_type);
+            let archive = installer
+                .install_version_verified(version, None, &NoProgress)
+                .await?;
+            Ok((archive, Some(version.to_string())))
+        }
+        (None, Some(version))
+            if catalog_mod
+                .version
+                .as_deref()
+                .and_then(Version::parse)
+                .is_none_or(|listed| Version::parse(version) != Some(listed)) =>
+        {
+            Err(AppError::InvalidState(format!(
+                "{} can only be installed at the version listed in the index",
+                catalog_mod.title
+            )))
+        }
+        _ => {
+            if catalog_mod.download_url.is_empty() {
+                return Err(AppError::mod_install_error(
+                    &catalog_mod.title,
+                    "index entry has no download URL",
+                ));
+            }
+            let folder_name = catalog_mod
+                .folderName
+                .clone()
+                .filter(|f| !f.is_empty())
+                .unwrap_or_else(|| catalog_mod.title.split_whitespace().collect());
+            let archive = install_mod(
+                installation_path,
+                catalog_mod.download_url.clone(),
+                Some(folder_name),
+                catalog_mod.sha256.as_deref(),
+            )
+            .await?;
+            Ok((archive, catalog_mod.version.clone()))
+        }
+    }
+}
+
+/// Get the archive behind `url` and its SHA-256. A cached copy is used when
+/// the expected hash is already in the archive store, and the last cached
+/// download of `url` is used when the network is unreachable. Fresh downloads
+/// are verified and added to the store.
+pub async fn fetch_archive(
+    url: &str,
+    expected_sha256: Option<&str>,
+) -> Result<(bytes::Bytes, String), AppError> {
+    fetch_archive_with_progress(url, expected_sha256, &NoProgress).await
+}
+
+/// [`fetch_archive`], reporting the download to `progress`.
+pub async fn fetch_archive_with_progress(
+    url: &str,
+    expected_sha256: Option<&str>,
+    progress: &dyn ProgressSink,
+) -> Result<(bytes::Bytes, String), AppError> {
+    let store = ArchiveStore::open_default()
+        .inspect_err(|e| log::warn!("Archive cache unavailable: {}", e))
+        .ok();
+    let expected = expected_sha256.map(checksum::normalize_digest);
+
+    if let (Some(store), Some(expected)) = (&store, &expected) {
+        if let Some(data) = store.get(expected)? {
+            log::info!("Using cached archive for {}", url);
+            if let Err(e) = store.put(Some(url), &data) {
+                log::warn!("Failed to record {} in the archive cache: {}", url, e);
+            }
+            return Ok((data.into(), expected.clone()));
+        }
+    }
+
+    let data = match download::shared().get_with_progress(url, progress).await {
+        Ok(data) => data,
+        Err(AppError::Cancelled) => return Err(AppError::Cancelled),
+        Err(e) => {
+            let cached = match &store {
+                Some(store) => store.find_by_url(url)?,
+                None => None,
+            };
+            return match cached {
+                Some((sha256, data)) if expected.as_ref().is_none_or(|x| *x == sha256) => {
+                    log::warn!("Download of {} failed ({}), using cached copy", url, e);
+                    Ok((data.into(), sha256))
+                }
+                _ => Err(e),
+            };
+        }
+    };
+
+    let sha256 = checksum::verify_sha256(url, &data, expected_sha256)?;
+    if let Some(store) = &store {
+        if let Err(e) = store.put(Some(url), &data) {
+            log::warn!("Failed to cache archive for {}: {}", url, e);
+        }
+    }
+
+    Ok((data, sha256))
+}
+
+/// Get the archive a mod was installed from before, by URL when one was
+/// recorded and otherwise from the archive cache alone.
+pub async fn fetch_recorded_archive(
+    url: Option<&str>,
+    sha256: Option<&str>,
+) -> Result<(bytes::Bytes, String), AppError> {
+    match (url, sha256) {
+        (Some(url), _) => fetch_archive(url, sha256).await,
+        (None, Some(sha256)) => match ArchiveStore::open_default()?.get(sha256)? {
+            Some(data) => Ok((data.into(), sha256.to_string())),
+            None => Err(AppError::InvalidState(format!(
+                "Archive {} is no longer cached",
+                sha256
+            ))),
+        },
+        (None, None) => Err(AppError::InvalidState(
+            "No download URL or archive hash was recorded".to_string(),
+        )),
+    }
+}
+
+/// Unpack a zip, tar or tar.gz archive into `mods_dir/mod_name`, replacing
+/// whatever is there only once extraction has succeeded.
+pub fn extract_archive(
+    mods_dir: &Path,
+    mod_name: &str,
+    file: bytes::Bytes,
+) -> Result<PathBuf, AppError> {
+    extract_archive_with_progress(mods_dir, mod_name, file, &NoProgress)
+}
+
+/// [`extract_archive`], reporting each extracted entry to `progress`.
+pub fn extract_archive_with_progress(
+    mods_dir: &Path,
+    mod_name: &str,
+    file: bytes::Bytes,
+    progress: &dyn ProgressSink,
+) -> Result<PathBuf, AppError> {
+    let file_type = infer::get(&file)
+        .ok_or_else(|| AppError::InvalidState("Unknown file type".into()))?
+        .mime_type();
+
+    if !is_plain_folder_name(mod_name) {
+        return Err(AppError::PathValidation {
+            path: mods_dir.join(mod_name),
+            reason: "Mod folder must be a single plain directory name".into(),
+        });
+    }
+    let target_dir = mods_dir.join(mod_name);
+    ensure_safe_path(mods_dir, &target_dir)?;
 
-    let installed_path = match file_type {
-        "application/zip" => handle_zip(file, &mod_dir, &mod_name)?,
-        "application/x-tar" => handle_tar(file, &mod_dir, &mod_name)?, // Updated
-        "application/gzip" => handle_tar_gz(file, &mod_dir, &mod_name)?, // Updated
+    // Extract next to the mods folder first so a bad archive never touches the installed copy
+    let staging = create_staging_dir(mods_dir)?;
+
+    let staged_path = match file_type {
+        "application/zip" => handle_zip(file, staging.path(), mod_name, progress)?,
+        "application/x-tar" => handle_tar(file, staging.path(), mod_name, progress)?, // Updated
+        "application/gzip" => handle_tar_gz(file, staging.path(), mod_name, progress)?, // Updated
         _ => {
             return Err(AppError::InvalidState(format!(
                 "Unsupported file type: {}",
@@ -87,11 +293,70 @@ pub async fn install_mod(
         }
     };
 
-    log::info!("Mod installed successfully at: {:?}", installed_path);
-    Ok(installed_path)
+    progress.check_cancelled()?;
+    progress.report(Progress::Finalising);
+    swap_into_place(&staged_path, &target_dir, staging.path())?;
+    Ok(target_dir)
+}
+
+/// Create a scratch directory beside the mods folder. Being on the same
+/// filesystem lets the finished mod be moved into place with a rename, and
+/// the directory is removed again when the returned handle is dropped.
+pub fn create_staging_dir(mods_dir: &Path) -> Result<TempDir, AppError> {
+    fs::create_dir_all(mods_dir).map_err(|e| AppError::DirCreate {
+        path: mods_dir.to_path_buf(),
+        source: e.to_string(),
+    })?;
+
+    let parent = mods_dir.parent().unwrap_or(mods_dir);
+    tempfile::Builder::new()
+        .prefix(".bmm-staging-")
+        .tempdir_in(parent)
+        .map_err(|e| AppError::DirCreate {
+            path: parent.to_path_buf(),
+            source: e.to_string(),
+        })
+}
+
+/// Replace `target` with the fully extracted `staged` directory. The previous
+/// version is parked in `staging_dir` and moved back if the swap fails.
+pub fn swap_into_place(staged: &Path, target: &Path, staging_dir: &Path) -> Result<(), AppError> {
+    let previous = staging_dir.join(".previous");
+    let had_previous = target.exists();
+
+    if had_previous {
+        log::info!("Replacing existing mod at: {:?}", target);
+        fs::rename(target, &previous).map_err(|e| AppError::FileWrite {
+            path: target.to_path_buf(),
+            source: format!("Failed to move existing mod aside: {}", e),
+        })?;
+    }
+
+    if let Err(e) = fs::rename(staged, target) {
+        if had_previous {
+            if let Err(restore_err) = fs::rename(&previous, target) {
+                log::error!(
+                    "Failed to restore previous version at {:?}: {}",
+                    target,
+                    restore_err
+                );
+            }
+        }
+        return Err(AppError::FileWrite {
+            path: target.to_path_buf(),
+            source: format!("Failed to move mod into place: {}", e),
+        });
+    }
+
+    Ok(())
 }
 
-fn handle_zip(file: bytes::Bytes, mod_dir: &Path, mod_name: &str) -> Result<PathBuf, AppError> {
+fn handle_zip(
+    file: bytes::Bytes,
+    mod_dir: &Path,
+    mod_name: &str,
+    progress: &dyn ProgressSink,
+) -> Result<PathBuf, AppError> {
     let cursor = Cursor::new(file);
     let mut zip = ZipArchive::new(cursor).map_err(|e| AppError::FileWrite {
         path: mod_dir.to_path_buf(),
@@ -125,7 +390,7 @@ fn handle_zip(file: bytes::Bytes, mod_dir: &Path, mod_name: &str) -> Result<Path
             source: e.to_string(),
         })?;
 
-        extract_zip_root(&mut zip, &target_dir)?;
+        extract_zip_root(&mut zip, &target_dir, progress)?;
     } else {
         // For ZIPs with a folder structure
         // Create temp directory
@@ -143,7 +408,7 @@ fn handle_zip(file: bytes::Bytes, mod_dir: &Path, mod_name: &str) -> Result<Path
         })?;
 
         // Extract to temp directory
-        extract_zip(&mut zip, &temp_dir)?;
+        extract_zip(&mut zip, &temp_dir, progress)?;
 
         // Get root directory name
         let root_dir = get_zip_root_dir(&mut zip, &temp_dir)?;
@@ -168,13 +433,16 @@ fn handle_zip(file: bytes::Bytes, mod_dir: &Path, mod_name: &str) -> Result<Path
 fn extract_zip_root(
     zip: &mut ZipArchive<Cursor<bytes::Bytes>>,
     path: &PathBuf,
+    progress: &dyn ProgressSink,
 ) -> Result<(), AppError> {
     fs::create_dir_all(path).map_err(|e| AppError::DirCreate {
         path: path.clone(),
         source: e.to_string(),
     })?;
 
-    for i in 0..zip.len() {
+    let total = zip.len();
+    for i in 0..total {
+        progress.check_cancelled()?;
         let mut file = zip.by_index(i).map_err(|e| AppError::FileRead {
             path: path.clone(),
             source: format!("Zip entry error: {}", e),
@@ -192,6 +460,7 @@ fn extract_zip_root(
             create_parent_dir(&entry_path)?;
             copy_file_contents(&mut file, &entry_path)?;
         }
+        progress.report(Progress::Extracting { done: i + 1, total });
     }
     Ok(())
 }
@@ -212,8 +481,16 @@ fn get_zip_root_dir(
         .ok_or_else(|| AppError::InvalidState("Empty zip archive".into()))
 }
 
-fn extract_zip(zip: &mut ZipArchive<Cursor<bytes::Bytes>>, mod_dir: &Path) -> Result<(), AppError> {
-    for i in 0..zip.len() {
+/// Unpack every entry of `zip` into `mod_dir` as it is laid out in the
+/// archive, reporting each one to `progress`.
+pub(crate) fn extract_zip(
+    zip: &mut ZipArchive<Cursor<bytes::Bytes>>,
+    mod_dir: &Path,
+    progress: &dyn ProgressSink,
+) -> Result<(), AppError> {
+    let total = zip.len();
+    for i in 0..total {
+        progress.check_cancelled()?;
         let mut file = zip.by_index(i).map_err(|e| AppError::FileRead {
             path: mod_dir.to_path_buf(),
             source: format!("Zip entry error: {}", e),
@@ -231,27 +508,44 @@ fn extract_zip(zip: &mut ZipArchive<Cursor<bytes::Bytes>>, mod_dir: &Path) -> Re
             create_parent_dir(&entry_path)?;
             copy_file_contents(&mut file, &entry_path)?;
         }
+        progress.report(Progress::Extracting { done: i + 1, total });
     }
     Ok(())
 }
 
-fn handle_tar(file: bytes::Bytes, mod_dir: &Path, mod_name: &str) -> Result<PathBuf, AppError> {
-    let cursor = Cursor::new(file);
-    let mut tar = Archive::new(cursor);
-    extract_tar(&mut tar, mod_dir, mod_name)
+fn handle_tar(
+    file: bytes::Bytes,
+    mod_dir: &Path,
+    mod_name: &str,
+    progress: &dyn ProgressSink,
+) -> Result<PathBuf, AppError> {
+    let total = count_tar_entries(Archive::new(Cursor::new(file.clone())));
+    let mut tar = Archive::new(Cursor::new(file));
+    extract_tar(&mut tar, mod_dir, mod_name, total, progress)
 }
 
-fn handle_tar_gz(file: bytes::Bytes, mod_dir: &Path, mod_name: &str) -> Result<PathBuf, AppError> {
-    let cursor = Cursor::new(file);
-    let gz = GzDecoder::new(cursor);
-    let mut tar = Archive::new(gz);
-    extract_tar(&mut tar, mod_dir, mod_name)
+fn handle_tar_gz(
+    file: bytes::Bytes,
+    mod_dir: &Path,
+    mod_name: &str,
+    progress: &dyn ProgressSink,
+) -> Result<PathBuf, AppError> {
+    let total = count_tar_entries(Archive::new(GzDecoder::new(Cursor::new(file.clone()))));
+    let mut tar = Archive::new(GzDecoder::new(Cursor::new(file)));
+    extract_tar(&mut tar, mod_dir, mod_name, total, progress)
+}
+
+// Tars have no index, so counting the entries takes a pass of its own
+fn count_tar_entries(mut tar: Archive<impl Read>) -> usize {
+    tar.entries().map_or(0, |entries| entries.count())
 }
 
 fn extract_tar(
     tar: &mut Archive<impl Read>,
     mod_dir: &Path,
     mod_name: &str,
+    total: usize,
+    progress: &dyn ProgressSink,
 ) -> Result<PathBuf, AppError> {
     let target_dir = mod_dir.join(mod_name);
     fs::create_dir_all(&target_dir).map_err(|e| AppError::DirCreate {
@@ -264,7 +558,8 @@ fn extract_tar(
         source: format!("Tar entry error: {}", e),
     })?;
 
-    for entry in entries {
+    for (i, entry) in entries.enumerate() {
+        progress.check_cancelled()?;
         let mut entry = entry.map_err(|e| AppError::FileRead {
             path: mod_dir.to_path_buf(),
             source: format!("Tar entry error: {}", e),
@@ -288,6 +583,10 @@ fn extract_tar(
             create_parent_dir(&path)?;
             copy_file_contents(&mut entry, &path)?;
         }
+        progress.report(Progress::Extracting {
+            done: i + 1,
+            total: total.max(i + 1),
+        });
     }
 
     Ok(target_dir)
@@ -318,8 +617,25 @@ fn copy_file_contents(reader: &mut impl io::Read, path: &PathBuf) -> Result<(),
     Ok(())
 }
 
+/// Whether `name` is a single ordinary directory name, so joining it onto
+/// the mods folder can't land anywhere else.
+pub fn is_plain_folder_name(name: &str) -> bool {
+    let mut components = Path::new(name).components();
+    !name.starts_with('.')
+        && !name.contains(['/', '\\'])
+        && matches!(
+            (components.next(), components.next()),
+            (Some(Component::Normal(_)), None)
+        )
+}
+
 fn ensure_safe_path(base: &Path, path: &Path) -> Result<(), AppError> {
-    if !path.starts_with(base) {
+    // starts_with alone would let "base/../elsewhere" through
+    let inside = path.strip_prefix(base).is_ok_and(|rest| {
+        rest.components()
+            .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+    });
+    if !inside {
         Err(AppError::PathValidation {
             path: path.to_path_buf(),
             reason: "Path traversal attempt detected".into(),
@@ -330,11 +646,14 @@ fn ensure_safe_path(base: &Path, path: &Path) -> Result<(), AppError> {
 }
 
 pub fn uninstall_mod(installation_path: Option<&String>, path: PathBuf) -> Result<(), AppError> {
-    log::info!("Uninstalling mod: {:?}", path);
-
     let mods_dir = get_lovely_mods_dir(installation_path);
+    remove_mod_dir(&mods_dir, path)
+}
+
+pub fn remove_mod_dir(mods_dir: &Path, path: PathBuf) -> Result<(), AppError> {
+    log::info!("Uninstalling mod: {:?}", path);
 
-    validate_uninstall_path(&path, &mods_dir)
+    validate_uninstall_path(&path, mods_dir)
         .inspect_err(|e| log::error!("Uninstall path validation error: {}", e.to_string()))?;
 
     if let Some(dir_name) = path.file_name().and_then(|n| n.to_str()) {
@@ -349,10 +668,10 @@ pub fn uninstall_mod(installation_path: Option<&String>, path: PathBuf) -> Resul
     })
 }
 
-fn validate_uninstall_path(path: &PathBuf, mods_dir: &PathBuf) -> Result<(), AppError> {
+fn validate_uninstall_path(path: &Path, mods_dir: &Path) -> Result<(), AppError> {
     if !path.exists() {
         return Err(AppError::PathValidation {
-            path: path.clone(),
+            path: path.to_path_buf(),
             reason: "Path doesn't exist".into(),
         });
     }
@@ -365,10 +684,85 @@ fn validate_uninstall_path(path: &PathBuf, mods_dir: &PathBuf) -> Result<(), App
 
     if !path.starts_with(mods_dir) {
         return Err(AppError::PathValidation {
-            path: path.clone(),
+            path: path.to_path_buf(),
             reason: "Path outside Mods directory".into(),
         });
     }
 
     Ok(())
 }
+
+// A mod is disabled when its root directory carries a .lovelyignore file
+pub fn is_mod_enabled(path: &Path) -> Result<bool, AppError> {
+    if !path.exists() {
+        return Err(AppError::DirNotFound(path.to_path_buf()));
+    }
+
+    Ok(!path.join(".lovelyignore").exists())
+}
+
+pub fn set_mod_enabled(path: &Path, enabled: bool) -> Result<(), AppError> {
+    if !path.exists() {
+        return Err(AppError::DirNotFound(path.to_path_buf()));
+    }
+
+    // Collect entries first - this prevents the borrow of path from being split across threads
+    let entries: Vec<_> = fs::read_dir(path)
+        .map_err(|e| AppError::FileRead {
+            path: path.to_path_buf(),
+            source: e.to_string(),
+        })?
+        .collect::<Result<_, _>>()
+        .map_err(|e| AppError::FileRead {
+            path: path.to_path_buf(),
+            source: e.to_string(),
+        })?;
+
+    let ignore_file_path = path.join(".lovelyignore");
+
+    if enabled {
+        // Process subdirectories in parallel
+        entries
+            .par_iter()
+            .filter(|entry| entry.path().is_dir())
+            .try_for_each(|entry| {
+                let ignore_path = entry.path().join(".lovelyignore");
+                if ignore_path.exists() {
+                    fs::remove_file(&ignore_path).map_err(|e| AppError::FileWrite {
+                        path: ignore_path,
+                        source: e.to_string(),
+                    })
+                } else {
+                    Ok(())
+                }
+            })?;
+
+        // Handle the top-level ignore file
+        if ignore_file_path.exists() {
+            fs::remove_file(&ignore_file_path).map_err(|e| AppError::FileWrite {
+                path: ignore_file_path,
+                source: e.to_string(),
+            })?;
+        }
+    } else {
+        // Process subdirectories in parallel for disabling
+        entries
+            .par_iter()
+            .filter(|entry| entry.path().is_dir())
+            .try_for_each(|entry| {
+                let ignore_path = entry.path().join(".lovelyignore");
+                fs::write(&ignore_path, "").map_err(|e| AppError::FileWrite {
+                    path: ignore_path,
+                    source: e.to_string(),
+                })
+            })?;
+
+        // Handle the top-level ignore file
+        fs::write(&ignore_file_path, "").map_err(|e| AppError::FileWrite {
+            path: ignore_file_path,
+            source: e.to_string(),
+        })?;
+    }
+
+    Ok(())
+}
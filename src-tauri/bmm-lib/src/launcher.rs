@@ -0,0 +1,122 @@
+use crate::errors::AppError;
+#[cfg(target_os = "macos")]
+use crate::lovely;
+use std::path::Path;
+#[cfg(target_os = "windows")]
+use std::path::PathBuf;
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+use std::process::Command;
+
+pub async fn launch_balatro(path: &Path, lovely_console_enabled: bool) -> Result<(), AppError> {
+    #[cfg(target_os = "macos")]
+    {
+        let lovely_path = lovely::ensure_lovely_exists().await?;
+        let balatro_executable = path.join("Balatro.app/Contents/MacOS/love");
+
+        if lovely_console_enabled {
+            // If the console is disabled, add the flag
+            let disable_arg = if !lovely_console_enabled {
+                " --disable-console"
+            } else {
+                ""
+            };
+            // Instead of using double quotes which cause conflicts in AppleScript,
+            // wrap the file paths in single quotes.
+            let command_line = format!(
+                "cd '{}' && DYLD_INSERT_LIBRARIES='{}' '{}'{}",
+                path.display(),
+                lovely_path.display(),
+                balatro_executable.display(),
+                disable_arg
+            );
+
+            // Construct the AppleScript command to run the command_line in Terminal.
+            let applescript = format!(
+                "tell application \"Terminal\" to do script \"{}\"",
+                command_line
+            );
+
+            Command::new("osascript")
+                .arg("-e")
+                .arg(applescript)
+                .spawn()
+                .map_err(|e| AppError::ProcessExecution(e.to_string()))?;
+        } else {
+            let mut command = Command::new(balatro_executable);
+            command
+                .env("DYLD_INSERT_LIBRARIES", lovely_path)
+                .current_dir(path);
+            command
+                .spawn()
+                .map_err(|e| AppError::ProcessExecution(e.to_string()))?;
+        }
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        // Find the executable file in the directory
+        let exe_path = find_executable_in_directory(path).ok_or_else(|| {
+            AppError::ProcessExecution(format!("No executable found in {}", path.display()))
+        })?;
+
+        // If version.dll doesn't exist, download it
+        crate::lovely::ensure_version_dll_exists(&path.to_path_buf()).await?;
+
+        // Launch the game
+        let mut command = Command::new(&exe_path);
+        command.current_dir(path);
+        if !lovely_console_enabled {
+            command.arg("--disable-console");
+        }
+        command.spawn().map_err(|e| {
+            AppError::ProcessExecution(format!("Failed to launch {}: {}", exe_path.display(), e))
+        })?;
+
+        log::debug!("Launched game from {}", exe_path.display());
+    }
+
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    {
+        log::warn!(
+            "Launching {} (console: {}) is not supported on this platform",
+            path.display(),
+            lovely_console_enabled
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(target_os = "windows")]
+fn find_executable_in_directory(dir: &Path) -> Option<PathBuf> {
+    if let Ok(entries) = std::fs::read_dir(dir) {
+        // Create a Vec to hold all executable files
+        let mut executables: Vec<PathBuf> = Vec::new();
+
+        // First, collect all executable files in the directory
+        for entry in entries.filter_map(Result::ok) {
+            let path = entry.path();
+            if path.is_file() && path.extension() == Some(std::ffi::OsStr::new("exe")) {
+                executables.push(path);
+            }
+        }
+
+        if executables.is_empty() {
+            return None;
+        }
+
+        // First, look for any executable with "balatro" in the name (case-insensitive)
+        for exe in &executables {
+            if let Some(file_name) = exe.file_name().and_then(|n| n.to_str()) {
+                if file_name.to_lowercase().contains("balatro") {
+                    return Some(exe.clone());
+                }
+            }
+        }
+
+        // If no Balatro-specific executable was found, return the first executable
+        return Some(executables[0].clone());
+    }
+
+    None
+}
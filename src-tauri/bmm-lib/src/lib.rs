@@ -10,3 +10,24 @@ pub mod errors;
 pub mod discord_rpc;
 pub mod local_mod_detection;
 pub mod logging;
+pub mod github_repo;
+pub mod index;
+pub mod launcher;
+pub mod manager;
+pub mod version;
+pub mod updater;
+pub mod checksum;
+pub mod archive_store;
+pub mod manifest;
+pub mod resolver;
+pub mod profiles;
+pub mod lockfile;
+pub mod migrations;
+pub mod settings;
+pub mod connection_pool;
+pub mod catalog;
+pub mod search;
+pub mod releases;
+pub mod github_api;
+pub mod download;
+pub mod progress;
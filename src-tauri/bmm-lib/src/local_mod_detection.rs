@@ -119,7 +119,7 @@ pub fn detect_manual_mods(
     #[cfg(not(target_os = "linux"))]
     let mods_dir = get_lovely_mods_dir(None);
     #[cfg(target_os = "linux")]
-    let mods_dir = get_lovely_mods_dir(db.get_installation_path()?.as_ref());
+    let mods_dir = get_lovely_mods_dir(db.settings()?.installation_path.as_ref());
 
     if !mods_dir.exists() {
         return Ok(Vec::new());
@@ -519,7 +519,9 @@ fn count_path_depth(path: &Path, base_path: &Path) -> usize {
         .count()
 }
 
-fn detect_mod_in_directory(mod_path: &Path) -> Result<Option<DetectedMod>, String> {
+/// Read the mod in `mod_path` from its Thunderstore manifest, json metadata
+/// or Steamodded header, if it has one.
+pub fn detect_mod_in_directory(mod_path: &Path) -> Result<Option<DetectedMod>, String> {
     // Get directory name
     let dir_name = mod_path
         .file_name()
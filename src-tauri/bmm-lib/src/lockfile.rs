@@ -0,0 +1,426 @@
+use crate::cache::Mod;
+use crate::checksum;
+use crate::database::InstalledMod;
+use crate::errors::AppError;
+use crate::index;
+use crate::installer::{self, InstalledArchive};
+use crate::lovely;
+use crate::manager::ModManager;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+pub const LOCKFILE_NAME: &str = "bmm.lock";
+
+/// Bumped when the lockfile layout changes incompatibly.
+pub const LOCKFILE_VERSION: u32 = 1;
+
+/// The exact contents of a mods folder: every mod with the archive it was
+/// extracted from, plus the framework and injector it runs on.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Lockfile {
+    pub lock_version: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub steamodded: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub talisman: Option<String>,
+    /// lovely doesn't report a version, so the injector binary is hashed instead.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lovely_sha256: Option<String>,
+    #[serde(default, rename = "mod")]
+    pub mods: Vec<LockedMod>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LockedMod {
+    pub name: String,
+    /// Directory name inside the mods folder.
+    pub folder: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    pub enabled: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dependencies: Vec<String>,
+}
+
+/// What syncing a lockfile has to change in the mods folder.
+#[derive(Debug, Default)]
+pub struct SyncPlan {
+    /// Locked mods that are missing, differ from their archive or live in
+    /// another folder.
+    pub install: Vec<LockedMod>,
+    /// Installed mods the lockfile doesn't list, as (name, path).
+    pub remove: Vec<(String, String)>,
+    pub unchanged: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct SyncReport {
+    pub installed: Vec<String>,
+    pub removed: Vec<String>,
+    pub unchanged: Vec<String>,
+    pub failed: Vec<SyncFailure>,
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SyncFailure {
+    pub name: String,
+    pub error: String,
+}
+
+impl SyncReport {
+    fn fail(&mut self, name: &str, error: impl ToString) {
+        self.failed.push(SyncFailure {
+            name: name.to_string(),
+            error: error.to_string(),
+        });
+    }
+}
+
+fn folder_of(path: &str) -> Option<String> {
+    Path::new(path)
+        .file_name()
+        .and_then(|n| n.to_str())
+        .map(str::to_string)
+}
+
+// Fills in the source from the index when the mod was installed at the listed version
+fn lock_mod(installed: &InstalledMod, catalog: &[Mod], enabled: bool) -> Option<LockedMod> {
+    let folder = folder_of(&installed.path)?;
+    let version = installed.current_version.clone().filter(|v| !v.is_empty());
+    let source = installed.download_url.clone().or_else(|| {
+        index::find_catalog_mod(catalog, &installed.name)
+            .filter(|m| m.version.is_some() && m.version == version)
+            .map(|m| m.download_url.clone())
+            .filter(|url| !url.is_empty())
+    });
+
+    Some(LockedMod {
+        name: installed.name.clone(),
+        folder,
+        version,
+        source,
+        sha256: installed.archive_sha256.clone(),
+        enabled,
+        dependencies: installed.dependencies.clone(),
+    })
+}
+
+impl Lockfile {
+    /// Lock every installed mod. Mods whose folder has gone missing are left out.
+    pub fn generate(manager: &ModManager, catalog: &[Mod]) -> Result<Self, AppError> {
+        let mut mods = Vec::new();
+        for installed in manager.db().get_installed_mods()? {
+            let Ok(enabled) = installer::is_mod_enabled(Path::new(&installed.path)) else {
+                log::warn!(
+                    "Leaving {} out of the lockfile, its folder is missing",
+                    installed.name
+                );
+                continue;
+            };
+            if let Some(locked) = lock_mod(&installed, catalog, enabled) {
+                mods.push(locked);
+            }
+        }
+        mods.sort_by_key(|a| a.name.to_lowercase());
+
+        let framework_version = |name: &str| {
+            mods.iter()
+                .find(|m| m.name.eq_ignore_ascii_case(name))
+                .and_then(|m| m.version.clone())
+        };
+
+        Ok(Self {
+            lock_version: LOCKFILE_VERSION,
+            steamodded: framework_version("Steamodded"),
+            talisman: framework_version("Talisman"),
+            lovely_sha256: lovely_sha256(),
+            mods,
+        })
+    }
+
+    pub fn to_toml(&self) -> Result<String, AppError> {
+        toml::to_string_pretty(self).map_err(|e| AppError::Serialization {
+            format: "TOML".to_string(),
+            source: e.to_string(),
+        })
+    }
+
+    pub fn from_toml(content: &str) -> Result<Self, AppError> {
+        let lockfile: Self = toml::from_str(content).map_err(|e| AppError::Serialization {
+            format: "TOML".to_string(),
+            source: e.to_string(),
+        })?;
+        if lockfile.lock_version > LOCKFILE_VERSION {
+            return Err(AppError::InvalidState(format!(
+                "Lockfile version {} is newer than this version of the manager supports",
+                lockfile.lock_version
+            )));
+        }
+        Ok(lockfile)
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), AppError> {
+        std::fs::write(path, self.to_toml()?).map_err(|e| AppError::FileWrite {
+            path: path.to_path_buf(),
+            source: e.to_string(),
+        })
+    }
+
+    pub fn read(path: &Path) -> Result<Self, AppError> {
+        let content = std::fs::read_to_string(path).map_err(|e| AppError::FileRead {
+            path: path.to_path_buf(),
+            source: e.to_string(),
+        })?;
+        Self::from_toml(&content)
+    }
+}
+
+fn lovely_sha256() -> Option<String> {
+    let path = lovely::installed_lovely_path()?;
+    checksum::sha256_file(&path)
+        .inspect_err(|e| log::warn!("Failed to hash {}: {}", path.display(), e))
+        .ok()
+}
+
+/// Compare the lockfile against the installed mods. `is_intact` should tell
+/// whether a mod's files still match the manifest recorded at install time.
+pub fn plan_sync<F>(lockfile: &Lockfile, installed_mods: &[InstalledMod], is_intact: F) -> SyncPlan
+where
+    F: Fn(&InstalledMod) -> bool,
+{
+    let mut plan = SyncPlan::default();
+
+    for locked in &lockfile.mods {
+        let current = installed_mods
+            .iter()
+            .find(|m| m.name.eq_ignore_ascii_case(&locked.name));
+        let matches = current.is_some_and(|m| {
+            folder_of(&m.path).as_deref() == Some(locked.folder.as_str())
+                && match &locked.sha256 {
+                    Some(sha256) => {
+                        m.archive_sha256.as_deref()
+                            == Some(checksum::normalize_digest(sha256).as_str())
+                    }
+                    None => m.current_version == locked.version,
+                }
+                && is_intact(m)
+        });
+
+        if matches {
+            plan.unchanged.push(locked.name.clone());
+        } else {
+            plan.install.push(locked.clone());
+        }
+    }
+
+    for installed in installed_mods {
+        let locked = lockfile
+            .mods
+            .iter()
+            .any(|m| m.name.eq_ignore_ascii_case(&installed.name));
+        if !locked {
+            plan.remove
+                .push((installed.name.clone(), installed.path.clone()));
+        }
+    }
+
+    plan
+}
+
+/// Download the archive of every mod the plan installs, checked against its
+/// locked hash. Mods that can't be fetched are added to the report.
+pub async fn fetch_locked(
+    plan: &SyncPlan,
+    report: &mut SyncReport,
+) -> Vec<(LockedMod, bytes::Bytes, String)> {
+    let mut downloads = Vec::new();
+    for locked in &plan.install {
+        if locked.sha256.is_none() {
+            report.warnings.push(format!(
+                "{} has no archive hash, its contents can't be checked",
+                locked.name
+            ));
+        }
+        match installer::fetch_recorded_archive(locked.source.as_deref(), locked.sha256.as_deref())
+            .await
+        {
+            Ok((data, sha256)) => downloads.push((locked.clone(), data, sha256)),
+            Err(e) => {
+                log::error!("Failed to fetch {} for sync: {}", locked.name, e);
+                report.fail(&locked.name, e);
+            }
+        }
+    }
+    downloads
+}
+
+/// Bring the mods folder in line with the lockfile: remove mods it doesn't
+/// list, extract the fetched archives into their locked folders and apply
+/// each mod's enabled state.
+pub fn apply_sync(
+    manager: &ModManager,
+    lockfile: &Lockfile,
+    plan: &SyncPlan,
+    downloads: Vec<(LockedMod, bytes::Bytes, String)>,
+    report: &mut SyncReport,
+) {
+    report.unchanged.extend(plan.unchanged.iter().cloned());
+
+    for (name, path) in &plan.remove {
+        match manager.force_remove_mod(name, Path::new(path)) {
+            Ok(()) => report.removed.push(name.clone()),
+            Err(e) => report.fail(name, e),
+        }
+    }
+
+    for (locked, data, sha256) in downloads {
+        match install_locked(manager, &locked, data, sha256) {
+            Ok(()) => report.installed.push(locked.name.clone()),
+            Err(e) => {
+                log::error!("Failed to install {} for sync: {}", locked.name, e);
+                report.fail(&locked.name, e);
+            }
+        }
+    }
+
+    for locked in &lockfile.mods {
+        if report.failed.iter().any(|f| f.name == locked.name) {
+            continue;
+        }
+        let path = manager.mods_dir().join(&locked.folder);
+        let result = match installer::is_mod_enabled(&path) {
+            Ok(enabled) if enabled == locked.enabled => Ok(()),
+            Ok(_) => installer::set_mod_enabled(&path, locked.enabled),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            report.fail(&locked.name, e);
+        }
+    }
+
+    if let Some(expected) = &lockfile.lovely_sha256 {
+        if lovely_sha256().as_ref() != Some(expected) {
+            report
+                .warnings
+                .push("The installed lovely injector differs from the locked one".to_string());
+        }
+    }
+}
+
+fn install_locked(
+    manager: &ModManager,
+    locked: &LockedMod,
+    data: bytes::Bytes,
+    sha256: String,
+) -> Result<(), AppError> {
+    if locked.folder.contains(['/', '\\']) || locked.folder.starts_with('.') {
+        return Err(AppError::InvalidState(format!(
+            "Refusing to install into folder '{}'",
+            locked.folder
+        )));
+    }
+
+    // A mod that moved folders would otherwise be left behind next to the new copy
+    if let Ok(previous) = manager.db().get_mod_details(&locked.name) {
+        if folder_of(&previous.path).as_deref() != Some(locked.folder.as_str()) {
+            manager.force_remove_mod(&previous.name, Path::new(&previous.path))?;
+        }
+    }
+
+    let path = installer::extract_archive(manager.mods_dir(), &locked.folder, data)?;
+    match &locked.source {
+        Some(url) => manager.record_install(
+            &locked.name,
+            &path,
+            &locked.dependencies,
+            locked.version.clone(),
+            Some(&InstalledArchive {
+                path: path.clone(),
+                url: url.clone(),
+                sha256,
+            }),
+        ),
+        None => {
+            manager.record_install(
+                &locked.name,
+                &path,
+                &locked.dependencies,
+                locked.version.clone(),
+                None,
+            )?;
+            manager.db().set_mod_checksum(&locked.name, &sha256)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn installed(name: &str, sha256: Option<&str>) -> InstalledMod {
+        InstalledMod {
+            name: name.to_string(),
+            path: format!("/mods/{}", name),
+            dependencies: Vec::new(),
+            current_version: Some("1.0.0".to_string()),
+            archive_sha256: sha256.map(str::to_string),
+            download_url: Some(format!("https://example.com/{}.zip", name)),
+            pinned: false,
+        }
+    }
+
+    #[test]
+    fn test_round_trip_and_plan_sync() -> Result<(), AppError> {
+        let hash_a = "a".repeat(64);
+        let hash_b = "b".repeat(64);
+        let current = vec![
+            installed("Same", Some(&hash_a)),
+            installed("Changed", Some(&hash_a)),
+            installed("Broken", Some(&hash_a)),
+            installed("Extra", None),
+        ];
+
+        let mut mods: Vec<LockedMod> = current[..3]
+            .iter()
+            .filter_map(|m| lock_mod(m, &[], true))
+            .collect();
+        mods[1].sha256 = Some(hash_b);
+        mods.push(LockedMod {
+            name: "Missing".to_string(),
+            folder: "Missing".to_string(),
+            version: None,
+            source: None,
+            sha256: None,
+            enabled: false,
+            dependencies: Vec::new(),
+        });
+        let lockfile = Lockfile {
+            lock_version: LOCKFILE_VERSION,
+            steamodded: Some("1.0.0".to_string()),
+            talisman: None,
+            lovely_sha256: None,
+            mods,
+        };
+
+        let parsed = Lockfile::from_toml(&lockfile.to_toml()?)?;
+        assert_eq!(parsed.mods.len(), 4);
+        assert_eq!(parsed.mods[0].folder, "Same");
+        assert_eq!(
+            parsed.mods[0].source.as_deref(),
+            Some("https://example.com/Same.zip")
+        );
+
+        let plan = plan_sync(&parsed, &current, |m| m.name != "Broken");
+        assert_eq!(plan.unchanged, vec!["Same"]);
+        let install: Vec<_> = plan.install.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(install, vec!["Changed", "Broken", "Missing"]);
+        assert_eq!(plan.remove.len(), 1);
+        assert_eq!(plan.remove[0].0, "Extra");
+        Ok(())
+    }
+}
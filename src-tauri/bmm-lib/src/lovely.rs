@@ -1,3 +1,4 @@
+use crate::download;
 use crate::errors::AppError;
 #[cfg(any(target_os = "windows", target_os = "linux"))]
 use std::fs::File;
@@ -94,6 +95,23 @@ fn detect_architecture() -> Result<&'static str, AppError> {
     }
 }
 
+/// Where the lovely injector lives if it has already been installed. Nothing
+/// is downloaded.
+pub fn installed_lovely_path() -> Option<PathBuf> {
+    #[cfg(target_os = "macos")]
+    let path = dirs::config_dir().map(|dir| dir.join("Balatro/bins/liblovely.dylib"));
+
+    #[cfg(any(target_os = "windows", target_os = "linux"))]
+    let path = crate::finder::get_balatro_paths()
+        .first()
+        .map(|game_path| game_path.join("version.dll"));
+
+    #[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+    let path: Option<PathBuf> = None;
+
+    path.filter(|p| p.exists())
+}
+
 pub async fn ensure_lovely_exists() -> Result<PathBuf, AppError> {
     #[cfg(target_os = "macos")]
     {
@@ -167,10 +185,8 @@ async fn download_and_install_lovely(target_path: &Path) -> Result<(), AppError>
     );
 
     // Download latest release
-    let client = reqwest::Client::new();
-    let response = client
-        .get(url)
-        .send()
+    let bytes = download::shared()
+        .get(&url)
         .await
         .map_err(|e| AppError::Network(e.to_string()))?;
 
@@ -181,10 +197,6 @@ async fn download_and_install_lovely(target_path: &Path) -> Result<(), AppError>
         source: e.to_string(),
     })?;
 
-    let bytes = response
-        .bytes()
-        .await
-        .map_err(|e| AppError::Network(e.to_string()))?;
     std::io::copy(&mut bytes.as_ref(), &mut file).map_err(|e| AppError::FileWrite {
         path: temp_tar_gz.clone(),
         source: e.to_string(),
@@ -234,10 +246,8 @@ async fn download_version_dll(outfile: &mut File) -> Result<(), AppError> {
     log::info!("Downloading lovely injector for Linux/Proton from {}", url);
 
     // Download the ZIP file
-    let client = reqwest::Client::new();
-    let response = client
+    let bytes = download::shared()
         .get(url)
-        .send()
         .await
         .map_err(|e| AppError::Network(format!("Failed to download lovely injector: {}", e)))?;
 
@@ -248,11 +258,6 @@ async fn download_version_dll(outfile: &mut File) -> Result<(), AppError> {
         source: e.to_string(),
     })?;
 
-    let bytes = response
-        .bytes()
-        .await
-        .map_err(|e| AppError::Network(format!("Failed to read download response: {}", e)))?;
-
     std::io::copy(&mut bytes.as_ref(), &mut file).map_err(|e| AppError::FileWrite {
         path: temp_zip.clone(),
         source: e.to_string(),
@@ -0,0 +1,994 @@
+use crate::archive_store::{ArchiveStore, StoredArchive};
+use crate::checksum;
+use crate::database::{Database, VersionRecord};
+use crate::errors::AppError;
+use crate::installer::{self, InstalledArchive};
+use crate::manifest::{self, VerifyReport};
+use crate::profiles::{Profile, ProfileMod};
+use flate2::read::GzDecoder;
+use serde_json::json;
+use std::collections::HashSet;
+use std::fs;
+use std::io::{Cursor, Read, Seek};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tar::Archive;
+use walkdir::WalkDir;
+use zip::ZipArchive;
+
+/// Everything the app does to the mods folder, independent of the GUI. The
+/// Tauri commands and the CLI are thin wrappers around this.
+pub struct ModManager {
+    db: Database,
+    mods_dir: PathBuf,
+    backup_dir: PathBuf,
+    archive_dir: Option<PathBuf>,
+}
+
+enum ArchiveKind {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+impl ArchiveKind {
+    fn from_file_name(file_name: &str) -> Option<Self> {
+        if file_name.ends_with(".zip") {
+            Some(ArchiveKind::Zip)
+        } else if file_name.ends_with(".tar") {
+            Some(ArchiveKind::Tar)
+        } else if file_name.ends_with(".tar.gz") || file_name.ends_with(".tgz") {
+            Some(ArchiveKind::TarGz)
+        } else {
+            None
+        }
+    }
+}
+
+impl ModManager {
+    pub fn new(db: Database, mods_dir: PathBuf) -> Self {
+        Self {
+            db,
+            mods_dir,
+            backup_dir: std::env::temp_dir().join("balatro_mod_manager_backups"),
+            archive_dir: None,
+        }
+    }
+
+    pub fn with_backup_dir(mut self, backup_dir: PathBuf) -> Self {
+        self.backup_dir = backup_dir;
+        self
+    }
+
+    pub fn with_archive_dir(mut self, archive_dir: PathBuf) -> Self {
+        self.archive_dir = Some(archive_dir);
+        self
+    }
+
+    pub fn db(&self) -> &Database {
+        &self.db
+    }
+
+    pub fn mods_dir(&self) -> &Path {
+        &self.mods_dir
+    }
+
+    /// Register a freshly installed mod together with the archive it came
+    /// from and a manifest of the files it put on disk. The version it
+    /// replaces, if any, is kept in the mod's history for `rollback_mod`.
+    pub fn record_install(
+        &self,
+        name: &str,
+        path: &Path,
+        dependencies: &[String],
+        version: Option<String>,
+        source: Option<&InstalledArchive>,
+    ) -> Result<(), AppError> {
+        let manifest = manifest::build_manifest(path)?;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+
+        // One transaction, so a failure can't leave a new version next to
+        // the old archive details
+        self.db.transaction(|conn| {
+            if let Ok(previous) = Database::get_mod_details_in(conn, name) {
+                let replaced = previous.current_version != version
+                    || previous.archive_sha256.as_deref() != source.map(|s| s.sha256.as_str());
+                if replaced
+                    && (previous.archive_sha256.is_some() || previous.download_url.is_some())
+                {
+                    Database::push_version_history_in(
+                        conn,
+                        name,
+                        previous.current_version.as_deref(),
+                        previous.download_url.as_deref(),
+                        previous.archive_sha256.as_deref(),
+                        now,
+                    )?;
+                }
+            }
+
+            Database::add_installed_mod_in(
+                conn,
+                name,
+                &path.to_string_lossy(),
+                dependencies,
+                version,
+            )?;
+            if let Some(source) = source {
+                Database::set_mod_checksum_in(conn, name, &source.sha256)?;
+                Database::set_mod_download_url_in(conn, name, &source.url)?;
+            }
+            Database::set_mod_manifest_in(conn, name, &manifest)
+        })
+    }
+
+    /// The version `rollback_mod` would go back to.
+    pub fn rollback_target(&self, name: &str) -> Result<VersionRecord, AppError> {
+        self.db
+            .get_version_history(name)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| {
+                AppError::InvalidState(format!("No previous version of {} recorded", name))
+            })
+    }
+
+    /// Replace a mod with the archive of its previous version, as fetched for
+    /// `rollback_target`. The rolled back version leaves the history, so
+    /// repeated rollbacks keep going further back.
+    pub fn rollback_mod(
+        &self,
+        name: &str,
+        target: &VersionRecord,
+        data: bytes::Bytes,
+    ) -> Result<(), AppError> {
+        let mod_details = self.db.get_mod_details(name)?;
+        let path = PathBuf::from(&mod_details.path);
+        let folder_name = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .ok_or(AppError::PathConversionError)?;
+        let was_enabled = installer::is_mod_enabled(&path).unwrap_or(true);
+
+        log::info!(
+            "Rolling back {} from {:?} to {:?}",
+            name,
+            mod_details.current_version,
+            target.version
+        );
+        let sha256 = checksum::sha256_hex(&data);
+        installer::extract_archive(&self.mods_dir, folder_name, data)?;
+        if !was_enabled {
+            installer::set_mod_enabled(&path, false)?;
+        }
+
+        let manifest = manifest::build_manifest(&path)?;
+        self.db.transaction(|conn| {
+            Database::add_installed_mod_in(
+                conn,
+                name,
+                &mod_details.path,
+                &mod_details.dependencies,
+                target.version.clone(),
+            )?;
+            Database::set_mod_checksum_in(conn, name, &sha256)?;
+            if let Some(url) = &target.download_url {
+                Database::set_mod_download_url_in(conn, name, url)?;
+            }
+            Database::set_mod_manifest_in(conn, name, &manifest)?;
+            Database::remove_version_history_entry_in(conn, target.id)
+        })
+    }
+
+    /// Compare a mod folder against the manifest recorded at install time.
+    pub fn verify_mod(&self, name: &str) -> Result<VerifyReport, AppError> {
+        let mod_details = self.db.get_mod_details(name)?;
+        let recorded = self.db.get_mod_manifest(name)?;
+        if recorded.is_empty() {
+            return Err(AppError::InvalidState(format!(
+                "No file manifest recorded for {}",
+                name
+            )));
+        }
+
+        let on_disk = manifest::build_manifest(Path::new(&mod_details.path))?;
+        Ok(manifest::compare(&recorded, &on_disk))
+    }
+
+    /// Re-extract a mod from its cached archive, keeping it disabled if it was.
+    /// Returns the verification result after the repair.
+    pub fn repair_mod(&self, name: &str) -> Result<VerifyReport, AppError> {
+        let mod_details = self.db.get_mod_details(name)?;
+        let sha256 = mod_details.archive_sha256.ok_or_else(|| {
+            AppError::InvalidState(format!("{} was not installed from an archive", name))
+        })?;
+        let data = self.archives()?.get(&sha256)?.ok_or_else(|| {
+            AppError::InvalidState(format!(
+                "The archive for {} is no longer cached, reinstall it instead",
+                name
+            ))
+        })?;
+
+        let path = PathBuf::from(&mod_details.path);
+        let folder_name = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .ok_or(AppError::PathConversionError)?;
+        let was_enabled = installer::is_mod_enabled(&path).unwrap_or(true);
+
+        log::info!("Repairing {} from cached archive {}", name, sha256);
+        installer::extract_archive(&self.mods_dir, folder_name, data.into())?;
+        if !was_enabled {
+            installer::set_mod_enabled(&path, false)?;
+        }
+
+        self.verify_mod(name)
+    }
+
+    /// Capture the installed mods and their enabled state as a profile. Pinned
+    /// mods keep their current version.
+    pub fn snapshot_profile(&self, name: &str) -> Result<Profile, AppError> {
+        let mut mods = Vec::new();
+        for installed in self.db.get_installed_mods()? {
+            let Ok(enabled) = installer::is_mod_enabled(Path::new(&installed.path)) else {
+                log::warn!(
+                    "Leaving {} out of the profile, its folder is missing",
+                    installed.name
+                );
+                continue;
+            };
+            mods.push(ProfileMod {
+                version: installed
+                    .current_version
+                    .filter(|v| installed.pinned && !v.is_empty()),
+                name: installed.name,
+                enabled,
+            });
+        }
+        Ok(Profile {
+            name: name.to_string(),
+            mods,
+        })
+    }
+
+    pub fn list_archives(&self) -> Result<Vec<StoredArchive>, AppError> {
+        self.archives()?.list()
+    }
+
+    /// Shrink the archive cache to `max_bytes`. Archives that installed mods
+    /// were extracted from are kept so they can still be repaired offline.
+    pub fn prune_archives(&self, max_bytes: u64) -> Result<Vec<StoredArchive>, AppError> {
+        let mut keep = HashSet::new();
+        for installed in self.db.get_installed_mods()? {
+            // Earlier versions too, so rolling back works offline
+            for record in self.db.get_version_history(&installed.name)? {
+                keep.extend(record.archive_sha256);
+            }
+            keep.extend(installed.archive_sha256);
+        }
+        self.archives()?.prune(max_bytes, &keep)
+    }
+
+    pub fn is_mod_enabled(&self, mod_name: &str) -> Result<bool, AppError> {
+        let mod_details = self.db.get_mod_details(mod_name)?;
+        installer::is_mod_enabled(Path::new(&mod_details.path))
+    }
+
+    pub fn set_mod_enabled(&self, mod_name: &str, enabled: bool) -> Result<(), AppError> {
+        let mod_details = self.db.get_mod_details(mod_name)?;
+        installer::set_mod_enabled(Path::new(&mod_details.path), enabled)
+    }
+
+    // Installed mods that list `mod_name` as a dependency, excluding itself
+    pub fn get_dependents(&self, mod_name: &str) -> Result<Vec<String>, AppError> {
+        Ok(self
+            .db
+            .get_dependents(mod_name)?
+            .into_iter()
+            .filter(|dep| dep != mod_name)
+            .collect())
+    }
+
+    /// Remove a single mod. Frameworks that other mods still depend on are
+    /// refused; use `cascade_uninstall` for those.
+    pub fn remove_mod(&self, name: &str, path: &Path) -> Result<(), AppError> {
+        let is_framework =
+            name.eq_ignore_ascii_case("steamodded") || name.eq_ignore_ascii_case("talisman");
+
+        if is_framework {
+            let dependents = self.get_dependents(name)?;
+            if !dependents.is_empty() {
+                return Err(AppError::InvalidState(format!(
+                    "Use cascade_uninstall to remove {} with {} dependents",
+                    name,
+                    dependents.len()
+                )));
+            }
+        }
+
+        self.force_remove_mod(name, path)
+    }
+
+    pub fn force_remove_mod(&self, name: &str, path: &Path) -> Result<(), AppError> {
+        installer::remove_mod_dir(&self.mods_dir, path.to_path_buf())?;
+        self.db.remove_installed_mod(name)
+    }
+
+    /// Remove `root_mod` and everything that depends on it, directly or not.
+    /// Returns the names that were removed.
+    pub fn cascade_uninstall(&self, root_mod: &str) -> Result<Vec<String>, AppError> {
+        let mut to_uninstall = vec![root_mod.to_string()];
+        let mut processed = HashSet::new();
+        let mut removed = Vec::new();
+
+        while let Some(current) = to_uninstall.pop() {
+            if !processed.insert(current.clone()) {
+                continue;
+            }
+
+            let mod_details = self.db.get_mod_details(&current)?;
+
+            // Add dependents to queue
+            to_uninstall.extend(self.db.get_dependents(&current)?);
+
+            installer::remove_mod_dir(&self.mods_dir, PathBuf::from(mod_details.path))?;
+            self.db.remove_installed_mod(&current)?;
+            removed.push(current);
+        }
+
+        Ok(removed)
+    }
+
+    /// Drop database entries whose folder no longer exists. Returns
+    /// `(files_removed, entries_cleaned)`; no files are ever removed.
+    pub fn reindex(&self) -> Result<(usize, usize), AppError> {
+        let installed_mods = self.db.get_installed_mods()?;
+        let mut cleaned_entries = 0;
+
+        for installed_mod in installed_mods {
+            if !PathBuf::from(&installed_mod.path).exists() {
+                self.db.remove_installed_mod(&installed_mod.name)?;
+                cleaned_entries += 1;
+            }
+        }
+
+        Ok((0, cleaned_entries))
+    }
+
+    // Delete anything in the mods folder that the database doesn't know about
+    pub fn refresh_mods_folder(&self) -> Result<(), AppError> {
+        let installed_mods = self.db.get_installed_mods()?;
+
+        let entries = fs::read_dir(&self.mods_dir).map_err(|e| AppError::FileRead {
+            path: self.mods_dir.clone(),
+            source: e.to_string(),
+        })?;
+
+        for entry in entries {
+            let entry = entry.map_err(|e| AppError::FileRead {
+                path: self.mods_dir.clone(),
+                source: e.to_string(),
+            })?;
+            let path = entry.path();
+            let name = path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .ok_or_else(|| AppError::InvalidState("Invalid filename".to_string()))?;
+
+            if name.contains("lovely") || installed_mods.iter().any(|m| m.path.contains(name)) {
+                continue;
+            }
+
+            let ft = entry.file_type().map_err(|e| AppError::FileRead {
+                path: path.clone(),
+                source: e.to_string(),
+            })?;
+
+            let result = if ft.is_dir() {
+                fs::remove_dir_all(&path)
+            } else if ft.is_file() {
+                fs::remove_file(&path)
+            } else {
+                continue;
+            };
+            result.map_err(|e| AppError::FileWrite {
+                path: path.clone(),
+                source: e.to_string(),
+            })?;
+        }
+        Ok(())
+    }
+
+    // Delete a mod that was installed by hand and isn't tracked in the database
+    pub fn delete_manual_mod(&self, path: &Path) -> Result<(), AppError> {
+        if !path.exists() {
+            return Err(AppError::invalid_path(path, "Path doesn't exist"));
+        }
+
+        // Security check: Make sure the path is within the Mods directory
+        let canonicalized_path = path
+            .canonicalize()
+            .map_err(|e| AppError::invalid_path(path, e.to_string()))?;
+        let canonicalized_mods_dir = self
+            .mods_dir
+            .canonicalize()
+            .map_err(|e| AppError::invalid_path(&self.mods_dir, e.to_string()))?;
+
+        if !canonicalized_path.starts_with(&canonicalized_mods_dir) {
+            return Err(AppError::invalid_path(
+                path,
+                "Path is outside of the mods directory",
+            ));
+        }
+
+        log::info!("Deleting manual mod at path: {}", path.display());
+
+        let result = if path.is_dir() {
+            fs::remove_dir_all(path)
+        } else {
+            fs::remove_file(path)
+        };
+        result.map_err(|e| AppError::FileWrite {
+            path: path.to_path_buf(),
+            source: e.to_string(),
+        })?;
+
+        log::info!("Successfully removed {}", path.display());
+        Ok(())
+    }
+
+    /// Install a mod from an archive on disk, e.g. one dropped onto the window.
+    pub fn install_archive_file(&self, archive_path: &Path) -> Result<PathBuf, AppError> {
+        let file_name = archive_path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .ok_or_else(|| AppError::invalid_path(archive_path, "Invalid file name"))?;
+
+        let file = fs::File::open(archive_path).map_err(|e| AppError::FileRead {
+            path: archive_path.to_path_buf(),
+            source: e.to_string(),
+        })?;
+
+        self.install_archive(file_name, file)
+    }
+
+    /// Install a mod from archive bytes that were read by the frontend.
+    pub fn install_archive_bytes(
+        &self,
+        file_name: &str,
+        data: Vec<u8>,
+    ) -> Result<PathBuf, AppError> {
+        self.install_archive(file_name, Cursor::new(data))
+    }
+
+    fn install_archive<R: Read + Seek>(
+        &self,
+        file_name: &str,
+        reader: R,
+    ) -> Result<PathBuf, AppError> {
+        let kind = ArchiveKind::from_file_name(file_name).ok_or_else(|| {
+            AppError::InvalidState(
+                "Unsupported file format. Only ZIP, TAR, and TAR.GZ are supported.".to_string(),
+            )
+        })?;
+
+        // Determine the name of the mod (without extension)
+        let mod_name = file_name
+            .trim_end_matches(".zip")
+            .trim_end_matches(".tar")
+            .trim_end_matches(".tar.gz")
+            .trim_end_matches(".tgz");
+        let mod_dir = self.mods_dir.join(mod_name);
+
+        // Extract and validate off to the side; the installed copy is only
+        // replaced once the new one is known to be good
+        let staging = installer::create_staging_dir(&self.mods_dir)?;
+        let staged_dir = staging.path().join(mod_name);
+
+        match kind {
+            ArchiveKind::Zip => extract_zip(reader, &staged_dir)?,
+            ArchiveKind::Tar => extract_tar(reader, &staged_dir)?,
+            ArchiveKind::TarGz => extract_tar(GzDecoder::new(reader), &staged_dir)?,
+        }
+
+        flatten_single_root(&staged_dir)?;
+
+        if !contains_lua_files(&staged_dir) {
+            return Err(AppError::InvalidState(
+                "No Lua files found in the archive. This doesn't appear to be a valid Balatro mod."
+                    .to_string(),
+            ));
+        }
+
+        installer::swap_into_place(&staged_dir, &mod_dir, staging.path())?;
+        Ok(mod_dir)
+    }
+
+    /// Copy a mod aside so it can be restored after an install replaces it.
+    pub fn backup_local_mod(&self, path: &Path) -> Result<(), AppError> {
+        if !path.exists() {
+            return Err(AppError::invalid_path(path, "Path doesn't exist"));
+        }
+
+        let file_name = path
+            .file_name()
+            .ok_or_else(|| AppError::invalid_path(path, "Path has no file name"))?;
+        let backup_time = SystemTime::now().duration_since(UNIX_EPOCH)?;
+
+        // Create a unique backup ID
+        let backup_path = self
+            .backup_dir()?
+            .join(format!("backup_{}", backup_time.as_millis()));
+        fs::create_dir_all(&backup_path).map_err(|e| AppError::DirCreate {
+            path: backup_path.clone(),
+            source: e.to_string(),
+        })?;
+
+        if path.is_dir() {
+            copy_dir_all(path, &backup_path.join(file_name))?;
+        } else {
+            copy_file(path, &backup_path.join(file_name))?;
+        }
+
+        // Store the original path in a metadata file for restoration
+        let metadata = json!({
+            "original_path": path.to_string_lossy().to_string(),
+            "backup_time": backup_time.as_secs()
+        });
+
+        let metadata_path = backup_path.join("metadata.json");
+        fs::write(&metadata_path, serde_json::to_string_pretty(&metadata)?).map_err(|e| {
+            AppError::FileWrite {
+                path: metadata_path,
+                source: e.to_string(),
+            }
+        })
+    }
+
+    // Restore the most recent backup taken of `path`
+    pub fn restore_from_backup(&self, path: &Path) -> Result<(), AppError> {
+        let backup_path = self
+            .find_backups(path)?
+            .into_iter()
+            .max_by_key(|(_, backup_time)| *backup_time)
+            .map(|(backup_path, _)| backup_path)
+            .ok_or_else(|| AppError::InvalidState("No backup found for this path".to_string()))?;
+
+        let parent = path
+            .parent()
+            .ok_or_else(|| AppError::invalid_path(path, "Path has no parent directory"))?;
+        fs::create_dir_all(parent).map_err(|e| AppError::DirCreate {
+            path: parent.to_path_buf(),
+            source: e.to_string(),
+        })?;
+
+        for entry in read_dir_entries(&backup_path)? {
+            let file_name = entry.file_name();
+
+            // Skip metadata file
+            if file_name == "metadata.json" {
+                continue;
+            }
+
+            let dest_path = parent.join(&file_name);
+            if entry.path().is_dir() {
+                copy_dir_all(&entry.path(), &dest_path)?;
+            } else {
+                copy_file(&entry.path(), &dest_path)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn remove_backup(&self, path: &Path) -> Result<(), AppError> {
+        for (backup_path, _) in self.find_backups(path)? {
+            fs::remove_dir_all(&backup_path).map_err(|e| AppError::FileWrite {
+                path: backup_path.clone(),
+                source: e.to_string(),
+            })?;
+        }
+        Ok(())
+    }
+
+    fn archives(&self) -> Result<ArchiveStore, AppError> {
+        match &self.archive_dir {
+            Some(dir) => Ok(ArchiveStore::new(dir.clone())),
+            None => ArchiveStore::open_default(),
+        }
+    }
+
+    fn backup_dir(&self) -> Result<PathBuf, AppError> {
+        fs::create_dir_all(&self.backup_dir).map_err(|e| AppError::DirCreate {
+            path: self.backup_dir.clone(),
+            source: e.to_string(),
+        })?;
+        Ok(self.backup_dir.clone())
+    }
+
+    // Every backup of `path` with the time it was taken
+    fn find_backups(&self, path: &Path) -> Result<Vec<(PathBuf, u64)>, AppError> {
+        let mut backups = Vec::new();
+
+        for entry in read_dir_entries(&self.backup_dir()?)? {
+            let metadata_path = entry.path().join("metadata.json");
+            if !metadata_path.exists() {
+                continue;
+            }
+
+            let content = fs::read_to_string(&metadata_path).map_err(|e| AppError::FileRead {
+                path: metadata_path.clone(),
+                source: e.to_string(),
+            })?;
+            let metadata: serde_json::Value =
+                serde_json::from_str(&content).map_err(|e| AppError::JsonParse {
+                    path: metadata_path.clone(),
+                    source: e.to_string(),
+                })?;
+
+            let original_path = metadata.get("original_path").and_then(|v| v.as_str());
+            if original_path == Some(path.to_string_lossy().as_ref()) {
+                let backup_time = metadata
+                    .get("backup_time")
+                    .and_then(|v| v.as_u64())
+                    .unwrap_or_default();
+                backups.push((entry.path(), backup_time));
+            }
+        }
+
+        Ok(backups)
+    }
+}
+
+fn read_dir_entries(dir: &Path) -> Result<Vec<fs::DirEntry>, AppError> {
+    fs::read_dir(dir)
+        .and_then(|entries| entries.collect())
+        .map_err(|e| AppError::FileRead {
+            path: dir.to_path_buf(),
+            source: e.to_string(),
+        })
+}
+
+fn extract_zip<R: Read + Seek>(reader: R, target_dir: &Path) -> Result<(), AppError> {
+    fs::create_dir_all(target_dir).map_err(|e| AppError::DirCreate {
+        path: target_dir.to_path_buf(),
+        source: e.to_string(),
+    })?;
+
+    let mut archive = ZipArchive::new(reader).map_err(|e| AppError::FileRead {
+        path: target_dir.to_path_buf(),
+        source: format!("Failed to open ZIP archive: {}", e),
+    })?;
+
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i).map_err(|e| AppError::FileRead {
+            path: target_dir.to_path_buf(),
+            source: format!("Failed to access file in archive: {}", e),
+        })?;
+
+        let file_path = match file.enclosed_name() {
+            Some(path) => path.to_owned(),
+            None => continue,
+        };
+        let output_path = target_dir.join(&file_path);
+
+        if file.is_dir() {
+            fs::create_dir_all(&output_path).map_err(|e| AppError::DirCreate {
+                path: output_path.clone(),
+                source: e.to_string(),
+            })?;
+        } else {
+            if let Some(parent) = output_path.parent() {
+                fs::create_dir_all(parent).map_err(|e| AppError::DirCreate {
+                    path: parent.to_path_buf(),
+                    source: e.to_string(),
+                })?;
+            }
+
+            let mut outfile = fs::File::create(&output_path).map_err(|e| AppError::FileWrite {
+                path: output_path.clone(),
+                source: e.to_string(),
+            })?;
+            std::io::copy(&mut file, &mut outfile).map_err(|e| AppError::FileWrite {
+                path: output_path.clone(),
+                source: e.to_string(),
+            })?;
+        }
+    }
+
+    Ok(())
+}
+
+fn extract_tar<R: Read>(reader: R, target_dir: &Path) -> Result<(), AppError> {
+    fs::create_dir_all(target_dir).map_err(|e| AppError::DirCreate {
+        path: target_dir.to_path_buf(),
+        source: e.to_string(),
+    })?;
+
+    let mut archive = Archive::new(reader);
+    let entries = archive.entries().map_err(|e| AppError::FileRead {
+        path: target_dir.to_path_buf(),
+        source: format!("Failed to read TAR entries: {}", e),
+    })?;
+
+    for entry in entries {
+        let mut entry = entry.map_err(|e| AppError::FileRead {
+            path: target_dir.to_path_buf(),
+            source: format!("Failed to read TAR entry: {}", e),
+        })?;
+
+        // unpack_in refuses entries that would escape the target directory
+        entry
+            .unpack_in(target_dir)
+            .map_err(|e| AppError::FileWrite {
+                path: target_dir.to_path_buf(),
+                source: format!("Failed to unpack entry: {}", e),
+            })?;
+    }
+
+    Ok(())
+}
+
+// GitHub archives wrap everything in one top-level folder; move its contents up
+fn flatten_single_root(mod_dir: &Path) -> Result<(), AppError> {
+    let entries = read_dir_entries(mod_dir)?;
+    if entries.len() != 1 || !entries[0].file_type().is_ok_and(|t| t.is_dir()) {
+        return Ok(());
+    }
+
+    let nested_dir = entries[0].path();
+    for entry in read_dir_entries(&nested_dir)? {
+        let target_path = mod_dir.join(entry.file_name());
+        fs::rename(entry.path(), &target_path).map_err(|e| AppError::FileWrite {
+            path: target_path.clone(),
+            source: format!("Failed to move out of nested directory: {}", e),
+        })?;
+    }
+
+    fs::remove_dir_all(&nested_dir).map_err(|e| AppError::FileWrite {
+        path: nested_dir.clone(),
+        source: e.to_string(),
+    })
+}
+
+fn contains_lua_files(dir: &Path) -> bool {
+    WalkDir::new(dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .any(|entry| {
+            entry.file_type().is_file() && entry.path().extension().is_some_and(|ext| ext == "lua")
+        })
+}
+
+fn copy_file(src: &Path, dst: &Path) -> Result<(), AppError> {
+    fs::copy(src, dst).map_err(|e| AppError::FileCopy {
+        source: src.display().to_string(),
+        dest: dst.display().to_string(),
+        source_error: e.to_string(),
+    })?;
+    Ok(())
+}
+
+fn copy_dir_all(src: &Path, dst: &Path) -> Result<(), AppError> {
+    fs::create_dir_all(dst).map_err(|e| AppError::DirCreate {
+        path: dst.to_path_buf(),
+        source: e.to_string(),
+    })?;
+
+    for entry in read_dir_entries(src)? {
+        let path = entry.path();
+        let target = dst.join(entry.file_name());
+
+        if path.is_dir() {
+            copy_dir_all(&path, &target)?;
+        } else {
+            copy_file(&path, &target)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::tempdir;
+    use zip::write::SimpleFileOptions;
+
+    fn zip_with(files: &[(&str, &str)]) -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, content) in files {
+            writer
+                .start_file(*name, SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(content.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    fn manager_in(dir: &Path) -> Result<ModManager, AppError> {
+        let mods_dir = dir.join("Mods");
+        fs::create_dir_all(&mods_dir)?;
+        Ok(ModManager::new(Database::open_in_memory()?, mods_dir)
+            .with_backup_dir(dir.join("backups"))
+            .with_archive_dir(dir.join("archives")))
+    }
+
+    #[test]
+    fn test_install_archive_flattens_github_root() -> Result<(), AppError> {
+        let temp = tempdir()?;
+        let manager = manager_in(temp.path())?;
+
+        let data = zip_with(&[
+            ("Mod-main/main.lua", "-- mod"),
+            ("Mod-main/assets/a.txt", "a"),
+        ]);
+        let installed = manager.install_archive_bytes("CoolMod.zip", data)?;
+
+        assert_eq!(installed, manager.mods_dir().join("CoolMod"));
+        assert!(installed.join("main.lua").exists());
+        assert!(installed.join("assets/a.txt").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_install_archive_rejects_archives_without_lua() -> Result<(), AppError> {
+        let temp = tempdir()?;
+        let manager = manager_in(temp.path())?;
+
+        let data = zip_with(&[("readme.txt", "not a mod")]);
+        assert!(manager.install_archive_bytes("NotAMod.zip", data).is_err());
+        assert!(!manager.mods_dir().join("NotAMod").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_failed_reinstall_keeps_previous_version() -> Result<(), AppError> {
+        let temp = tempdir()?;
+        let manager = manager_in(temp.path())?;
+
+        let installed =
+            manager.install_archive_bytes("Kept.zip", zip_with(&[("main.lua", "-- v1")]))?;
+        let broken = zip_with(&[("readme.txt", "no lua here")]);
+        assert!(manager.install_archive_bytes("Kept.zip", broken).is_err());
+
+        assert_eq!(fs::read_to_string(installed.join("main.lua"))?, "-- v1");
+        let leftovers = fs::read_dir(temp.path())?
+            .filter_map(Result::ok)
+            .filter(|e| e.file_name().to_string_lossy().starts_with(".bmm-staging-"))
+            .count();
+        assert_eq!(leftovers, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_cascade_uninstall_removes_dependents() -> Result<(), AppError> {
+        let temp = tempdir()?;
+        let manager = manager_in(temp.path())?;
+
+        for name in ["Steamodded", "Dependent"] {
+            let path = manager.mods_dir().join(name);
+            fs::create_dir_all(&path)?;
+            let deps = if name == "Dependent" {
+                vec!["Steamodded".to_string()]
+            } else {
+                vec![]
+            };
+            manager
+                .db()
+                .add_installed_mod(name, &path.to_string_lossy(), &deps, None)?;
+        }
+
+        let path = manager.mods_dir().join("Steamodded");
+        assert!(manager.remove_mod("Steamodded", &path).is_err());
+
+        let mut removed = manager.cascade_uninstall("Steamodded")?;
+        removed.sort();
+        assert_eq!(removed, vec!["Dependent", "Steamodded"]);
+        assert!(manager.db().get_installed_mods()?.is_empty());
+        assert!(!path.exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_toggle_and_backup_round_trip() -> Result<(), AppError> {
+        let temp = tempdir()?;
+        let manager = manager_in(temp.path())?;
+
+        let path = manager.mods_dir().join("Toggled");
+        fs::create_dir_all(path.join("sub"))?;
+        fs::write(path.join("main.lua"), "-- v1")?;
+        manager
+            .db()
+            .add_installed_mod("Toggled", &path.to_string_lossy(), &[], None)?;
+
+        manager.set_mod_enabled("Toggled", false)?;
+        assert!(!manager.is_mod_enabled("Toggled")?);
+        assert!(path.join("sub/.lovelyignore").exists());
+        manager.set_mod_enabled("Toggled", true)?;
+        assert!(manager.is_mod_enabled("Toggled")?);
+
+        manager.backup_local_mod(&path)?;
+        fs::write(path.join("main.lua"), "-- v2")?;
+        manager.restore_from_backup(&path)?;
+        assert_eq!(fs::read_to_string(path.join("main.lua"))?, "-- v1");
+
+        manager.remove_backup(&path)?;
+        assert!(manager.restore_from_backup(&path).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_and_repair_from_cached_archive() -> Result<(), AppError> {
+        let temp = tempdir()?;
+        let manager = manager_in(temp.path())?;
+
+        let data = zip_with(&[("main.lua", "-- mod"), ("lib/util.lua", "-- util")]);
+        let sha256 = ArchiveStore::new(temp.path().join("archives")).put(None, &data)?;
+        let path = installer::extract_archive(manager.mods_dir(), "Repairable", data.into())?;
+        let source = InstalledArchive {
+            path: path.clone(),
+            url: "https://example.com/repairable.zip".to_string(),
+            sha256,
+        };
+        manager.record_install("Repairable", &path, &[], None, Some(&source))?;
+        manager.set_mod_enabled("Repairable", false)?;
+        assert!(manager.verify_mod("Repairable")?.is_clean());
+
+        fs::write(path.join("main.lua"), "-- edited")?;
+        fs::remove_file(path.join("lib/util.lua"))?;
+        fs::write(path.join("notes.txt"), "extra")?;
+        let report = manager.verify_mod("Repairable")?;
+        assert_eq!(report.modified, vec!["main.lua"]);
+        assert_eq!(report.missing, vec!["lib/util.lua"]);
+        assert_eq!(report.extra, vec!["notes.txt"]);
+
+        assert!(manager.repair_mod("Repairable")?.is_clean());
+        assert!(!path.join("notes.txt").exists());
+        assert!(!manager.is_mod_enabled("Repairable")?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_rollback_restores_previous_version() -> Result<(), AppError> {
+        let temp = tempdir()?;
+        let manager = manager_in(temp.path())?;
+
+        let v1 = zip_with(&[("main.lua", "-- v1")]);
+        let v2 = zip_with(&[("main.lua", "-- v2")]);
+        for (version, data) in [("1.0.0", &v1), ("1.1.0", &v2)] {
+            let path =
+                installer::extract_archive(manager.mods_dir(), "Rolled", data.clone().into())?;
+            let source = InstalledArchive {
+                path: path.clone(),
+                url: format!("https://example.com/rolled-{}.zip", version),
+                sha256: checksum::sha256_hex(data),
+            };
+            manager.record_install("Rolled", &path, &[], Some(version.into()), Some(&source))?;
+        }
+
+        // The archive of the version before is kept for an offline rollback
+        let archives = manager.archives()?;
+        archives.put(None, &v1)?;
+        archives.put(None, &v2)?;
+        assert!(manager.prune_archives(0)?.is_empty());
+
+        let target = manager.rollback_target("Rolled")?;
+        assert_eq!(target.version.as_deref(), Some("1.0.0"));
+        assert_eq!(
+            target.archive_sha256.as_deref(),
+            Some(checksum::sha256_hex(&v1).as_str())
+        );
+
+        manager.rollback_mod("Rolled", &target, v1.into())?;
+        let details = manager.db().get_mod_details("Rolled")?;
+        assert_eq!(details.current_version.as_deref(), Some("1.0.0"));
+        assert_eq!(
+            fs::read_to_string(Path::new(&details.path).join("main.lua"))?,
+            "-- v1"
+        );
+        assert!(manager.verify_mod("Rolled")?.is_clean());
+        assert!(manager.rollback_target("Rolled").is_err());
+        Ok(())
+    }
+}
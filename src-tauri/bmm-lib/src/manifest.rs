@@ -0,0 +1,105 @@
+use crate::checksum;
+use crate::errors::AppError;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use walkdir::WalkDir;
+
+/// One file of an installed mod, as it was right after installation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// Path relative to the mod folder, always with `/` separators
+    pub path: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct VerifyReport {
+    pub modified: Vec<String>,
+    pub missing: Vec<String>,
+    pub extra: Vec<String>,
+}
+
+impl VerifyReport {
+    pub fn is_clean(&self) -> bool {
+        self.modified.is_empty() && self.missing.is_empty() && self.extra.is_empty()
+    }
+}
+
+// Toggling a mod writes these, which shouldn't count as tampering
+fn is_ignored(relative: &str) -> bool {
+    relative == ".lovelyignore" || relative.ends_with("/.lovelyignore")
+}
+
+/// Hash every file below `mod_dir`. A missing folder yields an empty manifest.
+pub fn build_manifest(mod_dir: &Path) -> Result<Vec<ManifestEntry>, AppError> {
+    let mut entries = Vec::new();
+    if !mod_dir.exists() {
+        return Ok(entries);
+    }
+
+    for entry in WalkDir::new(mod_dir).follow_links(false) {
+        let entry = entry.map_err(|e| AppError::FileRead {
+            path: mod_dir.to_path_buf(),
+            source: e.to_string(),
+        })?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+
+        let relative = entry
+            .path()
+            .strip_prefix(mod_dir)
+            .map_err(|_| AppError::PathConversionError)?
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        if is_ignored(&relative) {
+            continue;
+        }
+
+        let size = entry
+            .metadata()
+            .map_err(|e| AppError::FileRead {
+                path: entry.path().to_path_buf(),
+                source: e.to_string(),
+            })?
+            .len();
+
+        entries.push(ManifestEntry {
+            path: relative,
+            size,
+            sha256: checksum::sha256_file(entry.path())?,
+        });
+    }
+
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(entries)
+}
+
+pub fn compare(expected: &[ManifestEntry], actual: &[ManifestEntry]) -> VerifyReport {
+    let on_disk: HashMap<&str, &ManifestEntry> =
+        actual.iter().map(|e| (e.path.as_str(), e)).collect();
+    let recorded: HashMap<&str, &ManifestEntry> =
+        expected.iter().map(|e| (e.path.as_str(), e)).collect();
+
+    let mut report = VerifyReport::default();
+    for entry in expected {
+        match on_disk.get(entry.path.as_str()) {
+            None => report.missing.push(entry.path.clone()),
+            Some(found) if found.size != entry.size || found.sha256 != entry.sha256 => {
+                report.modified.push(entry.path.clone())
+            }
+            Some(_) => {}
+        }
+    }
+    report.extra = actual
+        .iter()
+        .filter(|e| !recorded.contains_key(e.path.as_str()))
+        .map(|e| e.path.clone())
+        .collect();
+
+    report
+}
@@ -0,0 +1,340 @@
+use crate::errors::AppError;
+use crate::mod_collections::ModCollectionManager;
+use rusqlite::{Connection, Transaction};
+use std::path::{Path, PathBuf};
+
+/// One step of the `bmm_storage.db` schema. Steps run in order, each in its
+/// own transaction, and the last one applied is stored in `PRAGMA
+/// user_version`. Never edit a step that has shipped; add a new one instead.
+pub struct Migration {
+    pub version: u32,
+    pub description: &'static str,
+    pub up: fn(&Transaction) -> rusqlite::Result<()>,
+}
+
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "settings and installed mods",
+        up: initial_schema,
+    },
+    Migration {
+        version: 2,
+        description: "archive hashes of installed mods",
+        up: archive_hashes,
+    },
+    Migration {
+        version: 3,
+        description: "per-file manifests",
+        up: mod_manifests,
+    },
+    Migration {
+        version: 4,
+        description: "download URLs, pinning and version history",
+        up: version_history,
+    },
+    Migration {
+        version: 5,
+        description: "profiles",
+        up: profiles,
+    },
+    Migration {
+        version: 6,
+        description: "mod collections",
+        up: mod_collections,
+    },
+    Migration {
+        version: 7,
+        description: "JSON encoded settings",
+        up: json_settings,
+    },
+];
+
+/// Schema version a fully migrated database is at.
+pub fn latest_version() -> u32 {
+    MIGRATIONS.last().map_or(0, |m| m.version)
+}
+
+pub fn schema_version(conn: &Connection) -> Result<u32, AppError> {
+    Ok(conn.query_row("PRAGMA user_version", [], |row| row.get(0))?)
+}
+
+/// Apply every pending migration. Databases from before `user_version` was
+/// used start at 0; every step tolerates the tables they already have.
+pub fn migrate(conn: &mut Connection) -> Result<u32, AppError> {
+    migrate_to(conn, latest_version())
+}
+
+/// Apply pending migrations up to and including `target`.
+pub fn migrate_to(conn: &mut Connection, target: u32) -> Result<u32, AppError> {
+    apply(conn, MIGRATIONS, target)
+}
+
+/// Whether `migrate` would change anything.
+pub fn has_pending(conn: &Connection) -> Result<bool, AppError> {
+    Ok(schema_version(conn)? < latest_version())
+}
+
+fn apply(conn: &mut Connection, migrations: &[Migration], target: u32) -> Result<u32, AppError> {
+    let current = schema_version(conn)?;
+    if current > latest_version() {
+        return Err(AppError::DatabaseInit(format!(
+            "Database schema {} is newer than this version of the manager supports ({})",
+            current,
+            latest_version()
+        )));
+    }
+
+    let mut version = current;
+    for migration in migrations
+        .iter()
+        .filter(|m| m.version > current && m.version <= target)
+    {
+        log::info!(
+            "Migrating database to schema {}: {}",
+            migration.version,
+            migration.description
+        );
+        let tx = conn
+            .transaction()
+            .map_err(|e| AppError::DatabaseTransaction(e.to_string()))?;
+        (migration.up)(&tx).map_err(|e| {
+            AppError::DatabaseInit(format!(
+                "Migration {} ({}) failed: {}",
+                migration.version, migration.description, e
+            ))
+        })?;
+        // PRAGMA doesn't take bound parameters
+        tx.execute_batch(&format!("PRAGMA user_version = {}", migration.version))?;
+        tx.commit()
+            .map_err(|e| AppError::DatabaseTransaction(e.to_string()))?;
+        version = migration.version;
+    }
+
+    Ok(version)
+}
+
+/// Copy the database file aside before migrating it. The copy is named after
+/// the schema it holds, e.g. `bmm_storage.db.v4.bak`.
+pub fn backup(db_path: &Path, conn: &Connection) -> Result<PathBuf, AppError> {
+    let version = schema_version(conn)?;
+    let file_name = db_path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .ok_or(AppError::PathConversionError)?;
+    let backup_path = db_path.with_file_name(format!("{}.v{}.bak", file_name, version));
+
+    // VACUUM INTO refuses to overwrite, and an older copy of the same schema is stale anyway
+    if backup_path.exists() {
+        std::fs::remove_file(&backup_path).map_err(|e| AppError::FileWrite {
+            path: backup_path.clone(),
+            source: e.to_string(),
+        })?;
+    }
+    conn.execute("VACUUM INTO ?1", [backup_path.to_string_lossy().as_ref()])?;
+    Ok(backup_path)
+}
+
+fn has_column(tx: &Transaction, table: &str, column: &str) -> rusqlite::Result<bool> {
+    let mut stmt = tx.prepare(&format!("PRAGMA table_info({})", table))?;
+    let names = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(names.iter().any(|name| name == column))
+}
+
+fn add_column(tx: &Transaction, table: &str, column: &str, decl: &str) -> rusqlite::Result<()> {
+    if !has_column(tx, table, column)? {
+        tx.execute_batch(&format!(
+            "ALTER TABLE {} ADD COLUMN {} {}",
+            table, column, decl
+        ))?;
+    }
+    Ok(())
+}
+
+fn initial_schema(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "CREATE TABLE IF NOT EXISTS settings (
+            setting TEXT PRIMARY KEY,
+            value TEXT NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS installed_mods (
+            name TEXT PRIMARY KEY,
+            path TEXT NOT NULL
+        );
+        INSERT OR IGNORE INTO settings (setting, value) VALUES ('security_warning_acknowledged', 'no');
+        DELETE FROM settings WHERE setting = 'db_version';",
+    )?;
+    // Very old databases predate these columns
+    add_column(
+        tx,
+        "installed_mods",
+        "dependencies",
+        "TEXT NOT NULL DEFAULT '[]'",
+    )?;
+    add_column(tx, "installed_mods", "current_version", "TEXT")
+}
+
+fn archive_hashes(tx: &Transaction) -> rusqlite::Result<()> {
+    add_column(tx, "installed_mods", "archive_sha256", "TEXT")
+}
+
+fn mod_manifests(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "CREATE TABLE IF NOT EXISTS mod_manifests (
+            mod_name TEXT NOT NULL,
+            rel_path TEXT NOT NULL,
+            size INTEGER NOT NULL,
+            sha256 TEXT NOT NULL,
+            PRIMARY KEY (mod_name, rel_path)
+        );",
+    )
+}
+
+fn version_history(tx: &Transaction) -> rusqlite::Result<()> {
+    add_column(tx, "installed_mods", "download_url", "TEXT")?;
+    add_column(tx, "installed_mods", "pinned", "INTEGER NOT NULL DEFAULT 0")?;
+    tx.execute_batch(
+        "CREATE TABLE IF NOT EXISTS mod_version_history (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            mod_name TEXT NOT NULL,
+            version TEXT,
+            download_url TEXT,
+            archive_sha256 TEXT,
+            installed_at INTEGER NOT NULL
+        );",
+    )
+}
+
+fn profiles(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "CREATE TABLE IF NOT EXISTS profiles (
+            name TEXT PRIMARY KEY,
+            created_at INTEGER NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS profile_mods (
+            profile_name TEXT NOT NULL,
+            mod_name TEXT NOT NULL,
+            enabled INTEGER NOT NULL,
+            version TEXT,
+            position INTEGER NOT NULL,
+            PRIMARY KEY (profile_name, mod_name)
+        );",
+    )
+}
+
+fn mod_collections(tx: &Transaction) -> rusqlite::Result<()> {
+    // The table was declared with a hash key before it was ever used
+    if tx
+        .prepare("SELECT hash FROM mod_collections LIMIT 0")
+        .is_ok()
+    {
+        tx.execute_batch("DROP TABLE mod_collections;")?;
+    }
+    ModCollectionManager::initialize_table(tx)
+}
+
+// Settings used to be stored as "enabled"/"disabled", "yes"/"no" and bare strings
+fn json_settings(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "UPDATE settings SET value = CASE value WHEN 'enabled' THEN 'true' ELSE 'false' END
+            WHERE setting IN ('discord_rpc', 'lovely_console', 'background_enabled');
+        UPDATE settings SET value = CASE value WHEN 'yes' THEN 'true' ELSE 'false' END
+            WHERE setting = 'security_warning_acknowledged';
+        UPDATE settings SET value = json_quote(value)
+            WHERE setting IN ('installation_path', 'active_profile');",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table_exists(conn: &Connection, table: &str) -> bool {
+        conn.query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name=?1",
+            [table],
+            |row| row.get::<_, i64>(0),
+        )
+        .unwrap()
+            > 0
+    }
+
+    #[test]
+    fn test_each_step_from_empty() -> Result<(), AppError> {
+        let mut conn = Connection::open_in_memory()?;
+        for migration in MIGRATIONS {
+            assert_eq!(migrate_to(&mut conn, migration.version)?, migration.version);
+            assert_eq!(schema_version(&conn)?, migration.version);
+        }
+        assert!(!has_pending(&conn)?);
+        assert!(table_exists(&conn, "mod_collection_mods"));
+
+        // Running again is a no-op
+        assert_eq!(migrate(&mut conn)?, latest_version());
+        Ok(())
+    }
+
+    #[test]
+    fn test_legacy_database_keeps_its_data() -> Result<(), AppError> {
+        let mut conn = Connection::open_in_memory()?;
+        conn.execute_batch(
+            "CREATE TABLE settings (setting TEXT PRIMARY KEY, value TEXT NOT NULL);
+            CREATE TABLE installed_mods (
+                name TEXT PRIMARY KEY,
+                path TEXT NOT NULL,
+                dependencies TEXT NOT NULL DEFAULT '[]',
+                current_version TEXT
+            );
+            INSERT INTO settings VALUES ('db_version', '1.1'), ('installation_path', '/games/Balatro');
+            INSERT INTO installed_mods VALUES ('Cryptid', '/mods/Cryptid', '[\"Steamodded\"]', '0.5.2');",
+        )?;
+
+        migrate(&mut conn)?;
+
+        let (version, pinned): (String, bool) = conn.query_row(
+            "SELECT current_version, pinned FROM installed_mods WHERE name = 'Cryptid'",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        assert_eq!(version, "0.5.2");
+        assert!(!pinned);
+        let settings: i64 =
+            conn.query_row("SELECT COUNT(*) FROM settings", [], |row| row.get(0))?;
+        // installation_path kept, db_version dropped, warning default added
+        assert_eq!(settings, 2);
+        let path: String = conn.query_row(
+            "SELECT value FROM settings WHERE setting = 'installation_path'",
+            [],
+            |row| row.get(0),
+        )?;
+        assert_eq!(path, "\"/games/Balatro\"");
+        Ok(())
+    }
+
+    #[test]
+    fn test_failed_step_is_rolled_back() -> Result<(), AppError> {
+        fn broken(tx: &Transaction) -> rusqlite::Result<()> {
+            tx.execute_batch("CREATE TABLE half_done (id INTEGER); SELECT * FROM missing;")
+        }
+        let migrations = [
+            Migration {
+                version: 1,
+                description: "settings and installed mods",
+                up: initial_schema,
+            },
+            Migration {
+                version: 2,
+                description: "broken",
+                up: broken,
+            },
+        ];
+
+        let mut conn = Connection::open_in_memory()?;
+        assert!(apply(&mut conn, &migrations, 2).is_err());
+        assert_eq!(schema_version(&conn)?, 1);
+        assert!(!table_exists(&conn, "half_done"));
+        Ok(())
+    }
+}
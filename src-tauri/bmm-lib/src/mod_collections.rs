@@ -1,130 +1,497 @@
-use rusqlite::{Connection, Result};
-use std::collections::HashMap;
-use std::hash::{Hash, Hasher};
-use std::path::PathBuf;
+use crate::cache::Mod;
+use crate::checksum;
+use crate::database::InstalledMod;
+use crate::errors::AppError;
+use crate::index;
+use crate::installer::{self, InstalledArchive};
+use crate::version::Version;
+use rusqlite::{params, Connection, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
 
-#[derive(Clone)]
+/// Bumped when the exported file layout changes incompatibly.
+pub const COLLECTION_FORMAT_VERSION: u32 = 1;
+
+/// A shareable list of mods pinned to the exact versions and downloads they
+/// were installed from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ModCollection {
+    pub id: String,
     pub name: String,
-    pub path: PathBuf,
-    pub hash: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub created_at: u64,
+    #[serde(default)]
+    pub mods: Vec<CollectionMod>,
 }
 
-impl ModCollection {
-    pub fn new(name: String, path: PathBuf) -> Self {
-        use std::hash::DefaultHasher;
-        let mut hasher = DefaultHasher::new();
-        name.hash(&mut hasher);
-        path.to_string_lossy().hash(&mut hasher);
-        let hash = hasher.finish();
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CollectionMod {
+    pub name: String,
+    /// Title of the mod in the index, if it came from there.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub catalog_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub download_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+}
 
-        Self { name, path, hash }
-    }
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CollectionFormat {
+    Json,
+    Toml,
 }
 
-pub struct ModCollectionManager {
-    collections: HashMap<u64, ModCollection>,
+// On-disk layout of an exported collection
+#[derive(Serialize, Deserialize)]
+struct CollectionFile {
+    format_version: u32,
+    #[serde(flatten)]
+    collection: ModCollection,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct CollectionReport {
+    pub installed: Vec<String>,
+    pub unchanged: Vec<String>,
+    pub failed: Vec<CollectionFailure>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CollectionFailure {
+    pub name: String,
+    pub error: String,
 }
 
-impl Default for ModCollectionManager {
-    fn default() -> Self {
-        Self::new()
+impl CollectionFormat {
+    /// Pick the format from a file extension, defaulting to JSON.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("toml") => CollectionFormat::Toml,
+            _ => CollectionFormat::Json,
+        }
     }
 }
 
-impl ModCollectionManager {
-    pub fn new() -> Self {
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+impl ModCollection {
+    /// A new collection with an id derived from its name and creation time.
+    /// The id is a SHA-256 prefix, so it is the same on every platform and
+    /// toolchain and survives export and import.
+    pub fn new(name: String, mods: Vec<CollectionMod>) -> Self {
         Self {
-            collections: HashMap::new(),
+            id: Self::new_id(&name),
+            name,
+            description: None,
+            created_at: now(),
+            mods,
         }
     }
 
+    pub(crate) fn new_id(name: &str) -> String {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        checksum::sha256_hex(format!("{}\n{}", name, nanos).as_bytes())[..16].to_string()
+    }
+
+    /// Capture installed mods, matching them to catalog entries where possible.
+    pub fn from_installed(name: String, installed_mods: &[InstalledMod], catalog: &[Mod]) -> Self {
+        let mods = installed_mods
+            .iter()
+            .map(|installed| CollectionMod {
+                name: installed.name.clone(),
+                catalog_id: index::find_catalog_mod(catalog, &installed.name)
+                    .map(|m| m.title.clone()),
+                version: installed.current_version.clone().filter(|v| !v.is_empty()),
+                download_url: installed.download_url.clone(),
+                sha256: installed.archive_sha256.clone(),
+            })
+            .collect();
+        Self::new(name, mods)
+    }
+
+    pub fn export(&self, format: CollectionFormat) -> Result<String, AppError> {
+        let file = CollectionFile {
+            format_version: COLLECTION_FORMAT_VERSION,
+            collection: self.clone(),
+        };
+        match format {
+            CollectionFormat::Json => Ok(serde_json::to_string_pretty(&file)?),
+            CollectionFormat::Toml => {
+                toml::to_string_pretty(&file).map_err(|e| AppError::Serialization {
+                    format: "TOML".to_string(),
+                    source: e.to_string(),
+                })
+            }
+        }
+    }
+
+    pub fn import(content: &str, format: CollectionFormat) -> Result<Self, AppError> {
+        let file: CollectionFile = match format {
+            CollectionFormat::Json => serde_json::from_str(content)?,
+            CollectionFormat::Toml => {
+                toml::from_str(content).map_err(|e| AppError::Serialization {
+                    format: "TOML".to_string(),
+                    source: e.to_string(),
+                })?
+            }
+        };
+
+        if file.format_version > COLLECTION_FORMAT_VERSION {
+            return Err(AppError::InvalidState(format!(
+                "Collection format {} is newer than this version of the manager supports",
+                file.format_version
+            )));
+        }
+        let collection = file.collection;
+        if collection.id.is_empty() || collection.name.trim().is_empty() {
+            return Err(AppError::InvalidState(
+                "Collection is missing an id or name".to_string(),
+            ));
+        }
+        if let Some(bad) = collection
+            .mods
+            .iter()
+            .filter_map(|m| m.sha256.as_deref())
+            .find(|h| !checksum::is_sha256_hex(&checksum::normalize_digest(h)))
+        {
+            return Err(AppError::InvalidState(format!(
+                "Collection has an invalid archive hash: {}",
+                bad
+            )));
+        }
+        Ok(collection)
+    }
+}
+
+fn is_installed_at(installed_mods: &[InstalledMod], entry: &CollectionMod) -> bool {
+    installed_mods.iter().any(|m| {
+        let same_name = m.name.eq_ignore_ascii_case(&entry.name);
+        let same_archive = match (&entry.sha256, &m.archive_sha256) {
+            (Some(expected), Some(actual)) => checksum::normalize_digest(expected) == *actual,
+            _ => match (&entry.version, &m.current_version) {
+                (Some(expected), Some(actual)) => {
+                    Version::parse(expected).is_some_and(|v| Version::parse(actual) == Some(v))
+                        || expected == actual
+                }
+                (None, _) => true,
+                (Some(_), None) => false,
+            },
+        };
+        same_name && same_archive
+    })
+}
+
+/// Install every mod of a collection that isn't already installed in the
+/// same version. Recorded downloads are fetched as-is and checked against
+/// their hash; entries without one fall back to the catalog. `record` is
+/// called after each successful install with the entry, the archive, the
+/// installed version and the mod's dependencies.
+pub async fn install_collection<F>(
+    installation_path: Option<&String>,
+    collection: &ModCollection,
+    installed_mods: &[InstalledMod],
+    catalog: &[Mod],
+    mut record: F,
+) -> CollectionReport
+where
+    F: FnMut(&CollectionMod, &InstalledArchive, Option<String>, &[String]) -> Result<(), AppError>,
+{
+    let mut report = CollectionReport::default();
+
+    for entry in &collection.mods {
+        if is_installed_at(installed_mods, entry) {
+            report.unchanged.push(entry.name.clone());
+            continue;
+        }
+
+        let catalog_mod = entry
+            .catalog_id
+            .as_deref()
+            .and_then(|id| index::find_catalog_mod(catalog, id))
+            .or_else(|| index::find_catalog_mod(catalog, &entry.name));
+        let dependencies = catalog_mod
+            .map(installer::catalog_dependencies)
+            .unwrap_or_default();
+        let is_framework = ["steamodded", "talisman"].contains(&entry.name.to_lowercase().as_str());
+
+        let result = match (&entry.download_url, catalog_mod) {
+            (Some(url), _) if !is_framework => {
+                let folder_name = catalog_mod
+                    .and_then(|m| m.folderName.clone())
+                    .filter(|f| !f.is_empty())
+                    .unwrap_or_else(|| entry.name.split_whitespace().collect());
+                // The name comes from whoever shared the collection
+                if installer::is_plain_folder_name(&folder_name) {
+                    installer::install_mod(
+                        installation_path,
+                        url.clone(),
+                        Some(folder_name),
+                        entry.sha256.as_deref(),
+                    )
+                    .await
+                    .map(|archive| (archive, entry.version.clone()))
+                } else {
+                    Err(AppError::InvalidState(format!(
+                        "Refusing to install into folder '{}'",
+                        folder_name
+                    )))
+                }
+            }
+            (_, Some(catalog_mod)) => {
+                installer::install_from_catalog(
+                    installation_path,
+                    catalog_mod,
+                    entry.version.as_deref(),
+                )
+                .await
+            }
+            (_, None) => Err(AppError::InvalidState(
+                "no download URL and not in the mod index".to_string(),
+            )),
+        };
+
+        match result.and_then(|(archive, version)| record(entry, &archive, version, &dependencies))
+        {
+            Ok(()) => report.installed.push(entry.name.clone()),
+            Err(e) => {
+                log::error!("Failed to install {} from collection: {}", entry.name, e);
+                report.failed.push(CollectionFailure {
+                    name: entry.name.clone(),
+                    error: e.to_string(),
+                });
+            }
+        }
+    }
+
+    report
+}
+
+/// Storage for collections in the manager database.
+pub struct ModCollectionManager;
+
+impl ModCollectionManager {
     pub fn initialize_table(conn: &Connection) -> Result<()> {
         conn.execute(
             "CREATE TABLE IF NOT EXISTS mod_collections (
-                hash INTEGER PRIMARY KEY,
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                description TEXT,
+                created_at INTEGER NOT NULL
+            )",
+            [],
+        )?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS mod_collection_mods (
+                collection_id TEXT NOT NULL,
+                position INTEGER NOT NULL,
                 name TEXT NOT NULL,
-                path TEXT NOT NULL
+                catalog_id TEXT,
+                version TEXT,
+                download_url TEXT,
+                sha256 TEXT,
+                PRIMARY KEY (collection_id, position)
             )",
             [],
         )?;
         Ok(())
     }
 
-    pub fn add_collection(&mut self, conn: &Connection, collection: ModCollection) -> Result<()> {
-        self.collections.insert(collection.hash, collection.clone());
-
-        conn.execute(
-            "INSERT OR REPLACE INTO mod_collections (hash, name, path) VALUES (?1, ?2, ?3)",
-            [
-                &collection.hash.to_string(),
-                &collection.name,
-                &collection.path.to_string_lossy().to_string(),
+    /// Create or replace a collection by id.
+    pub fn save_collection(conn: &Connection, collection: &ModCollection) -> Result<()> {
+        let tx = conn.unchecked_transaction()?;
+        tx.execute(
+            "INSERT OR REPLACE INTO mod_collections (id, name, description, created_at) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                collection.id,
+                collection.name,
+                collection.description,
+                collection.created_at as i64
             ],
         )?;
+        tx.execute(
+            "DELETE FROM mod_collection_mods WHERE collection_id = ?1",
+            [&collection.id],
+        )?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT INTO mod_collection_mods (collection_id, position, name, catalog_id, version, download_url, sha256) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            )?;
+            for (position, entry) in collection.mods.iter().enumerate() {
+                stmt.execute(params![
+                    collection.id,
+                    position as i64,
+                    entry.name,
+                    entry.catalog_id,
+                    entry.version,
+                    entry.download_url,
+                    entry.sha256
+                ])?;
+            }
+        }
+        tx.commit()
+    }
 
-        Ok(())
+    pub fn get_collection(conn: &Connection, id: &str) -> Result<Option<ModCollection>> {
+        let mut stmt = conn.prepare(
+            "SELECT id, name, description, created_at FROM mod_collections WHERE id = ?1",
+        )?;
+        let mut rows = stmt.query([id])?;
+        let Some(row) = rows.next()? else {
+            return Ok(None);
+        };
+        let mut collection = ModCollection {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            description: row.get(2)?,
+            created_at: row.get::<_, i64>(3)? as u64,
+            mods: Vec::new(),
+        };
+
+        let mut stmt = conn.prepare(
+            "SELECT name, catalog_id, version, download_url, sha256 FROM mod_collection_mods WHERE collection_id = ?1 ORDER BY position",
+        )?;
+        collection.mods = stmt
+            .query_map([id], |row| {
+                Ok(CollectionMod {
+                    name: row.get(0)?,
+                    catalog_id: row.get(1)?,
+                    version: row.get(2)?,
+                    download_url: row.get(3)?,
+                    sha256: row.get(4)?,
+                })
+            })?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Some(collection))
     }
 
-    pub fn get_collection(&self, hash: u64) -> Option<&ModCollection> {
-        self.collections.get(&hash)
+    pub fn get_all_collections(conn: &Connection) -> Result<Vec<ModCollection>> {
+        let mut stmt =
+            conn.prepare("SELECT id FROM mod_collections ORDER BY name COLLATE NOCASE")?;
+        let ids = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut collections = Vec::new();
+        for id in ids {
+            if let Some(collection) = Self::get_collection(conn, &id)? {
+                collections.push(collection);
+            }
+        }
+        Ok(collections)
     }
 
-    pub fn remove_collection(&mut self, conn: &Connection, hash: u64) -> Result<()> {
-        self.collections.remove(&hash);
+    pub fn remove_collection(conn: &Connection, id: &str) -> Result<()> {
         conn.execute(
-            "DELETE FROM mod_collections WHERE hash = ?1",
-            [hash.to_string()],
+            "DELETE FROM mod_collection_mods WHERE collection_id = ?1",
+            [id],
         )?;
+        conn.execute("DELETE FROM mod_collections WHERE id = ?1", [id])?;
         Ok(())
     }
+}
 
-    pub fn load_collections(&mut self, conn: &Connection) -> Result<Vec<ModCollection>> {
-        let mut stmt = conn.prepare("SELECT hash, name, path FROM mod_collections")?;
-
-        let collections = stmt.query_map([], |row| {
-            let hash: u64 = row.get::<_, String>(0)?.parse().unwrap();
-            let name: String = row.get(1)?;
-            let path: String = row.get(2)?;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-            Ok(ModCollection {
-                hash,
-                name,
-                path: PathBuf::from(path),
-            })
-        })?;
+    fn collection() -> ModCollection {
+        let mut collection = ModCollection::new(
+            "Friday Run".to_string(),
+            vec![
+                CollectionMod {
+                    name: "Steamodded".to_string(),
+                    catalog_id: Some("Steamodded".to_string()),
+                    version: Some("1.0.0~BETA-0301a".to_string()),
+                    download_url: None,
+                    sha256: None,
+                },
+                CollectionMod {
+                    name: "Cryptid".to_string(),
+                    catalog_id: None,
+                    version: Some("0.5.2".to_string()),
+                    download_url: Some("https://example.com/cryptid.zip".to_string()),
+                    sha256: Some("ab".repeat(32)),
+                },
+            ],
+        );
+        collection.description = Some("Shared setup".to_string());
+        collection
+    }
 
-        let mut result = Vec::new();
-        for collection in collections {
-            let collection = collection?;
-            self.collections.insert(collection.hash, collection.clone());
-            result.push(collection);
+    #[test]
+    fn test_export_import_round_trip() -> Result<(), AppError> {
+        let original = collection();
+        for format in [CollectionFormat::Json, CollectionFormat::Toml] {
+            let exported = original.export(format)?;
+            let imported = ModCollection::import(&exported, format)?;
+            assert_eq!(imported.id, original.id);
+            assert_eq!(imported.mods.len(), 2);
+            assert_eq!(imported.mods[1].sha256, original.mods[1].sha256);
+            assert!(imported.mods[0].download_url.is_none());
         }
 
-        Ok(result)
+        let newer = original
+            .export(CollectionFormat::Json)?
+            .replace("\"format_version\": 1", "\"format_version\": 99");
+        assert!(ModCollection::import(&newer, CollectionFormat::Json).is_err());
+        Ok(())
     }
 
-    pub fn get_all_collections(&self, conn: &Connection) -> Result<Vec<ModCollection>> {
-        let mut stmt = conn.prepare("SELECT hash, name, path FROM mod_collections")?;
+    #[tokio::test]
+    async fn test_install_refuses_folders_outside_mods() -> Result<(), AppError> {
+        let mut shared = collection();
+        shared.mods = vec![CollectionMod {
+            name: "../../x".to_string(),
+            catalog_id: None,
+            version: None,
+            download_url: Some("https://example.com/x.zip".to_string()),
+            sha256: None,
+        }];
+        let imported = ModCollection::import(
+            &shared.export(CollectionFormat::Json)?,
+            CollectionFormat::Json,
+        )?;
 
-        let collections = stmt.query_map([], |row| {
-            let hash: u64 = row.get::<_, String>(0)?.parse().unwrap();
-            let name: String = row.get(1)?;
-            let path: String = row.get(2)?;
+        let report = install_collection(None, &imported, &[], &[], |_, _, _, _| {
+            panic!("nothing should be installed")
+        })
+        .await;
+        assert!(report.installed.is_empty());
+        assert_eq!(report.failed[0].name, "../../x");
+        assert!(report.failed[0].error.contains("Refusing"));
+        Ok(())
+    }
 
-            Ok(ModCollection {
-                hash,
-                name,
-                path: PathBuf::from(path),
-            })
-        })?;
+    #[test]
+    fn test_collections_table() -> Result<(), AppError> {
+        let conn = Connection::open_in_memory()?;
+        ModCollectionManager::initialize_table(&conn)?;
 
-        let mut result = Vec::new();
-        for collection in collections {
-            result.push(collection?);
-        }
+        let original = collection();
+        ModCollectionManager::save_collection(&conn, &original)?;
+        ModCollectionManager::save_collection(&conn, &original)?;
+        let loaded = ModCollectionManager::get_collection(&conn, &original.id)?.unwrap();
+        assert_eq!(loaded.name, "Friday Run");
+        assert_eq!(loaded.mods[0].name, "Steamodded");
+        assert_eq!(ModCollectionManager::get_all_collections(&conn)?.len(), 1);
 
-        Ok(result)
+        ModCollectionManager::remove_collection(&conn, &original.id)?;
+        assert!(ModCollectionManager::get_collection(&conn, &original.id)?.is_none());
+        Ok(())
     }
 }
-
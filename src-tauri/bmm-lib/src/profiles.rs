@@ -0,0 +1,232 @@
+use crate::cache::Mod;
+use crate::database::InstalledMod;
+use crate::errors::AppError;
+use crate::index;
+use crate::installer::{self, InstalledArchive};
+use crate::manager::ModManager;
+use crate::version::Version;
+use serde::{Deserialize, Serialize};
+
+/// A named set of mods and whether each should be enabled.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Profile {
+    pub name: String,
+    pub mods: Vec<ProfileMod>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfileMod {
+    pub name: String,
+    pub enabled: bool,
+    /// Keep the mod at this version while the profile is active.
+    #[serde(default)]
+    pub version: Option<String>,
+}
+
+/// What activating a profile has to change on disk.
+#[derive(Debug, Default)]
+pub struct ProfilePlan {
+    pub enable: Vec<String>,
+    pub disable: Vec<String>,
+    /// Mods that are missing or installed at a different version than pinned.
+    pub install: Vec<ProfileMod>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ProfileReport {
+    pub profile: String,
+    pub enabled: Vec<String>,
+    pub disabled: Vec<String>,
+    pub installed: Vec<String>,
+    pub failed: Vec<ProfileFailure>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProfileFailure {
+    pub name: String,
+    pub error: String,
+}
+
+impl ProfileReport {
+    pub fn new(profile: &str) -> Self {
+        Self {
+            profile: profile.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn fail(&mut self, name: &str, error: impl ToString) {
+        self.failed.push(ProfileFailure {
+            name: name.to_string(),
+            error: error.to_string(),
+        });
+    }
+}
+
+fn same_version(a: Option<&str>, b: &str) -> bool {
+    match (a.and_then(Version::parse), Version::parse(b)) {
+        (Some(a), Some(b)) => a == b,
+        _ => a == Some(b),
+    }
+}
+
+/// Work out which installed mods to enable or disable and what to install so
+/// that exactly the mods in `profile` are active. Installed mods that are not
+/// part of the profile get disabled, never removed.
+pub fn plan_activation(profile: &Profile, installed_mods: &[InstalledMod]) -> ProfilePlan {
+    let mut plan = ProfilePlan::default();
+
+    for entry in &profile.mods {
+        let installed = installed_mods
+            .iter()
+            .find(|m| m.name.eq_ignore_ascii_case(&entry.name));
+        let wrong_version = entry.version.as_deref().is_some_and(|v| {
+            !same_version(installed.and_then(|m| m.current_version.as_deref()), v)
+        });
+
+        if installed.is_none() || wrong_version {
+            plan.install.push(entry.clone());
+        }
+        // Freshly installed mods need their state applied as well
+        let name = installed.map_or(&entry.name, |m| &m.name).clone();
+        if entry.enabled {
+            plan.enable.push(name);
+        } else {
+            plan.disable.push(name);
+        }
+    }
+
+    for installed in installed_mods {
+        let in_profile = profile
+            .mods
+            .iter()
+            .any(|m| m.name.eq_ignore_ascii_case(&installed.name));
+        if !in_profile {
+            plan.disable.push(installed.name.clone());
+        }
+    }
+
+    plan
+}
+
+/// Install the mods a plan is missing from the catalog. `record` is called
+/// after each successful install with the entry, the catalog mod, the archive
+/// and the version that was installed, and should register the mod under the
+/// entry's name so `apply_states` can find it. Failures are added to the report and the rest still run.
+pub async fn install_missing<F>(
+    installation_path: Option<&String>,
+    plan: &ProfilePlan,
+    catalog: &[Mod],
+    report: &mut ProfileReport,
+    mut record: F,
+) where
+    F: FnMut(&ProfileMod, &Mod, &InstalledArchive, Option<String>) -> Result<(), AppError>,
+{
+    for entry in &plan.install {
+        let Some(catalog_mod) = index::find_catalog_mod(catalog, &entry.name) else {
+            report.fail(&entry.name, "not in the mod index");
+            continue;
+        };
+
+        let result = match installer::install_from_catalog(
+            installation_path,
+            catalog_mod,
+            entry.version.as_deref(),
+        )
+        .await
+        {
+            Ok((archive, version)) => record(entry, catalog_mod, &archive, version),
+            Err(e) => Err(e),
+        };
+
+        match result {
+            Ok(()) => report.installed.push(entry.name.clone()),
+            Err(e) => {
+                log::error!("Failed to install {} for profile: {}", entry.name, e);
+                report.fail(&entry.name, e);
+            }
+        }
+    }
+}
+
+/// Enable and disable installed mods as planned, reporting only the mods
+/// whose state actually changed. Run it after `install_missing`; mods that
+/// failed to install are skipped.
+pub fn apply_states(manager: &ModManager, plan: &ProfilePlan, report: &mut ProfileReport) {
+    let changes = plan
+        .enable
+        .iter()
+        .map(|name| (name, true))
+        .chain(plan.disable.iter().map(|name| (name, false)));
+
+    for (name, enabled) in changes {
+        if report.failed.iter().any(|f| &f.name == name) {
+            continue;
+        }
+
+        match manager.is_mod_enabled(name) {
+            Ok(current) if current == enabled => continue,
+            Ok(_) => {}
+            Err(e) => {
+                report.fail(name, e);
+                continue;
+            }
+        }
+
+        match manager.set_mod_enabled(name, enabled) {
+            Ok(()) if enabled => report.enabled.push(name.clone()),
+            Ok(()) => report.disabled.push(name.clone()),
+            Err(e) => report.fail(name, e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn installed(name: &str, version: &str) -> InstalledMod {
+        InstalledMod {
+            name: name.to_string(),
+            path: format!("/mods/{}", name),
+            dependencies: Vec::new(),
+            current_version: Some(version.to_string()),
+            archive_sha256: None,
+            download_url: None,
+            pinned: false,
+        }
+    }
+
+    fn entry(name: &str, enabled: bool, version: Option<&str>) -> ProfileMod {
+        ProfileMod {
+            name: name.to_string(),
+            enabled,
+            version: version.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_plan_activation() {
+        let profile = Profile {
+            name: "Content".to_string(),
+            mods: vec![
+                entry("Steamodded", true, Some("v1.0.0")),
+                entry("Talisman", true, Some("2.1.0")),
+                entry("Cryptid", false, None),
+                entry("Missing", true, None),
+            ],
+        };
+        let installed_mods = vec![
+            installed("Steamodded", "1.0.0"),
+            installed("Talisman", "2.0.0"),
+            installed("Cryptid", "0.5.0"),
+            installed("QoL", "1.0.0"),
+        ];
+
+        let plan = plan_activation(&profile, &installed_mods);
+        assert_eq!(plan.enable, vec!["Steamodded", "Talisman", "Missing"]);
+        assert_eq!(plan.disable, vec!["Cryptid", "QoL"]);
+        let install: Vec<_> = plan.install.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(install, vec!["Talisman", "Missing"]);
+    }
+}
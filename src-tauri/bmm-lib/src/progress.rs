@@ -0,0 +1,48 @@
+use crate::errors::AppError;
+use serde::Serialize;
+
+/// Where a download or install currently is.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "phase", rename_all = "snake_case")]
+pub enum Progress {
+    /// Working out what to download, e.g. looking up a release.
+    Resolving,
+    /// `total` is missing when the server doesn't say how big the body is.
+    Downloading {
+        received: u64,
+        total: Option<u64>,
+    },
+    Extracting {
+        done: usize,
+        total: usize,
+    },
+    /// Moving the extracted files into place.
+    Finalising,
+}
+
+/// Receives the progress of a long-running operation and tells it whether
+/// to stop. Operations check for cancellation between steps, never halfway
+/// through moving files into place.
+pub trait ProgressSink: Send + Sync {
+    fn report(&self, progress: Progress);
+
+    fn is_cancelled(&self) -> bool {
+        false
+    }
+
+    /// [`AppError::Cancelled`] once the operation has been cancelled.
+    fn check_cancelled(&self) -> Result<(), AppError> {
+        if self.is_cancelled() {
+            Err(AppError::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// For callers that don't show progress.
+pub struct NoProgress;
+
+impl ProgressSink for NoProgress {
+    fn report(&self, _progress: Progress) {}
+}
@@ -0,0 +1,312 @@
+use crate::cache::Mod;
+use crate::catalog::CatalogStore;
+use crate::errors::AppError;
+use crate::github_api::{self, Freshness};
+use crate::github_repo::parse_github_url;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// How long a looked-up release is trusted before GitHub is asked again.
+pub const RELEASE_CHECK_INTERVAL: u64 = 6 * 60 * 60;
+
+/// The newest non-draft, non-prerelease GitHub release of a mod's repository.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LatestRelease {
+    pub tag: String,
+    pub name: Option<String>,
+    /// Unix time the release was published.
+    pub published_at: Option<u64>,
+    pub html_url: String,
+    pub zipball_url: Option<String>,
+    pub assets: Vec<ReleaseAssetLink>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReleaseAssetLink {
+    pub name: String,
+    pub url: String,
+    /// Size in bytes.
+    pub size: u64,
+}
+
+/// What one pass of [`check_releases`] did.
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct ReleaseCheckSummary {
+    /// Repositories GitHub was asked about.
+    pub checked: usize,
+    /// Mods whose latest release changed.
+    pub updated: usize,
+    /// Answered from the stored copy because the ETag still matched.
+    pub not_modified: usize,
+    pub failed: usize,
+    /// Checked recently enough to be left alone.
+    pub skipped: usize,
+    /// Set when GitHub's rate limit stopped the pass: when it resets.
+    pub rate_limited_until: Option<u64>,
+}
+
+// The parts of GitHub's release object we keep
+#[derive(Deserialize)]
+struct ApiRelease {
+    tag_name: String,
+    name: Option<String>,
+    published_at: Option<String>,
+    html_url: String,
+    zipball_url: Option<String>,
+    #[serde(default)]
+    assets: Vec<ApiAsset>,
+}
+
+#[derive(Deserialize)]
+struct ApiAsset {
+    name: String,
+    browser_download_url: String,
+    size: u64,
+}
+
+impl From<ApiRelease> for LatestRelease {
+    fn from(release: ApiRelease) -> Self {
+        LatestRelease {
+            tag: release.tag_name,
+            name: release.name.filter(|n| !n.is_empty()),
+            published_at: release
+                .published_at
+                .and_then(|at| chrono::DateTime::parse_from_rfc3339(&at).ok())
+                .and_then(|at| u64::try_from(at.timestamp()).ok()),
+            html_url: release.html_url,
+            zipball_url: release.zipball_url,
+            assets: release
+                .assets
+                .into_iter()
+                .map(|asset| ReleaseAssetLink {
+                    name: asset.name,
+                    url: asset.browser_download_url,
+                    size: asset.size,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl LatestRelease {
+    /// The archive to install: the first zip asset, else GitHub's source zip.
+    pub fn download_url(&self) -> Option<&str> {
+        self.assets
+            .iter()
+            .find(|asset| asset.name.to_lowercase().ends_with(".zip"))
+            .map(|asset| asset.url.as_str())
+            .or(self.zipball_url.as_deref())
+    }
+}
+
+/// Show a looked-up release on a catalog entry. When the index entry has no
+/// download the release supplies it, and its tag becomes the version updates
+/// are compared against; otherwise the index's version and download stay, so
+/// the version recorded on install is the one that was downloaded.
+pub fn apply_release(entry: &mut Mod, release: LatestRelease) {
+    if entry.download_url.is_empty() {
+        if let Some(url) = release.download_url() {
+            entry.download_url = url.to_string();
+            entry.version = Some(release.tag.clone());
+            if let Some(published_at) = release.published_at {
+                entry.details.last_updated = Some(published_at);
+            }
+        }
+    }
+    entry.details.latest_release = Some(release);
+}
+
+// None when the repository has no published release
+async fn lookup(owner: &str, repo: &str) -> Result<(Option<LatestRelease>, Freshness), AppError> {
+    let path = format!("repos/{}/{}/releases/latest", owner, repo);
+    let response = github_api::shared().get(&path, github_api::JSON).await?;
+    if response.status == StatusCode::NOT_FOUND {
+        return Ok((None, response.freshness));
+    }
+    if !response.status.is_success() {
+        return Err(AppError::NetworkRequest {
+            url: format!("{}/{}", github_api::API_ROOT, path),
+            source: format!("GitHub returned {}", response.status),
+        });
+    }
+    let release: ApiRelease = serde_json::from_str(&response.body)
+        .map_err(|e| AppError::InvalidApiResponse(e.to_string()))?;
+    Ok((Some(release.into()), response.freshness))
+}
+
+/// Look up the latest GitHub release of every catalog mod that opted into
+/// automatic version checks and store it with the catalog. Releases checked
+/// in the last [`RELEASE_CHECK_INTERVAL`] are skipped unless `force` is set.
+/// Stops early, keeping what it has, when GitHub's rate limit runs out.
+pub async fn check_releases(
+    store: &CatalogStore,
+    force: bool,
+) -> Result<ReleaseCheckSummary, AppError> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|e| AppError::SystemTime(e.to_string()))?
+        .as_secs();
+    let mut summary = ReleaseCheckSummary::default();
+
+    for entry in store.mods()? {
+        if !entry.details.automatic_version_check {
+            continue;
+        }
+        let Some((owner, repo)) = parse_github_url(&entry.repo) else {
+            continue;
+        };
+        let previous = store
+            .release_check(&entry.title)?
+            .filter(|check| check.repo == entry.repo);
+        let fresh = previous
+            .as_ref()
+            .is_some_and(|check| now.saturating_sub(check.checked_at) < RELEASE_CHECK_INTERVAL);
+        if !force && fresh {
+            summary.skipped += 1;
+            continue;
+        }
+
+        summary.checked += 1;
+        match lookup(&owner, &repo).await {
+            // Served from the cache because the quota is gone; not a real check
+            Ok((_, Freshness::Stale)) => {
+                summary.checked -= 1;
+                summary.rate_limited_until = github_api::shared().rate_limit().map(|l| l.reset);
+                break;
+            }
+            Ok((release, freshness)) => {
+                if freshness == Freshness::NotModified {
+                    summary.not_modified += 1;
+                }
+                if previous.as_ref().map(|check| &check.release) != Some(&release) {
+                    summary.updated += 1;
+                }
+                store.record_release(&entry.title, &entry.repo, now, release.as_ref())?;
+            }
+            Err(AppError::ApiLimitExceeded { reset }) => {
+                log::warn!(
+                    "GitHub rate limit reached, release checks resume after {}",
+                    reset
+                );
+                summary.checked -= 1;
+                summary.rate_limited_until = Some(reset);
+                break;
+            }
+            Err(e) => {
+                log::warn!(
+                    "Failed to look up the latest release of {}: {}",
+                    entry.title,
+                    e
+                );
+                summary.failed += 1;
+            }
+        }
+    }
+
+    log::info!(
+        "Release check: {} checked, {} updated, {} not modified, {} failed",
+        summary.checked,
+        summary.updated,
+        summary.not_modified,
+        summary.failed
+    );
+    Ok(summary)
+}
+
+/// Check releases right away and then every [`RELEASE_CHECK_INTERVAL`],
+/// waiting out GitHub's rate limit when a pass runs into it. `on_pass` sees
+/// the result of each pass. Runs until the task it is spawned on ends.
+pub async fn run_release_checker<F>(on_pass: F)
+where
+    F: Fn(&ReleaseCheckSummary),
+{
+    loop {
+        let result = match CatalogStore::open() {
+            Ok(store) => check_releases(&store, false).await,
+            Err(e) => Err(e),
+        };
+        let wait = match result {
+            Ok(summary) => {
+                on_pass(&summary);
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |d| d.as_secs());
+                match summary.rate_limited_until {
+                    Some(reset) => reset.saturating_sub(now).max(60),
+                    None => RELEASE_CHECK_INTERVAL,
+                }
+            }
+            Err(e) => {
+                log::warn!("Release check failed: {}", e);
+                RELEASE_CHECK_INTERVAL
+            }
+        };
+        tokio::time::sleep(std::time::Duration::from_secs(wait)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::ModDetails;
+
+    #[test]
+    fn test_recorded_release_is_applied_to_the_catalog() -> Result<(), AppError> {
+        let store = CatalogStore::open_in_memory()?;
+        let entry = Mod {
+            requires_steamodded: true,
+            publisher: "MathIsFun".into(),
+            repo: "https://github.com/MathIsFun0/Cryptid".into(),
+            download_url: String::new(),
+            version: Some("0.5.0".into()),
+            details: ModDetails {
+                automatic_version_check: true,
+                ..Default::default()
+            },
+            ..Mod::for_test("Cryptid")
+        };
+        store.refresh(std::slice::from_ref(&entry))?;
+
+        let release = LatestRelease {
+            tag: "v0.5.3".into(),
+            name: None,
+            published_at: Some(1_700_000_000),
+            html_url: "https://github.com/MathIsFun0/Cryptid/releases/tag/v0.5.3".into(),
+            zipball_url: Some(
+                "https://api.github.com/repos/MathIsFun0/Cryptid/zipball/v0.5.3".into(),
+            ),
+            assets: vec![ReleaseAssetLink {
+                name: "Cryptid.zip".into(),
+                url: "https://github.com/MathIsFun0/Cryptid/releases/download/v0.5.3/Cryptid.zip"
+                    .into(),
+                size: 4096,
+            }],
+        };
+        store.record_release(&entry.title, &entry.repo, 100, Some(&release))?;
+
+        let stored = store.get("cryptid")?.expect("entry is stored");
+        assert_eq!(stored.version.as_deref(), Some("v0.5.3"));
+        assert!(stored.download_url.ends_with("/Cryptid.zip"));
+        assert_eq!(stored.details.last_updated, Some(1_700_000_000));
+
+        // The release survives a refresh of the unchanged index entry
+        let summary = store.refresh(std::slice::from_ref(&entry))?;
+        assert_eq!(summary.unchanged, 1);
+        let check = store.release_check("Cryptid")?.expect("check is stored");
+        assert_eq!(check.checked_at, 100);
+        assert_eq!(check.release.as_ref(), Some(&release));
+
+        // An index download stays paired with the index version
+        let mut pinned = entry.clone();
+        pinned.download_url = "https://example.com/Cryptid-0.5.0.zip".into();
+        apply_release(&mut pinned, release.clone());
+        assert_eq!(pinned.version.as_deref(), Some("0.5.0"));
+        assert_eq!(pinned.download_url, "https://example.com/Cryptid-0.5.0.zip");
+        assert_eq!(pinned.details.latest_release, Some(release));
+
+        store.refresh(&[])?;
+        assert!(store.release_check("Cryptid")?.is_none());
+        Ok(())
+    }
+}
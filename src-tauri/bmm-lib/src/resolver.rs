@@ -0,0 +1,575 @@
+use crate::cache::Mod;
+use crate::database::Database;
+use crate::errors::AppError;
+use crate::index;
+use crate::local_mod_detection::{self, DetectedMod};
+use crate::version::Version;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fmt;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+/// A mod name with optional version bounds, as written in Steamodded headers
+/// (`Steamodded (>=1.0.0~ALPHA-1304a)`, `Talisman>=2.0`, `Cryptid (>>1.0)(<<2.0)`)
+/// or Thunderstore manifests (`Author-Name-1.2.3`, meaning at least 1.2.3).
+#[derive(Debug, Clone)]
+pub struct Requirement {
+    pub name: String,
+    bounds: Vec<(Op, Version)>,
+    raw: String,
+}
+
+/// One entry of a dependency list. Any of the alternatives separated by `|`
+/// satisfies it.
+#[derive(Debug, Clone)]
+pub struct Dependency {
+    pub alternatives: Vec<Requirement>,
+}
+
+/// A mod as the resolver sees it, either installed or from the catalog.
+#[derive(Debug, Clone)]
+pub struct Package {
+    pub name: String,
+    /// Other names the mod is known by, such as its mod id or folder name.
+    pub aliases: Vec<String>,
+    pub version: Option<String>,
+    pub dependencies: Vec<String>,
+    pub conflicts: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PlannedInstall {
+    pub name: String,
+    pub version: Option<String>,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Problem {
+    /// Nothing installed or in the catalog provides the dependency.
+    Missing {
+        dependency: String,
+        required_by: String,
+    },
+    /// The dependency exists, but not in a version the constraint allows.
+    Unsatisfiable {
+        dependency: String,
+        required_by: String,
+        available: Option<String>,
+    },
+    Conflict {
+        package: String,
+        conflicts_with: String,
+        rule: String,
+    },
+}
+
+/// What installing a set of mods would do. Mods in `install` are ordered so
+/// that dependencies come before the mods that need them.
+#[derive(Debug, Default, Serialize)]
+pub struct InstallPlan {
+    pub install: Vec<PlannedInstall>,
+    pub problems: Vec<Problem>,
+}
+
+fn parse_op(s: &str) -> Option<(Op, &str)> {
+    // Two-character operators first so ">=" isn't read as ">"
+    const OPS: [(&str, Op); 9] = [
+        ("<<", Op::Lt),
+        ("<=", Op::Le),
+        (">>", Op::Gt),
+        (">=", Op::Ge),
+        ("==", Op::Eq),
+        ("!=", Op::Ne),
+        ("<", Op::Lt),
+        (">", Op::Gt),
+        ("=", Op::Eq),
+    ];
+    OPS.iter()
+        .find_map(|(token, op)| s.strip_prefix(token).map(|rest| (*op, rest.trim())))
+}
+
+fn parse_bound(s: &str) -> Option<(Op, Version)> {
+    let (op, version) = parse_op(s.trim())?;
+    Some((op, Version::parse(version)?))
+}
+
+// "Author-Name-1.2.3" with a plain x.y.z version, as Thunderstore writes it
+fn parse_thunderstore(s: &str) -> Option<(&str, Version)> {
+    let mut parts = s.splitn(3, '-');
+    let (_author, name, version) = (parts.next()?, parts.next()?, parts.next()?);
+    let is_plain = version.split('.').count() == 3
+        && version
+            .split('.')
+            .all(|p| !p.is_empty() && p.chars().all(|c| c.is_ascii_digit()));
+    if name.is_empty() || !is_plain {
+        return None;
+    }
+    Some((name, Version::parse(version)?))
+}
+
+impl Requirement {
+    pub fn parse(input: &str) -> Option<Self> {
+        let raw = input.trim();
+        if raw.is_empty() {
+            return None;
+        }
+
+        let (name, bounds) = if let Some(idx) = raw.find('(') {
+            let bounds = raw[idx..]
+                .split(['(', ')'])
+                .map(str::trim)
+                .filter(|b| !b.is_empty())
+                .filter_map(|b| {
+                    let bound = parse_bound(b);
+                    if bound.is_none() {
+                        log::warn!("Ignoring unreadable version bound '{}' in '{}'", b, raw);
+                    }
+                    bound
+                })
+                .collect();
+            (raw[..idx].trim(), bounds)
+        } else if let Some(idx) = raw.find(['<', '>', '=', '!']) {
+            (
+                raw[..idx].trim(),
+                parse_bound(&raw[idx..]).into_iter().collect(),
+            )
+        } else if let Some((name, version)) = parse_thunderstore(raw) {
+            (name, vec![(Op::Ge, version)])
+        } else {
+            (raw, Vec::new())
+        };
+
+        if name.is_empty() {
+            return None;
+        }
+        Some(Self {
+            name: name.to_string(),
+            bounds,
+            raw: raw.to_string(),
+        })
+    }
+
+    /// Whether `version` satisfies every bound. A version that is unknown or
+    /// can't be parsed can't be checked, so it is given the benefit of the doubt.
+    pub fn allows(&self, version: Option<&str>) -> bool {
+        let Some(version) = version.and_then(Version::parse) else {
+            return true;
+        };
+        self.bounds.iter().all(|(op, bound)| match op {
+            Op::Eq => version == *bound,
+            Op::Ne => version != *bound,
+            Op::Lt => version < *bound,
+            Op::Le => version <= *bound,
+            Op::Gt => version > *bound,
+            Op::Ge => version >= *bound,
+        })
+    }
+}
+
+impl fmt::Display for Requirement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.raw)
+    }
+}
+
+impl Dependency {
+    pub fn parse(input: &str) -> Option<Self> {
+        let alternatives: Vec<_> = input.split('|').filter_map(Requirement::parse).collect();
+        (!alternatives.is_empty()).then_some(Self { alternatives })
+    }
+}
+
+impl Package {
+    pub fn is_named(&self, name: &str) -> bool {
+        self.name.eq_ignore_ascii_case(name)
+            || self.aliases.iter().any(|a| a.eq_ignore_ascii_case(name))
+    }
+
+    fn provides(&self, requirement: &Requirement) -> bool {
+        self.is_named(&requirement.name) && requirement.allows(self.version.as_deref())
+    }
+}
+
+impl From<&Mod> for Package {
+    fn from(catalog_mod: &Mod) -> Self {
+        let mut dependencies = catalog_mod.dependencies.clone();
+        for (required, name) in [
+            (catalog_mod.requires_steamodded, "Steamodded"),
+            (catalog_mod.requires_talisman, "Talisman"),
+        ] {
+            let listed = dependencies
+                .iter()
+                .filter_map(|d| Dependency::parse(d))
+                .any(|d| {
+                    d.alternatives
+                        .iter()
+                        .any(|r| r.name.eq_ignore_ascii_case(name))
+                });
+            if required && !listed {
+                dependencies.push(name.to_string());
+            }
+        }
+
+        Self {
+            name: catalog_mod.title.clone(),
+            aliases: catalog_mod.folderName.iter().cloned().collect(),
+            version: catalog_mod.version.clone(),
+            dependencies,
+            conflicts: catalog_mod.conflicts.clone(),
+        }
+    }
+}
+
+impl From<&DetectedMod> for Package {
+    fn from(detected: &DetectedMod) -> Self {
+        let folder = Path::new(&detected.path)
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned());
+        Self {
+            name: detected.name.clone(),
+            aliases: std::iter::once(detected.id.clone()).chain(folder).collect(),
+            version: detected.version.clone(),
+            dependencies: detected.dependencies.clone(),
+            conflicts: detected.conflicts.clone(),
+        }
+    }
+}
+
+impl InstallPlan {
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+
+    /// Human readable list of the problems, one per line.
+    pub fn explain(&self) -> String {
+        self.problems
+            .iter()
+            .map(|problem| match problem {
+                Problem::Missing {
+                    dependency,
+                    required_by,
+                } => format!(
+                    "{} needs {}, which is neither installed nor in the index",
+                    required_by, dependency
+                ),
+                Problem::Unsatisfiable {
+                    dependency,
+                    required_by,
+                    available,
+                } => format!(
+                    "{} needs {}, but only version {} is available",
+                    required_by,
+                    dependency,
+                    available.as_deref().unwrap_or("unknown")
+                ),
+                Problem::Conflict {
+                    package,
+                    conflicts_with,
+                    rule,
+                } => format!("{} conflicts with {} ({})", package, conflicts_with, rule),
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+struct Resolver<'a> {
+    installed: &'a [Package],
+    catalog: &'a [Mod],
+    // Installed mods that the plan replaces
+    replaced: HashSet<String>,
+    planned: Vec<(Package, PlannedInstall)>,
+    visiting: HashSet<String>,
+    problems: Vec<Problem>,
+}
+
+impl Resolver<'_> {
+    fn is_planned(&self, name: &str) -> bool {
+        self.planned.iter().any(|(p, _)| p.is_named(name))
+    }
+
+    fn kept_installed(&self) -> impl Iterator<Item = &Package> {
+        self.installed
+            .iter()
+            .filter(|p| !self.replaced.contains(&p.name.to_lowercase()))
+    }
+
+    fn visit(&mut self, package: Package, reason: String) {
+        let key = package.name.to_lowercase();
+        if self.is_planned(&package.name) || !self.visiting.insert(key.clone()) {
+            return;
+        }
+        self.replaced.insert(key.clone());
+        for installed in self.installed.iter().filter(|p| p.is_named(&package.name)) {
+            self.replaced.insert(installed.name.to_lowercase());
+        }
+
+        for raw in &package.dependencies {
+            let Some(dependency) = Dependency::parse(raw) else {
+                continue;
+            };
+            self.require(&package.name, raw, &dependency);
+        }
+
+        self.visiting.remove(&key);
+        let planned = PlannedInstall {
+            name: package.name.clone(),
+            version: package.version.clone(),
+            reason,
+        };
+        self.planned.push((package, planned));
+    }
+
+    fn require(&mut self, required_by: &str, raw: &str, dependency: &Dependency) {
+        let satisfied = dependency.alternatives.iter().any(|r| {
+            self.kept_installed().any(|p| p.provides(r))
+                || self.planned.iter().any(|(p, _)| p.provides(r))
+                || self.visiting.contains(&r.name.to_lowercase())
+        });
+        if satisfied {
+            return;
+        }
+
+        // Take the first alternative the catalog can provide
+        let candidate = dependency.alternatives.iter().find_map(|r| {
+            index::find_catalog_mod(self.catalog, &r.name)
+                .map(Package::from)
+                .filter(|p| r.allows(p.version.as_deref()))
+        });
+        if let Some(package) = candidate {
+            self.visit(package, format!("required by {}", required_by));
+            return;
+        }
+
+        // Explain why the first alternative couldn't be used
+        let first = &dependency.alternatives[0];
+        let available = self
+            .kept_installed()
+            .find(|p| p.is_named(&first.name))
+            .map(|p| p.version.clone())
+            .or_else(|| {
+                index::find_catalog_mod(self.catalog, &first.name).map(|m| m.version.clone())
+            });
+        self.problems.push(match available {
+            Some(available) => Problem::Unsatisfiable {
+                dependency: raw.to_string(),
+                required_by: required_by.to_string(),
+                available,
+            },
+            None => Problem::Missing {
+                dependency: raw.to_string(),
+                required_by: required_by.to_string(),
+            },
+        });
+    }
+
+    fn check_conflicts(&mut self) {
+        let kept: Vec<&Package> = self.kept_installed().collect();
+        let planned: Vec<&Package> = self.planned.iter().map(|(p, _)| p).collect();
+
+        let mut found = Vec::new();
+        for package in &planned {
+            for other in kept.iter().chain(planned.iter()) {
+                if std::ptr::eq(*package, *other) {
+                    continue;
+                }
+                // Rules can be declared on either side
+                for (owner, target) in [(package, other), (other, package)] {
+                    for rule in owner.conflicts.iter().filter_map(|c| Requirement::parse(c)) {
+                        if target.provides(&rule) {
+                            found.push(Problem::Conflict {
+                                package: owner.name.clone(),
+                                conflicts_with: target.name.clone(),
+                                rule: rule.to_string(),
+                            });
+                        }
+                    }
+                }
+            }
+        }
+
+        // Two planned mods see each other's rules twice
+        for problem in found {
+            let duplicate = self.problems.iter().any(|p| match (p, &problem) {
+                (
+                    Problem::Conflict {
+                        package: a,
+                        conflicts_with: b,
+                        rule: r,
+                    },
+                    Problem::Conflict {
+                        package: c,
+                        conflicts_with: d,
+                        rule: s,
+                    },
+                ) => a == c && b == d && r == s,
+                _ => false,
+            });
+            if !duplicate {
+                self.problems.push(problem);
+            }
+        }
+    }
+}
+
+/// Work out everything that installing `requested` catalog mods involves:
+/// missing dependencies are pulled in from the catalog, installed ones that
+/// are too old are upgraded, and unmet constraints or conflicts with what is
+/// installed are reported as problems. Nothing on disk is touched.
+pub fn plan_install(
+    requested: &[&str],
+    installed: &[Package],
+    catalog: &[Mod],
+) -> Result<InstallPlan, AppError> {
+    let mut resolver = Resolver {
+        installed,
+        catalog,
+        replaced: HashSet::new(),
+        planned: Vec::new(),
+        visiting: HashSet::new(),
+        problems: Vec::new(),
+    };
+
+    for name in requested {
+        let catalog_mod =
+            index::find_catalog_mod(catalog, name).ok_or_else(|| AppError::ModNotFound {
+                mod_name: name.to_string(),
+                version: String::new(),
+            })?;
+        resolver.visit(Package::from(catalog_mod), "requested".to_string());
+    }
+    resolver.check_conflicts();
+
+    Ok(InstallPlan {
+        install: resolver.planned.into_iter().map(|(_, p)| p).collect(),
+        problems: resolver.problems,
+    })
+}
+
+/// Everything currently in the mods folder: mods installed through the
+/// manager, with dependency and conflict rules read from their headers, and
+/// mods that were dropped in by hand.
+pub fn installed_packages(db: &Database, catalog: &[Mod]) -> Result<Vec<Package>, AppError> {
+    let mut packages = Vec::new();
+
+    for installed in db.get_installed_mods()? {
+        let header = local_mod_detection::detect_mod_in_directory(Path::new(&installed.path))
+            .ok()
+            .flatten();
+        let mut package = match &header {
+            Some(detected) => Package::from(detected),
+            None => Package {
+                name: installed.name.clone(),
+                aliases: Vec::new(),
+                version: None,
+                dependencies: installed.dependencies.clone(),
+                conflicts: Vec::new(),
+            },
+        };
+        if package.name != installed.name {
+            package.aliases.push(std::mem::take(&mut package.name));
+            package.name = installed.name;
+        }
+        if let Some(version) = installed.current_version.filter(|v| !v.is_empty()) {
+            package.version = Some(version);
+        }
+        packages.push(package);
+    }
+
+    let manual =
+        local_mod_detection::detect_manual_mods(db, catalog).map_err(AppError::InvalidState)?;
+    packages.extend(manual.iter().filter(|m| !m.is_duplicate).map(Package::from));
+
+    Ok(packages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn catalog_mod(title: &str, version: &str, dependencies: &[&str]) -> Mod {
+        Mod {
+            version: Some(version.to_string()),
+            dependencies: dependencies.iter().map(|d| d.to_string()).collect(),
+            ..Mod::for_test(title)
+        }
+    }
+
+    fn installed(name: &str, version: &str) -> Package {
+        Package {
+            name: name.to_string(),
+            aliases: Vec::new(),
+            version: Some(version.to_string()),
+            dependencies: Vec::new(),
+            conflicts: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_requirement_formats() {
+        let smods = Requirement::parse("Steamodded (>=1.0.0~ALPHA-1304a)").unwrap();
+        assert_eq!(smods.name, "Steamodded");
+        assert!(smods.allows(Some("1.0.0~ALPHA-1310a")));
+        assert!(!smods.allows(Some("1.0.0~ALPHA-1200a")));
+
+        let range = Requirement::parse("Cryptid (>>1.0)(<<2.0)").unwrap();
+        assert!(range.allows(Some("1.5")));
+        assert!(!range.allows(Some("2.0.0")));
+
+        let glued = Requirement::parse("Talisman>=2.0").unwrap();
+        assert_eq!(glued.name, "Talisman");
+        assert!(!glued.allows(Some("1.9")));
+
+        let thunderstore = Requirement::parse("Steamodded-smods-1.2.3").unwrap();
+        assert_eq!(thunderstore.name, "smods");
+        assert!(thunderstore.allows(Some("1.2.4")));
+        assert!(!thunderstore.allows(Some("1.2.2")));
+
+        let alternatives = Dependency::parse("Talisman | Cryptid (>=0.5)").unwrap();
+        assert_eq!(alternatives.alternatives.len(), 2);
+    }
+
+    #[test]
+    fn test_plan_pulls_in_and_upgrades_dependencies() -> Result<(), AppError> {
+        let catalog = vec![
+            catalog_mod("Steamodded", "1.0.0~BETA-0101a", &[]),
+            catalog_mod("Lib", "2.0.0", &["Steamodded (>=1.0.0~ALPHA-1304a)"]),
+            catalog_mod("Content", "1.0.0", &["Lib (>=2.0)", "Steamodded"]),
+        ];
+        let installed_mods = vec![installed("Steamodded", "1.0.0~ALPHA-1200a")];
+
+        let plan = plan_install(&["Content"], &installed_mods, &catalog)?;
+        assert!(plan.is_ok(), "{}", plan.explain());
+        let order: Vec<_> = plan.install.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(order, vec!["Steamodded", "Lib", "Content"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_plan_reports_missing_unsatisfiable_and_conflicts() -> Result<(), AppError> {
+        let mut content = catalog_mod("Content", "1.0.0", &["Nowhere", "Lib (>=3.0)"]);
+        content.conflicts = vec!["Rival".to_string()];
+        let catalog = vec![content, catalog_mod("Lib", "2.0.0", &[])];
+        let installed_mods = vec![installed("Rival", "1.0.0")];
+
+        let plan = plan_install(&["Content"], &installed_mods, &catalog)?;
+        assert_eq!(plan.problems.len(), 3, "{}", plan.explain());
+        assert!(matches!(plan.problems[0], Problem::Missing { .. }));
+        assert!(matches!(plan.problems[1], Problem::Unsatisfiable { .. }));
+        assert!(matches!(plan.problems[2], Problem::Conflict { .. }));
+        assert!(plan_install(&["Unknown"], &installed_mods, &catalog).is_err());
+        Ok(())
+    }
+}
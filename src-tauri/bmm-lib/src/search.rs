@@ -0,0 +1,444 @@
+use crate::cache::{Category, Mod};
+use crate::catalog::CatalogStore;
+use crate::database::Database;
+use crate::errors::AppError;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+
+// A hit in the title counts for more than one in the publisher, which counts
+// for more than one somewhere in the description
+const TITLE_WEIGHT: f32 = 3.0;
+const PUBLISHER_WEIGHT: f32 = 2.0;
+const DESCRIPTION_WEIGHT: f32 = 1.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortOrder {
+    /// Best match first; by name when there is no search text.
+    #[default]
+    Relevance,
+    NameAsc,
+    NameDesc,
+    UpdatedAsc,
+    UpdatedDesc,
+    /// Installed mods first, then by name.
+    Installed,
+}
+
+/// What to look for. Every filter that is set must match.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SearchQuery {
+    pub text: String,
+    /// Mods in any of these categories. Empty means all.
+    pub categories: Vec<Category>,
+    pub requires_steamodded: Option<bool>,
+    pub requires_talisman: Option<bool>,
+    pub installed: Option<bool>,
+    pub sort: SortOrder,
+    pub offset: usize,
+    pub limit: Option<usize>,
+}
+
+/// How many of the mods matching the search text fall in each facet, before
+/// the facet filters are applied.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SearchFacets {
+    pub categories: Vec<(Category, usize)>,
+    pub requires_steamodded: usize,
+    pub requires_talisman: usize,
+    pub installed: usize,
+}
+
+#[derive(Serialize)]
+pub struct SearchResults {
+    /// Matches before `offset` and `limit` were applied.
+    pub total: usize,
+    pub mods: Vec<Mod>,
+    pub facets: SearchFacets,
+}
+
+#[derive(Clone, Copy)]
+enum Field {
+    Title,
+    Publisher,
+    Description,
+}
+
+impl Field {
+    fn weight(self) -> f32 {
+        match self {
+            Field::Title => TITLE_WEIGHT,
+            Field::Publisher => PUBLISHER_WEIGHT,
+            Field::Description => DESCRIPTION_WEIGHT,
+        }
+    }
+}
+
+struct Document {
+    item: Mod,
+    title_key: String,
+    installed: bool,
+    updated_at: Option<u64>,
+}
+
+/// An inverted index over a catalog, built once and searched many times.
+pub struct SearchIndex {
+    docs: Vec<Document>,
+    // term -> (document, field) for every field the term appears in
+    terms: HashMap<String, Vec<(usize, Field)>>,
+}
+
+/// Lowercased alphanumeric words of `text`.
+pub fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+// Longer words may be further off and still be what the user meant
+fn allowed_typos(len: usize) -> usize {
+    match len {
+        0..=3 => 0,
+        4..=7 => 1,
+        _ => 2,
+    }
+}
+
+// Levenshtein distance, giving up as soon as it must exceed `max`
+fn edit_distance(a: &[char], b: &[char], max: usize) -> Option<usize> {
+    if a.len().abs_diff(b.len()) > max {
+        return None;
+    }
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for (i, ca) in a.iter().enumerate() {
+        current[0] = i + 1;
+        let mut row_min = current[0];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+            row_min = row_min.min(current[j + 1]);
+        }
+        if row_min > max {
+            return None;
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    Some(previous[b.len()]).filter(|d| *d <= max)
+}
+
+// How well an indexed term matches a query token, from 0 (not at all) to 1 (exactly)
+fn term_match(token: &[char], term: &str) -> f32 {
+    let term: Vec<char> = term.chars().collect();
+    if term == token {
+        return 1.0;
+    }
+    if token.len() >= 2 && term.starts_with(token) {
+        return 0.8;
+    }
+    match edit_distance(token, &term, allowed_typos(token.len())) {
+        Some(distance) if distance > 0 => 0.6 / distance as f32,
+        _ => 0.0,
+    }
+}
+
+impl SearchIndex {
+    /// Index `catalog`. `installed` holds the names of installed mods and
+    /// `updated_at` the time each catalog entry last changed, both keyed by
+    /// title; either may be empty.
+    pub fn new(catalog: Vec<Mod>, installed: &[String], updated_at: &HashMap<String, u64>) -> Self {
+        let installed: HashSet<String> = installed.iter().map(|n| n.to_lowercase()).collect();
+        let mut terms: HashMap<String, Vec<(usize, Field)>> = HashMap::new();
+        let mut docs = Vec::with_capacity(catalog.len());
+
+        for (id, item) in catalog.into_iter().enumerate() {
+            for (field, text) in [
+                (Field::Title, &item.title),
+                (Field::Publisher, &item.publisher),
+                (Field::Description, &item.description),
+            ] {
+                let unique: HashSet<String> = tokenize(text).into_iter().collect();
+                for term in unique {
+                    terms.entry(term).or_default().push((id, field));
+                }
+            }
+            let title_key = item.title.to_lowercase();
+            docs.push(Document {
+                installed: item.installed || installed.contains(&title_key),
+                updated_at: updated_at.get(&item.title).copied(),
+                title_key,
+                item,
+            });
+        }
+
+        Self { docs, terms }
+    }
+
+    /// Index the stored catalog, marking what `db` has installed.
+    pub fn load(store: &CatalogStore, db: &Database) -> Result<Self, AppError> {
+        let installed: Vec<String> = db
+            .get_installed_mods()?
+            .into_iter()
+            .map(|m| m.name)
+            .collect();
+        Ok(Self::new(
+            store.mods()?,
+            &installed,
+            &store.changed_times()?,
+        ))
+    }
+
+    pub fn len(&self) -> usize {
+        self.docs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.docs.is_empty()
+    }
+
+    // Score of every document that matches all tokens; every document when
+    // there are none
+    fn text_scores(&self, text: &str) -> Vec<(usize, f32)> {
+        let tokens = tokenize(text);
+        if tokens.is_empty() {
+            return (0..self.docs.len()).map(|id| (id, 0.0)).collect();
+        }
+
+        let mut totals: HashMap<usize, f32> = HashMap::new();
+        for (i, token) in tokens.iter().enumerate() {
+            let token: Vec<char> = token.chars().collect();
+            // Best match of this token in each document
+            let mut best: HashMap<usize, f32> = HashMap::new();
+            for (term, postings) in &self.terms {
+                let quality = term_match(&token, term);
+                if quality == 0.0 {
+                    continue;
+                }
+                for (id, field) in postings {
+                    let score = quality * field.weight();
+                    let entry = best.entry(*id).or_insert(0.0);
+                    *entry = entry.max(score);
+                }
+            }
+
+            if i == 0 {
+                totals = best;
+            } else {
+                totals.retain(|id, total| match best.get(id) {
+                    Some(score) => {
+                        *total += score;
+                        true
+                    }
+                    None => false,
+                });
+            }
+            if totals.is_empty() {
+                break;
+            }
+        }
+        totals.into_iter().collect()
+    }
+
+    pub fn search(&self, query: &SearchQuery) -> SearchResults {
+        let matched = self.text_scores(&query.text);
+
+        let mut facets = SearchFacets::default();
+        let mut category_counts: HashMap<u32, (Category, usize)> = HashMap::new();
+        for (id, _) in &matched {
+            let doc = &self.docs[*id];
+            for category in &doc.item.categories {
+                category_counts
+                    .entry(*category as u32)
+                    .or_insert((*category, 0))
+                    .1 += 1;
+            }
+            facets.requires_steamodded += usize::from(doc.item.requires_steamodded);
+            facets.requires_talisman += usize::from(doc.item.requires_talisman);
+            facets.installed += usize::from(doc.installed);
+        }
+        let mut categories: Vec<_> = category_counts.into_iter().collect();
+        categories.sort_by_key(|(key, _)| *key);
+        facets.categories = categories.into_iter().map(|(_, count)| count).collect();
+
+        let mut hits: Vec<(usize, f32)> = matched
+            .into_iter()
+            .filter(|(id, _)| self.passes_filters(&self.docs[*id], query))
+            .collect();
+        hits.sort_by(|a, b| self.compare(a, b, query.sort));
+
+        let total = hits.len();
+        let limit = query.limit.unwrap_or(usize::MAX);
+        let mods = hits
+            .into_iter()
+            .skip(query.offset)
+            .take(limit)
+            .map(|(id, _)| {
+                let doc = &self.docs[id];
+                let mut item = doc.item.clone();
+                item.installed = doc.installed;
+                item
+            })
+            .collect();
+
+        SearchResults {
+            total,
+            mods,
+            facets,
+        }
+    }
+
+    fn passes_filters(&self, doc: &Document, query: &SearchQuery) -> bool {
+        (query.categories.is_empty()
+            || doc
+                .item
+                .categories
+                .iter()
+                .any(|c| query.categories.contains(c)))
+            && filter_matches(query.requires_steamodded, doc.item.requires_steamodded)
+            && filter_matches(query.requires_talisman, doc.item.requires_talisman)
+            && filter_matches(query.installed, doc.installed)
+    }
+
+    fn compare(&self, a: &(usize, f32), b: &(usize, f32), sort: SortOrder) -> Ordering {
+        let (da, db) = (&self.docs[a.0], &self.docs[b.0]);
+        let by_name = da.title_key.cmp(&db.title_key);
+        match sort {
+            SortOrder::Relevance => b.1.total_cmp(&a.1).then(by_name),
+            SortOrder::NameAsc => by_name,
+            SortOrder::NameDesc => by_name.reverse(),
+            // Entries without a known date go last either way
+            SortOrder::UpdatedAsc => match (da.updated_at, db.updated_at) {
+                (Some(x), Some(y)) => x.cmp(&y),
+                (x, y) => y.is_some().cmp(&x.is_some()),
+            }
+            .then(by_name),
+            SortOrder::UpdatedDesc => db.updated_at.cmp(&da.updated_at).then(by_name),
+            SortOrder::Installed => db.installed.cmp(&da.installed).then(by_name),
+        }
+    }
+}
+
+// An unset filter lets everything through
+fn filter_matches(filter: Option<bool>, value: bool) -> bool {
+    filter.unwrap_or(value) == value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn catalog_mod(title: &str, publisher: &str, description: &str, category: Category) -> Mod {
+        Mod {
+            description: description.into(),
+            categories: vec![category],
+            requires_steamodded: category != Category::API,
+            publisher: publisher.into(),
+            ..Mod::for_test(title)
+        }
+    }
+
+    fn index() -> SearchIndex {
+        let catalog = vec![
+            catalog_mod(
+                "Cryptid",
+                "MathIsFun",
+                "An absurd content pack",
+                Category::Content,
+            ),
+            catalog_mod("Talisman", "MathIsFun", "Big number support", Category::API),
+            catalog_mod(
+                "Jokers Plus",
+                "someone",
+                "More jokers for cryptid fans",
+                Category::Joker,
+            ),
+        ];
+        let updated = HashMap::from([("Cryptid".to_string(), 10), ("Talisman".to_string(), 20)]);
+        SearchIndex::new(catalog, &["talisman".to_string()], &updated)
+    }
+
+    fn titles(results: &SearchResults) -> Vec<&str> {
+        results.mods.iter().map(|m| m.title.as_str()).collect()
+    }
+
+    #[test]
+    fn test_typos_prefixes_and_weights() {
+        let index = index();
+        let search = |text: &str| {
+            index.search(&SearchQuery {
+                text: text.into(),
+                ..Default::default()
+            })
+        };
+
+        // The title match outranks the description match
+        assert_eq!(titles(&search("cryptid")), ["Cryptid", "Jokers Plus"]);
+        assert_eq!(titles(&search("cryptd")), ["Cryptid", "Jokers Plus"]);
+        assert_eq!(titles(&search("tali")), ["Talisman"]);
+        assert_eq!(titles(&search("mathisfun number")), ["Talisman"]);
+        assert!(search("xyz").mods.is_empty());
+        assert_eq!(search("").total, 3);
+    }
+
+    #[test]
+    fn test_facets_filters_and_sorting() {
+        let index = index();
+        let results = index.search(&SearchQuery {
+            text: "mathisfun".into(),
+            requires_steamodded: Some(true),
+            ..Default::default()
+        });
+        assert_eq!(titles(&results), ["Cryptid"]);
+        assert_eq!(
+            results.facets,
+            SearchFacets {
+                categories: vec![(Category::Content, 1), (Category::API, 1)],
+                requires_steamodded: 1,
+                requires_talisman: 0,
+                installed: 1,
+            }
+        );
+
+        let installed = index.search(&SearchQuery {
+            installed: Some(true),
+            ..Default::default()
+        });
+        assert_eq!(titles(&installed), ["Talisman"]);
+        assert!(installed.mods[0].installed);
+
+        let sorted = |sort| {
+            index
+                .search(&SearchQuery {
+                    sort,
+                    ..Default::default()
+                })
+                .mods
+                .into_iter()
+                .map(|m| m.title)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            sorted(SortOrder::UpdatedDesc),
+            ["Talisman", "Cryptid", "Jokers Plus"]
+        );
+        assert_eq!(
+            sorted(SortOrder::UpdatedAsc),
+            ["Cryptid", "Talisman", "Jokers Plus"]
+        );
+        assert_eq!(
+            sorted(SortOrder::Installed),
+            ["Talisman", "Cryptid", "Jokers Plus"]
+        );
+
+        let page = index.search(&SearchQuery {
+            sort: SortOrder::NameDesc,
+            offset: 1,
+            limit: Some(1),
+            ..Default::default()
+        });
+        assert_eq!((page.total, titles(&page)), (3, vec!["Jokers Plus"]));
+    }
+}
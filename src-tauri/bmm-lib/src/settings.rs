@@ -0,0 +1,302 @@
+use crate::errors::AppError;
+use crate::index::{self, IndexSource};
+use rusqlite::{Connection, Transaction, TransactionBehavior};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::path::Path;
+
+/// Every user preference, stored one row per field in the `settings` table
+/// with the value encoded as JSON. Adding a preference only takes a new field
+/// here; missing rows fall back to the default.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub installation_path: Option<String>,
+    pub discord_rpc: bool,
+    pub lovely_console: bool,
+    pub background_enabled: bool,
+    pub security_warning_acknowledged: bool,
+    /// Unix time the mod index was last pulled.
+    pub last_fetched: u64,
+    pub active_profile: Option<String>,
+    /// Mod indexes to read, highest priority first.
+    pub index_sources: Vec<IndexSource>,
+    /// Personal access token for GitHub API requests.
+    pub github_token: Option<String>,
+    /// Proxy for downloads, overriding the system proxy.
+    pub download_proxy: Option<String>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            installation_path: None,
+            discord_rpc: true,
+            lovely_console: false,
+            background_enabled: false,
+            security_warning_acknowledged: false,
+            last_fetched: 0,
+            active_profile: None,
+            index_sources: vec![IndexSource::official()],
+            github_token: None,
+            download_proxy: None,
+        }
+    }
+}
+
+// Settings that are only ever shown masked
+const SECRET_SETTINGS: [&str; 1] = ["github_token"];
+const MASK: &str = "********";
+
+fn to_map(settings: &Settings) -> Result<Map<String, Value>, AppError> {
+    match serde_json::to_value(settings)? {
+        Value::Object(map) => Ok(map),
+        _ => unreachable!("Settings serializes to an object"),
+    }
+}
+
+impl Settings {
+    pub fn validate(&self) -> Result<(), AppError> {
+        if let Some(path) = &self.installation_path {
+            if path.trim().is_empty() || !Path::new(path).is_absolute() {
+                return Err(AppError::config_error(
+                    "installation_path",
+                    format!("'{}' is not an absolute path", path),
+                ));
+            }
+        }
+        if self
+            .active_profile
+            .as_ref()
+            .is_some_and(|name| name.trim().is_empty())
+        {
+            return Err(AppError::config_error(
+                "active_profile",
+                "profile name is empty",
+            ));
+        }
+        if self
+            .github_token
+            .as_ref()
+            .is_some_and(|token| token.trim().chars().any(char::is_whitespace))
+        {
+            return Err(AppError::config_error(
+                "github_token",
+                "token contains whitespace",
+            ));
+        }
+        if let Some(proxy) = &self.download_proxy {
+            if let Err(e) = reqwest::Proxy::all(proxy) {
+                return Err(AppError::config_error("download_proxy", e.to_string()));
+            }
+        }
+        if self.index_sources.is_empty() {
+            return Err(AppError::config_error(
+                "index_sources",
+                "at least one index source is needed",
+            ));
+        }
+        index::validate_sources(&self.index_sources)
+    }
+
+    /// The value of one setting by its field name.
+    pub fn get(&self, key: &str) -> Result<Value, AppError> {
+        to_map(self)?
+            .remove(key)
+            .ok_or_else(|| AppError::InvalidState(format!("Unknown setting '{}'", key)))
+    }
+
+    /// Every setting as it may be shown to the user, with secrets such as
+    /// the GitHub token masked.
+    pub fn to_display(&self) -> Result<Map<String, Value>, AppError> {
+        let mut map = to_map(self)?;
+        for key in SECRET_SETTINGS {
+            if let Some(value) = map.get_mut(key).filter(|v| !v.is_null()) {
+                *value = Value::String(MASK.to_string());
+            }
+        }
+        Ok(map)
+    }
+
+    /// [`Settings::get`], masked like [`Settings::to_display`].
+    pub fn get_display(&self, key: &str) -> Result<Value, AppError> {
+        self.to_display()?
+            .remove(key)
+            .ok_or_else(|| AppError::InvalidState(format!("Unknown setting '{}'", key)))
+    }
+
+    /// Change one setting by its field name. Nothing changes if the value has
+    /// the wrong type or doesn't validate.
+    pub fn set(&mut self, key: &str, value: Value) -> Result<(), AppError> {
+        let mut map = to_map(self)?;
+        if !map.contains_key(key) {
+            return Err(AppError::InvalidState(format!("Unknown setting '{}'", key)));
+        }
+        map.insert(key.to_string(), value);
+
+        let updated: Settings = serde_json::from_value(Value::Object(map))
+            .map_err(|e| AppError::config_error(key, e.to_string()))?;
+        updated.validate()?;
+        *self = updated;
+        Ok(())
+    }
+
+    /// Read the settings, ignoring rows that no longer parse so one bad value
+    /// can't lock the user out of the rest.
+    pub fn load(conn: &Connection) -> Result<Self, AppError> {
+        let mut stmt = conn.prepare("SELECT setting, value FROM settings")?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut settings = Settings::default();
+        let known = to_map(&settings)?;
+        for (key, raw) in rows {
+            if !known.contains_key(&key) {
+                continue;
+            }
+            let applied = serde_json::from_str(&raw)
+                .map_err(AppError::from)
+                .and_then(|value| settings.set(&key, value));
+            if let Err(e) = applied {
+                log::warn!("Ignoring stored setting {}: {}", key, e);
+            }
+        }
+        Ok(settings)
+    }
+
+    /// Write every setting in one transaction.
+    pub fn save(&self, conn: &Connection) -> Result<(), AppError> {
+        let tx = conn
+            .unchecked_transaction()
+            .map_err(|e| AppError::DatabaseTransaction(e.to_string()))?;
+        self.write(&tx)?;
+        tx.commit()
+            .map_err(|e| AppError::DatabaseTransaction(e.to_string()))
+    }
+
+    /// Load, change and save the settings in one transaction, so concurrent
+    /// updates of different settings don't overwrite each other.
+    pub fn update<F>(conn: &Connection, change: F) -> Result<Self, AppError>
+    where
+        F: FnOnce(&mut Settings) -> Result<(), AppError>,
+    {
+        // Taking the write lock up front makes a second updater wait for the
+        // busy timeout; upgrading a read transaction would fail outright
+        let tx = Transaction::new_unchecked(conn, TransactionBehavior::Immediate)
+            .map_err(|e| AppError::DatabaseTransaction(e.to_string()))?;
+        let mut settings = Self::load(&tx)?;
+        change(&mut settings)?;
+        settings.write(&tx)?;
+        tx.commit()
+            .map_err(|e| AppError::DatabaseTransaction(e.to_string()))?;
+        Ok(settings)
+    }
+
+    fn write(&self, conn: &Connection) -> Result<(), AppError> {
+        self.validate()?;
+        for (key, value) in to_map(self)? {
+            if value.is_null() {
+                conn.execute("DELETE FROM settings WHERE setting = ?1", [&key])?;
+            } else {
+                conn.execute(
+                    "INSERT OR REPLACE INTO settings (setting, value) VALUES (?1, ?2)",
+                    [key, value.to_string()],
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection_pool::ConnectionPool;
+    use serde_json::json;
+
+    #[test]
+    fn test_concurrent_updates_keep_both_changes() -> Result<(), AppError> {
+        let temp = tempfile::tempdir()?;
+        let pool = ConnectionPool::open(temp.path().join("settings.db"))?;
+        pool.get()?.execute(
+            "CREATE TABLE settings (setting TEXT PRIMARY KEY, value TEXT NOT NULL)",
+            [],
+        )?;
+
+        std::thread::scope(|scope| {
+            let counting = scope.spawn(|| {
+                for _ in 0..50 {
+                    Settings::update(&*pool.get()?, |s| {
+                        s.last_fetched += 1;
+                        Ok(())
+                    })?;
+                }
+                Ok::<_, AppError>(())
+            });
+            let naming = scope.spawn(|| {
+                for i in 1..=50 {
+                    Settings::update(&*pool.get()?, |s| {
+                        s.active_profile = Some(format!("profile {}", i));
+                        Ok(())
+                    })?;
+                }
+                Ok::<_, AppError>(())
+            });
+            counting.join().expect("counting thread panicked")?;
+            naming.join().expect("naming thread panicked")
+        })?;
+
+        let settings = Settings::load(&*pool.get()?)?;
+        assert_eq!(settings.last_fetched, 50);
+        assert_eq!(settings.active_profile.as_deref(), Some("profile 50"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_validates_and_round_trips() -> Result<(), AppError> {
+        let conn = Connection::open_in_memory()?;
+        conn.execute(
+            "CREATE TABLE settings (setting TEXT PRIMARY KEY, value TEXT NOT NULL)",
+            [],
+        )?;
+        assert_eq!(Settings::load(&conn)?, Settings::default());
+
+        let mut settings = Settings::default();
+        settings.set("lovely_console", json!(true))?;
+        settings.set("last_fetched", json!(1_700_000_000u64))?;
+        assert!(settings.set("lovely_console", json!("enabled")).is_err());
+        assert!(settings
+            .set("installation_path", json!("relative/path"))
+            .is_err());
+        assert!(settings.set("no_such_setting", json!(1)).is_err());
+        assert!(settings.lovely_console);
+
+        settings.save(&conn)?;
+        let game_dir = std::env::temp_dir().to_string_lossy().into_owned();
+        Settings::update(&conn, |s| {
+            s.installation_path = Some(game_dir.clone());
+            Ok(())
+        })?;
+        assert!(Settings::update(&conn, |s| s.set("active_profile", json!(""))).is_err());
+        conn.execute(
+            "INSERT OR REPLACE INTO settings (setting, value) VALUES ('discord_rpc', 'garbage')",
+            [],
+        )?;
+        let loaded = Settings::load(&conn)?;
+        assert_eq!(loaded.last_fetched, 1_700_000_000);
+        assert!(loaded.lovely_console);
+        assert!(loaded.discord_rpc);
+        assert_eq!(loaded.get("installation_path")?, json!(game_dir));
+        assert_eq!(loaded.get("active_profile")?, Value::Null);
+
+        // The token is never shown, only whether there is one
+        assert_eq!(loaded.get_display("github_token")?, Value::Null);
+        settings.set("github_token", json!("ghp_secret"))?;
+        assert_eq!(settings.get_display("github_token")?, json!("********"));
+        assert!(!serde_json::to_string(&settings.to_display()?)?.contains("ghp_secret"));
+        Ok(())
+    }
+}
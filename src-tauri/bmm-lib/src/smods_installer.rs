@@ -1,8 +1,12 @@
 use crate::finder::get_lovely_mods_dir;
-use anyhow::{anyhow, Context, Result};
+use crate::github_api;
+use crate::installer::{self, InstalledArchive};
+use crate::progress::{NoProgress, Progress, ProgressSink};
+use crate::version::Version;
+use anyhow::{anyhow, Result};
 use log::info;
-use reqwest::header::{HeaderMap, HeaderValue, USER_AGENT};
 use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
 use std::fs;
 use std::io::Cursor;
 use tokio::fs as tokio_fs;
@@ -60,9 +64,11 @@ impl ModType {
 struct ReleaseAsset {
     name: String,
     browser_download_url: String,
+    // "sha256:<hex>", only present on assets GitHub has hashed
+    #[serde(default)]
+    digest: Option<String>,
 }
 pub struct ModInstaller {
-    client: reqwest::Client,
     pub mod_type: ModType,
     pub installation_path: Option<String>,
 }
@@ -70,7 +76,6 @@ pub struct ModInstaller {
 impl ModInstaller {
     pub fn new(installation_path: Option<&String>, mod_type: ModType) -> Self {
         Self {
-            client: reqwest::Client::new(),
             mod_type,
             installation_path: installation_path.cloned(),
         }
@@ -103,43 +108,10 @@ impl ModInstaller {
     }
 
     pub async fn get_available_versions(&self) -> Result<Vec<String>> {
-        let mut headers = HeaderMap::new();
-        headers.insert(
-            USER_AGENT,
-            HeaderValue::from_static("Balatro-Mod-Manager/1.0"),
-        );
-        headers.insert(
-            "Accept",
-            HeaderValue::from_static("application/vnd.github.v3+json"),
-        );
-
-        let url = format!(
-            "https://api.github.com/repos/{}/releases",
-            self.mod_type.get_repo_url()
-        );
-
-        let response = self
-            .client
-            .get(&url)
-            .headers(headers)
-            .send()
-            .await
-            .context("Failed to fetch Steamodded releases")?;
-
-        if !response.status().is_success() {
-            let status = response.status();
-            let error_text = response.text().await?;
-            return Err(anyhow::anyhow!(
-                "GitHub API error: {} - {}",
-                status,
-                error_text
-            ));
-        }
-
-        let releases: Vec<Release> = response
-            .json()
-            .await
-            .context("Failed to decode Steamodded releases")?;
+        let releases: Vec<Release> = github_api::shared()
+            .get_json(&format!("repos/{}/releases", self.mod_type.get_repo_url()))
+            .await?
+            .ok_or_else(|| anyhow!("{} has no releases", self.mod_type))?;
 
         let mut versions: Vec<String> = releases
             .into_iter()
@@ -147,12 +119,34 @@ impl ModInstaller {
             .map(|r| r.tag_name)
             .collect();
 
-        versions.sort_by(|a, b| b.cmp(a));
+        // Newest first; tags that don't parse as versions go last
+        versions.sort_by(|a, b| match (Version::parse(a), Version::parse(b)) {
+            (Some(a), Some(b)) => b.cmp(&a),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => b.cmp(a),
+        });
 
         Ok(versions)
     }
 
     pub async fn install_version(&self, version: &str) -> Result<String> {
+        let installed = self
+            .install_version_verified(version, None, &NoProgress)
+            .await?;
+        Ok(installed.path.to_string_lossy().to_string())
+    }
+
+    /// Install `version`, rejecting the download if it doesn't match
+    /// `expected_sha256`. Without one, the digest GitHub publishes for the
+    /// release asset is used when there is one. Progress goes to `progress`,
+    /// and the install stops before replacing anything if it is cancelled.
+    pub async fn install_version_verified(
+        &self,
+        version: &str,
+        expected_sha256: Option<&str>,
+        progress: &dyn ProgressSink,
+    ) -> Result<InstalledArchive> {
         let mods_dir = get_lovely_mods_dir(self.installation_path.as_ref());
 
         match self.mod_type {
@@ -162,37 +156,40 @@ impl ModInstaller {
                     version, mods_dir
                 );
 
-                let mut headers = HeaderMap::new();
-                headers.insert(
-                    USER_AGENT,
-                    HeaderValue::from_static("Balatro-Mod-Manager/1.0"),
-                );
-
                 // Get release details
-                let url = format!(
-                    "https://api.github.com/repos/{}/releases/tags/{}",
+                progress.report(Progress::Resolving);
+                let path = format!(
+                    "repos/{}/releases/tags/{}",
                     self.mod_type.get_repo_url(),
                     version
                 );
-                let release: Release = self
-                    .client
-                    .get(url)
-                    .headers(headers.clone())
-                    .send()
-                    .await?
-                    .json()
-                    .await?;
+                let release = github_api::shared()
+                    .get_json::<Release>(&path)
+                    .await
+                    .map_err(anyhow::Error::from)
+                    .and_then(|release| {
+                        release.ok_or_else(|| anyhow!("Release {} not found", version))
+                    });
+
+                // Offline, the zipball URL is still predictable and may be cached
+                let zipball_url = match release {
+                    Ok(release) => release.zipball_url,
+                    Err(e) => {
+                        log::warn!("Failed to fetch release {}: {}", version, e);
+                        format!(
+                            "https://api.github.com/repos/{}/zipball/{}",
+                            self.mod_type.get_repo_url(),
+                            version
+                        )
+                    }
+                };
 
-                info!("Downloading from {}", release.zipball_url);
+                info!("Downloading from {}", zipball_url);
 
                 // Download the zip file
-                let response = self
-                    .client
-                    .get(&release.zipball_url)
-                    .headers(headers)
-                    .send()
-                    .await?;
-                let bytes = response.bytes().await?;
+                let (bytes, sha256) =
+                    installer::fetch_archive_with_progress(&zipball_url, expected_sha256, progress)
+                        .await?;
 
                 // Create temp directory
                 let temp_dir = mods_dir.join("temp_smods");
@@ -204,7 +201,7 @@ impl ModInstaller {
                 // Extract to temp directory
                 let cursor = Cursor::new(bytes);
                 let mut archive = ZipArchive::new(cursor)?;
-                archive.extract(&temp_dir)?;
+                installer::extract_zip(&mut archive, &temp_dir, progress)?;
 
                 // Find the root directory name (GitHub format: Steamodded-smods-commitHash)
                 let root_dir = fs::read_dir(&temp_dir)?
@@ -214,6 +211,12 @@ impl ModInstaller {
                     .into_string()
                     .map_err(|_| anyhow!("Invalid directory name"))?;
 
+                if let Err(e) = progress.check_cancelled() {
+                    fs::remove_dir_all(&temp_dir)?;
+                    return Err(e.into());
+                }
+                progress.report(Progress::Finalising);
+
                 // Move to final location
                 let final_dir = mods_dir.join(&root_dir);
                 if final_dir.exists() {
@@ -228,7 +231,11 @@ impl ModInstaller {
                     "Successfully installed Steamodded version {} to {:?}",
                     version, final_dir
                 );
-                Ok(final_dir.to_string_lossy().to_string())
+                Ok(InstalledArchive {
+                    path: final_dir,
+                    url: zipball_url,
+                    sha256,
+                })
             }
             ModType::Talisman => {
                 let url = format!(
@@ -238,9 +245,16 @@ impl ModInstaller {
 
                 info!("Downloading Talisman.zip from {}", url);
 
+                progress.report(Progress::Resolving);
+                let expected = match expected_sha256 {
+                    Some(hash) => Some(hash.to_string()),
+                    None => self.release_asset_digest(version, "Talisman.zip").await,
+                };
+
                 // Download and extract zip logic here
-                let response = self.client.get(&url).send().await?;
-                let bytes = response.bytes().await?;
+                let (bytes, sha256) =
+                    installer::fetch_archive_with_progress(&url, expected.as_deref(), progress)
+                        .await?;
 
                 // Create installation directory
                 tokio_fs::create_dir_all(&mods_dir).await?;
@@ -248,26 +262,37 @@ impl ModInstaller {
                 // Extract directly to installation path
                 let cursor = Cursor::new(bytes);
                 let mut archive = ZipArchive::new(cursor)?;
+                installer::extract_zip(&mut archive, &mods_dir, progress)?;
 
-                for i in 0..archive.len() {
-                    let mut file = archive.by_index(i)?;
-                    let outpath = mods_dir.join(file.name());
-
-                    if file.name().ends_with('/') {
-                        fs::create_dir_all(&outpath)?;
-                    } else {
-                        if let Some(p) = outpath.parent() {
-                            fs::create_dir_all(p)?;
-                        }
-                        let mut outfile = fs::File::create(&outpath)?;
-                        std::io::copy(&mut file, &mut outfile)?;
-                    }
-                }
-                Ok(mods_dir.join("Talisman").to_string_lossy().to_string())
+                Ok(InstalledArchive {
+                    path: mods_dir.join("Talisman"),
+                    url,
+                    sha256,
+                })
             }
         }
     }
 
+    // Best effort: a missing digest only means the download can't be checked
+    async fn release_asset_digest(&self, version: &str, asset_name: &str) -> Option<String> {
+        let path = format!(
+            "repos/{}/releases/tags/{}",
+            self.mod_type.get_repo_url(),
+            version
+        );
+        let release: Release = github_api::shared().get_json(&path).await.ok()??;
+
+        let digest = release
+            .assets
+            .into_iter()
+            .find(|asset| asset.name == asset_name)
+            .and_then(|asset| asset.digest);
+        if digest.is_none() {
+            log::debug!("No published digest for {} {}", asset_name, version);
+        }
+        digest
+    }
+
     pub async fn uninstall(&self) -> Result<()> {
         let mods_dir = get_lovely_mods_dir(self.installation_path.as_ref());
         if !mods_dir.exists() {
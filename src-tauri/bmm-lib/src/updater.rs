@@ -0,0 +1,222 @@
+use crate::cache::Mod;
+use crate::database::InstalledMod;
+use crate::errors::AppError;
+use crate::installer::{self, InstalledArchive};
+use crate::version::{self, UpdateKind};
+use serde::Serialize;
+use std::path::Path;
+
+#[derive(Debug, Default, Serialize)]
+pub struct UpdateReport {
+    pub updated: Vec<UpdatedMod>,
+    pub skipped: Vec<SkippedMod>,
+    pub failed: Vec<FailedMod>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UpdatedMod {
+    pub name: String,
+    pub from_version: String,
+    pub to_version: String,
+    pub kind: UpdateKind,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SkippedMod {
+    pub name: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FailedMod {
+    pub name: String,
+    pub error: String,
+}
+
+struct PendingUpdate<'a> {
+    installed: InstalledMod,
+    catalog_mod: &'a Mod,
+    from_version: String,
+    to_version: String,
+    kind: UpdateKind,
+}
+
+impl UpdateReport {
+    fn skip(&mut self, name: &str, reason: impl Into<String>) {
+        self.skipped.push(SkippedMod {
+            name: name.to_string(),
+            reason: reason.into(),
+        });
+    }
+}
+
+// Split installed mods into the ones with a newer catalog version and the rest
+fn plan_updates<'a>(
+    installed_mods: Vec<InstalledMod>,
+    catalog: &'a [Mod],
+    report: &mut UpdateReport,
+) -> Vec<PendingUpdate<'a>> {
+    let mut pending = Vec::new();
+
+    for installed in installed_mods {
+        let Some(catalog_mod) = catalog.iter().find(|m| {
+            m.title == installed.name || m.folderName.as_deref() == Some(installed.name.as_str())
+        }) else {
+            report.skip(&installed.name, "not in the mod index");
+            continue;
+        };
+
+        if installed.pinned {
+            report.skip(&installed.name, "pinned");
+            continue;
+        }
+
+        let Some(to_version) = catalog_mod.version.clone().filter(|v| !v.is_empty()) else {
+            report.skip(&installed.name, "index has no version");
+            continue;
+        };
+
+        let from_version = installed.current_version.clone().unwrap_or_default();
+        let Some(kind) = version::update_kind(&from_version, &to_version) else {
+            report.skip(&installed.name, "up to date");
+            continue;
+        };
+
+        if catalog_mod.download_url.is_empty() {
+            report.skip(&installed.name, "index has no download URL");
+            continue;
+        }
+
+        pending.push(PendingUpdate {
+            installed,
+            catalog_mod,
+            from_version,
+            to_version,
+            kind,
+        });
+    }
+
+    pending
+}
+
+/// Reinstall every installed mod that has a newer version in `catalog`.
+/// `record` is called after each successful install so the caller can store
+/// the new path, archive hash and version. A failing mod is reported and the rest still run.
+pub async fn update_all<F>(
+    installation_path: Option<&String>,
+    installed_mods: Vec<InstalledMod>,
+    catalog: &[Mod],
+    mut record: F,
+) -> UpdateReport
+where
+    F: FnMut(&InstalledMod, &InstalledArchive, &str) -> Result<(), AppError>,
+{
+    let mut report = UpdateReport::default();
+    let pending = plan_updates(installed_mods, catalog, &mut report);
+
+    for update in pending {
+        let name = update.installed.name.clone();
+        log::info!(
+            "Updating {} from '{}' to '{}'",
+            name,
+            update.from_version,
+            update.to_version
+        );
+
+        // Reinstall into the same folder the mod currently lives in
+        let current_path = Path::new(&update.installed.path);
+        // A fresh copy has no .lovelyignore, so a disabled mod is disabled again afterwards
+        let enabled = installer::is_mod_enabled(current_path).unwrap_or(true);
+        let folder_name = current_path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .map(str::to_string);
+
+        let result = match installer::install_mod(
+            installation_path,
+            update.catalog_mod.download_url.clone(),
+            folder_name,
+            update.catalog_mod.sha256.as_deref(),
+        )
+        .await
+        {
+            Ok(archive) if !enabled => installer::set_mod_enabled(&archive.path, false)
+                .and_then(|_| record(&update.installed, &archive, &update.to_version)),
+            Ok(archive) => record(&update.installed, &archive, &update.to_version),
+            Err(e) => Err(e),
+        };
+
+        match result {
+            Ok(()) => report.updated.push(UpdatedMod {
+                name,
+                from_version: update.from_version,
+                to_version: update.to_version,
+                kind: update.kind,
+            }),
+            Err(e) => {
+                log::error!("Failed to update {}: {}", name, e);
+                report.failed.push(FailedMod {
+                    name,
+                    error: e.to_string(),
+                });
+            }
+        }
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn catalog_mod(title: &str, version: Option<&str>) -> Mod {
+        Mod {
+            version: version.map(str::to_string),
+            ..Mod::for_test(title)
+        }
+    }
+
+    fn installed(name: &str, version: &str) -> InstalledMod {
+        InstalledMod {
+            name: name.to_string(),
+            path: format!("/mods/{}", name),
+            dependencies: Vec::new(),
+            current_version: Some(version.to_string()),
+            archive_sha256: None,
+            download_url: None,
+            pinned: false,
+        }
+    }
+
+    #[test]
+    fn test_plan_updates_only_picks_newer_versions() {
+        let catalog = vec![
+            catalog_mod("Newer", Some("1.1.0")),
+            catalog_mod("Same", Some("v1.0")),
+            catalog_mod("Older", Some("0.9.0")),
+            catalog_mod("Unversioned", None),
+            catalog_mod("Pinned", Some("2.0.0")),
+        ];
+        let installed_mods = vec![
+            installed("Newer", "1.0.0"),
+            installed("Same", "1.0.0"),
+            installed("Older", "1.0.0"),
+            installed("Unversioned", "1.0.0"),
+            installed("Manual", "1.0.0"),
+            InstalledMod {
+                pinned: true,
+                ..installed("Pinned", "1.0.0")
+            },
+        ];
+
+        let mut report = UpdateReport::default();
+        let pending = plan_updates(installed_mods, &catalog, &mut report);
+
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].installed.name, "Newer");
+        assert_eq!(pending[0].kind, UpdateKind::Minor);
+        assert_eq!(report.skipped.len(), 5);
+        assert!(report.failed.is_empty());
+    }
+}
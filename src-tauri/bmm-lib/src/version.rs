@@ -0,0 +1,199 @@
+use serde::Serialize;
+use std::cmp::Ordering;
+
+/// A loosely parsed mod version. Handles plain semver, a leading `v`,
+/// Steamodded's `1.0.0~ALPHA-1234a` pre-release style and dates such as
+/// `2024-03-01`. Missing components count as zero, so `1.0` == `1.0.0`.
+#[derive(Debug, Clone)]
+pub struct Version {
+    numbers: Vec<u64>,
+    pre: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UpdateKind {
+    Major,
+    Minor,
+    Patch,
+    Prerelease,
+}
+
+impl Version {
+    pub fn parse(input: &str) -> Option<Self> {
+        let trimmed = input.trim();
+        let trimmed = trimmed
+            .strip_prefix('v')
+            .or_else(|| trimmed.strip_prefix('V'))
+            .unwrap_or(trimmed);
+        // Build metadata never affects ordering
+        let trimmed = trimmed.split('+').next().unwrap_or_default();
+
+        // Dates use '-' between numbers, everything else uses it for pre-releases
+        let is_date = trimmed.len() == 10
+            && trimmed.chars().enumerate().all(|(i, c)| {
+                if i == 4 || i == 7 {
+                    c == '-'
+                } else {
+                    c.is_ascii_digit()
+                }
+            });
+
+        let (core, pre) = if is_date {
+            (trimmed, "")
+        } else {
+            match trimmed.find(['~', '-']) {
+                Some(idx) => (&trimmed[..idx], &trimmed[idx + 1..]),
+                None => (trimmed, ""),
+            }
+        };
+
+        let numbers = core
+            .split(['.', '-'])
+            .map(|part| part.parse::<u64>().ok())
+            .collect::<Option<Vec<_>>>()?;
+
+        let pre = pre
+            .split(['.', '-', '~'])
+            .filter(|part| !part.is_empty())
+            .map(|part| part.to_lowercase())
+            .collect();
+
+        Some(Self { numbers, pre })
+    }
+
+    fn number(&self, idx: usize) -> u64 {
+        self.numbers.get(idx).copied().unwrap_or(0)
+    }
+
+    fn compare_numbers(&self, other: &Self) -> (Ordering, usize) {
+        let len = self.numbers.len().max(other.numbers.len());
+        for idx in 0..len {
+            match self.number(idx).cmp(&other.number(idx)) {
+                Ordering::Equal => continue,
+                ordering => return (ordering, idx),
+            }
+        }
+        (Ordering::Equal, len)
+    }
+}
+
+impl PartialEq for Version {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Version {}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match self.compare_numbers(other).0 {
+            Ordering::Equal => {}
+            ordering => return ordering,
+        }
+
+        // A release sorts after any of its pre-releases
+        match (self.pre.is_empty(), other.pre.is_empty()) {
+            (true, true) => Ordering::Equal,
+            (true, false) => Ordering::Greater,
+            (false, true) => Ordering::Less,
+            (false, false) => {
+                for (a, b) in self.pre.iter().zip(&other.pre) {
+                    match compare_identifier(a, b) {
+                        Ordering::Equal => continue,
+                        ordering => return ordering,
+                    }
+                }
+                self.pre.len().cmp(&other.pre.len())
+            }
+        }
+    }
+}
+
+// Compares a leading number first so that "0301a" < "1234a" and "9" < "10"
+fn compare_identifier(a: &str, b: &str) -> Ordering {
+    fn split(s: &str) -> (Option<u64>, &str) {
+        let idx = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+        (s[..idx].parse::<u64>().ok(), &s[idx..])
+    }
+    let (a_num, a_rest) = split(a);
+    let (b_num, b_rest) = split(b);
+
+    match (a_num, b_num) {
+        (Some(x), Some(y)) => x.cmp(&y).then_with(|| a_rest.cmp(b_rest)),
+        // Numeric identifiers sort before alphanumeric ones, as in semver
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => a_rest.cmp(b_rest),
+    }
+}
+
+/// Returns the kind of update going from `installed` to `available`, or
+/// `None` if `available` is not newer or either side cannot be parsed.
+pub fn update_kind(installed: &str, available: &str) -> Option<UpdateKind> {
+    let (Some(current), Some(candidate)) = (Version::parse(installed), Version::parse(available))
+    else {
+        log::debug!(
+            "Cannot compare versions '{}' and '{}'",
+            installed,
+            available
+        );
+        return None;
+    };
+
+    if candidate <= current {
+        return None;
+    }
+
+    match current.compare_numbers(&candidate) {
+        (Ordering::Less, 0) => Some(UpdateKind::Major),
+        (Ordering::Less, 1) => Some(UpdateKind::Minor),
+        (Ordering::Less, _) => Some(UpdateKind::Patch),
+        _ => Some(UpdateKind::Prerelease),
+    }
+}
+
+pub fn is_newer(installed: &str, available: &str) -> bool {
+    update_kind(installed, available).is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v(s: &str) -> Version {
+        Version::parse(s).unwrap()
+    }
+
+    #[test]
+    fn test_loose_formats_compare() {
+        assert_eq!(v("v1.0"), v("1.0.0"));
+        assert!(v("1.0.0~ALPHA-0301a") < v("1.0.0~ALPHA-1234a"));
+        assert!(v("1.0.0~ALPHA-1234a") < v("1.0.0~BETA-0101a"));
+        assert!(v("1.0.0-beta") < v("1.0.0"));
+        assert!(v("1.9.0") < v("1.10.0"));
+        assert!(v("2024-03-01") < v("2024-11-20"));
+        assert!(Version::parse("latest").is_none());
+    }
+
+    #[test]
+    fn test_update_kind() {
+        assert_eq!(update_kind("1.2.3", "2.0.0"), Some(UpdateKind::Major));
+        assert_eq!(update_kind("v1.2.3", "1.3"), Some(UpdateKind::Minor));
+        assert_eq!(update_kind("1.2", "1.2.1"), Some(UpdateKind::Patch));
+        assert_eq!(
+            update_kind("1.0.0~ALPHA-1234a", "1.0.0~ALPHA-1300a"),
+            Some(UpdateKind::Prerelease)
+        );
+        assert_eq!(update_kind("1.0.0", "v1.0"), None);
+        assert_eq!(update_kind("1.3.0", "1.2.9"), None);
+        assert_eq!(update_kind("nightly", "1.0.0"), None);
+    }
+}
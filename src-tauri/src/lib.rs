@@ -1,48 +1,175 @@
-mod github_repo;
-
 use base64::{engine::general_purpose::STANDARD, Engine as _};
-use flate2::read::GzDecoder;
-use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
-use serde_json::json;
-use tar::Archive;
 use tauri::Emitter;
 use tauri::Manager;
 use tauri_plugin_window_state::StateFlags;
-use walkdir::WalkDir;
-use zip::ZipArchive;
 
-use std::collections::HashSet;
-use std::fs::File;
+use std::collections::HashMap;
 use std::path::Path;
 use std::path::PathBuf;
-use std::process::Command;
+use std::sync::atomic::{self, AtomicBool};
+use std::sync::Arc;
 use std::sync::Mutex;
 use std::time::SystemTime;
 use std::time::UNIX_EPOCH;
-use std::{fs, io::Cursor};
+use std::time::{Duration, Instant};
 
+use bmm_lib::archive_store::{self, StoredArchive};
 use bmm_lib::balamod::find_balatros;
 use bmm_lib::cache;
-use bmm_lib::cache::Mod;
+use bmm_lib::cache::{Category, Mod};
+use bmm_lib::catalog::{CatalogQuery, CatalogStatus, CatalogStore};
 use bmm_lib::database::Database;
-use bmm_lib::database::InstalledMod;
+use bmm_lib::database::{InstalledMod, VersionRecord};
 use bmm_lib::discord_rpc::DiscordRpcManager;
+use bmm_lib::download;
 use bmm_lib::errors::AppError;
+use bmm_lib::finder::get_lovely_mods_dir;
 use bmm_lib::finder::is_balatro_running;
 use bmm_lib::finder::is_steam_running;
+use bmm_lib::github_api::{self, RateLimit};
+use bmm_lib::github_repo::{self, RepoSyncSummary};
+use bmm_lib::index::{self, ModMeta};
+use bmm_lib::installer::{self, InstalledArchive};
+use bmm_lib::launcher;
 use bmm_lib::local_mod_detection;
-use bmm_lib::lovely;
+use bmm_lib::lockfile::{self, Lockfile, SyncReport};
+use bmm_lib::manager::ModManager;
+use bmm_lib::manifest::VerifyReport;
+use bmm_lib::mod_collections::{self, CollectionFormat, CollectionReport, ModCollection};
+use bmm_lib::profiles::{self, Profile, ProfileReport};
+use bmm_lib::progress::{NoProgress, Progress, ProgressSink};
+use bmm_lib::releases::{self, ReleaseCheckSummary};
+use bmm_lib::resolver::{self, InstallPlan};
+use bmm_lib::search::{SearchIndex, SearchQuery, SearchResults};
+use bmm_lib::settings::Settings;
 use bmm_lib::smods_installer::{ModInstaller, ModType};
+use bmm_lib::updater::{self, UpdateReport};
+use bmm_lib::version::{self, UpdateKind};
 
 fn map_error<T>(result: Result<T, AppError>) -> Result<T, String> {
     result.map_err(|e| e.to_string())
 }
 
-// Create a state structure to hold the database
+// Create a state structure to hold the mod manager and its database
 struct AppState {
-    db: Mutex<Database>,
+    // The database behind it hands out its own connections, so commands
+    // share the manager without locking
+    manager: ModManager,
+    // Held by every command that writes the mods folder or the installed
+    // mods in the database, so two of them never interleave
+    operations: tauri::async_runtime::Mutex<()>,
     discord_rpc: Mutex<DiscordRpcManager>,
+    // Cancel flags of installs reporting progress, keyed by their operation id
+    cancel_flags: Mutex<HashMap<String, Arc<AtomicBool>>>,
+}
+
+// Byte and file counts arrive far faster than anyone can read them
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct OperationProgress {
+    operation_id: String,
+    #[serde(flatten)]
+    progress: Progress,
+}
+
+// Forwards the progress of one install to the frontend as "operation-progress"
+// events and lets `cancel_operation` stop it. The operation id is released
+// when the sink is dropped.
+struct OperationSink {
+    app: tauri::AppHandle,
+    operation_id: String,
+    cancelled: Arc<AtomicBool>,
+    last_sent: Mutex<Option<Instant>>,
+}
+
+impl OperationSink {
+    fn start(
+        app: &tauri::AppHandle,
+        state: &AppState,
+        operation_id: String,
+    ) -> Result<Self, String> {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let mut flags = state.cancel_flags.lock().map_err(|e| e.to_string())?;
+        if flags.contains_key(&operation_id) {
+            return Err(format!("Operation {} is already running", operation_id));
+        }
+        flags.insert(operation_id.clone(), cancelled.clone());
+        Ok(Self {
+            app: app.clone(),
+            operation_id,
+            cancelled,
+            last_sent: Mutex::new(None),
+        })
+    }
+}
+
+impl ProgressSink for OperationSink {
+    fn report(&self, progress: Progress) {
+        let finished_step = match &progress {
+            Progress::Downloading { received, total } => Some(*received) == *total,
+            Progress::Extracting { done, total } => done == total,
+            Progress::Resolving | Progress::Finalising => true,
+        };
+        if let Ok(mut last_sent) = self.last_sent.lock() {
+            if !finished_step && last_sent.is_some_and(|at| at.elapsed() < PROGRESS_INTERVAL) {
+                return;
+            }
+            *last_sent = Some(Instant::now());
+        }
+        if let Err(e) = self.app.emit(
+            "operation-progress",
+            OperationProgress {
+                operation_id: self.operation_id.clone(),
+                progress,
+            },
+        ) {
+            log::warn!("Failed to report progress of {}: {}", self.operation_id, e);
+        }
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.load(atomic::Ordering::Relaxed)
+    }
+}
+
+impl Drop for OperationSink {
+    fn drop(&mut self) {
+        if let Ok(mut flags) = self.app.state::<AppState>().cancel_flags.lock() {
+            flags.remove(&self.operation_id);
+        }
+    }
+}
+
+// Progress goes nowhere unless the frontend gave the operation an id to follow
+fn progress_sink(
+    app: &tauri::AppHandle,
+    state: &AppState,
+    operation_id: Option<String>,
+) -> Result<Box<dyn ProgressSink>, String> {
+    Ok(match operation_id {
+        Some(id) => Box::new(OperationSink::start(app, state, id)?),
+        None => Box::new(NoProgress),
+    })
+}
+
+/// Ask a running install to stop. Returns false when no operation has that
+/// id, e.g. because it already finished.
+#[tauri::command]
+async fn cancel_operation(
+    state: tauri::State<'_, AppState>,
+    operation_id: String,
+) -> Result<bool, String> {
+    let flags = state.cancel_flags.lock().map_err(|e| e.to_string())?;
+    Ok(match flags.get(&operation_id) {
+        Some(cancelled) => {
+            cancelled.store(true, atomic::Ordering::Relaxed);
+            true
+        }
+        None => false,
+    })
 }
 
 #[derive(Clone, serde::Serialize)]
@@ -51,26 +178,6 @@ struct Payload {
     cwd: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ModMeta {
-    #[serde(rename = "requires-steamodded")]
-    pub requires_steamodded: bool,
-    #[serde(rename = "requires-talisman")]
-    pub requires_talisman: bool,
-    pub categories: Vec<String>,
-    pub author: String,
-    pub repo: String,
-    pub title: String,
-    #[serde(rename = "downloadURL")]
-    pub download_url: Option<String>,
-    #[serde(rename = "folderName", default)]
-    pub folder_name: String,
-    #[serde(default)]
-    pub version: String,
-    #[serde(rename = "automatic-version-check", default)]
-    automatic_version_check: bool,
-}
-
 #[tauri::command]
 async fn check_steam_running() -> bool {
     is_steam_running()
@@ -86,56 +193,438 @@ async fn save_versions_cache(mod_type: String, versions: Vec<String>) -> Result<
     map_error(cache::save_versions_cache(&mod_type, &versions))
 }
 
-#[tauri::command]
-async fn mod_update_available(
-    mod_name: String,
-    state: tauri::State<'_, AppState>,
-) -> Result<bool, String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
+// Compares the installed version against the cached index entry
+fn find_mod_update(
+    state: &tauri::State<'_, AppState>,
+    mod_name: &str,
+) -> Result<Option<UpdateKind>, String> {
+    let manager = &state.manager;
+    let db = manager.db();
     let last_installed_version = db
-        .get_last_installed_version(&mod_name)
+        .get_last_installed_version(mod_name)
         .map_err(|e| e.to_string())?;
 
-    if last_installed_version.is_empty() {
-        return Ok(false);
+    // Pinned mods stay on their version until they are unpinned
+    if last_installed_version.is_empty() || map_error(db.is_mod_pinned(mod_name))? {
+        return Ok(None);
     }
 
     let cached_mods = match crate::cache::load_cache().map_err(|e| e.to_string())? {
         Some((mods, _)) => mods,
-        None => return Ok(false),
+        None => return Ok(None),
     };
 
     for cached_mod in cached_mods {
-        if cached_mod.title == mod_name || (cached_mod.folderName.as_ref() == Some(&mod_name)) {
+        if cached_mod.title == mod_name || (cached_mod.folderName.as_deref() == Some(mod_name)) {
             if let Some(remote_version) = cached_mod.version {
-                return Ok(remote_version != last_installed_version);
+                return Ok(version::update_kind(
+                    &last_installed_version,
+                    &remote_version,
+                ));
             }
             break;
         }
     }
 
-    Ok(false)
+    Ok(None)
+}
+
+#[tauri::command]
+async fn mod_update_available(
+    mod_name: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<bool, String> {
+    Ok(find_mod_update(&state, &mod_name)?.is_some())
+}
+
+#[tauri::command]
+async fn get_mod_update_kind(
+    mod_name: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<Option<UpdateKind>, String> {
+    find_mod_update(&state, &mod_name)
+}
+
+#[tauri::command]
+async fn update_all_mods(state: tauri::State<'_, AppState>) -> Result<UpdateReport, String> {
+    let _operation = state.operations.lock().await;
+    let installed_mods = map_error(state.manager.db().get_installed_mods())?;
+
+    let catalog = match map_error(cache::load_cache())? {
+        Some((mods, _)) => mods,
+        None => return Err("Mod list is not cached yet".to_string()),
+    };
+
+    let report = updater::update_all(
+        None,
+        installed_mods,
+        &catalog,
+        |installed, archive, version| {
+            state.manager.record_install(
+                &installed.name,
+                &archive.path,
+                &installed.dependencies,
+                Some(version.to_string()),
+                Some(archive),
+            )
+        },
+    )
+    .await;
+
+    Ok(report)
+}
+
+#[derive(Clone, Serialize)]
+struct SettingChanged {
+    key: String,
+    value: serde_json::Value,
+}
+
+// Every settings write goes through here so dependent state stays in sync and
+// all windows hear about the change
+fn change_setting(
+    app: &tauri::AppHandle,
+    state: &AppState,
+    key: &str,
+    value: serde_json::Value,
+) -> Result<Settings, String> {
+    let settings = map_error(state.manager.db().update_settings(|s| s.set(key, value)))?;
+
+    if key == "discord_rpc" {
+        state
+            .discord_rpc
+            .lock()
+            .map_err(|_| AppError::LockPoisoned("Discord RPC lock poisoned".to_string()))?
+            .set_enabled(settings.discord_rpc);
+    }
+    if key == "github_token" {
+        github_api::shared().set_token(settings.github_token.clone());
+    }
+    if key == "download_proxy" {
+        map_error(download::shared().set_proxy(settings.download_proxy.clone()))?;
+    }
+
+    let value = map_error(settings.get_display(key))?;
+    if let Err(e) = app.emit(
+        "setting-changed",
+        SettingChanged {
+            key: key.to_string(),
+            value,
+        },
+    ) {
+        log::warn!("Failed to announce change of {}: {}", key, e);
+    }
+    Ok(settings)
+}
+
+#[tauri::command]
+async fn get_settings(
+    state: tauri::State<'_, AppState>,
+) -> Result<serde_json::Map<String, serde_json::Value>, String> {
+    map_error(map_error(state.manager.db().settings())?.to_display())
+}
+
+#[tauri::command]
+async fn get_setting(
+    state: tauri::State<'_, AppState>,
+    key: String,
+) -> Result<serde_json::Value, String> {
+    map_error(map_error(state.manager.db().settings())?.get_display(&key))
+}
+
+#[tauri::command]
+async fn set_setting(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    key: String,
+    value: serde_json::Value,
+) -> Result<serde_json::Map<String, serde_json::Value>, String> {
+    map_error(change_setting(&app, &state, &key, value)?.to_display())
+}
+
+#[tauri::command]
+async fn list_profiles(state: tauri::State<'_, AppState>) -> Result<Vec<String>, String> {
+    map_error(state.manager.db().list_profiles())
+}
+
+#[tauri::command]
+async fn get_profile(state: tauri::State<'_, AppState>, name: String) -> Result<Profile, String> {
+    map_error(state.manager.db().get_profile(&name))
+}
+
+#[tauri::command]
+async fn get_active_profile(state: tauri::State<'_, AppState>) -> Result<Option<String>, String> {
+    Ok(map_error(state.manager.db().settings())?.active_profile)
+}
+
+#[tauri::command]
+async fn save_profile(state: tauri::State<'_, AppState>, profile: Profile) -> Result<(), String> {
+    map_error(state.manager.db().save_profile(&profile))
+}
+
+// Saves the current mod setup under `name`
+#[tauri::command]
+async fn snapshot_profile(
+    state: tauri::State<'_, AppState>,
+    name: String,
+) -> Result<Profile, String> {
+    let manager = &state.manager;
+    let profile = map_error(manager.snapshot_profile(&name))?;
+    map_error(manager.db().save_profile(&profile))?;
+    Ok(profile)
+}
+
+#[tauri::command]
+async fn delete_profile(state: tauri::State<'_, AppState>, name: String) -> Result<(), String> {
+    map_error(state.manager.db().delete_profile(&name))
+}
+
+#[tauri::command]
+async fn activate_profile(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    name: String,
+) -> Result<ProfileReport, String> {
+    let _operation = state.operations.lock().await;
+    let (profile, plan) = {
+        let manager = &state.manager;
+        let profile = map_error(manager.db().get_profile(&name))?;
+        let installed_mods = map_error(manager.db().get_installed_mods())?;
+        let plan = profiles::plan_activation(&profile, &installed_mods);
+        (profile, plan)
+    };
+
+    let mut report = ProfileReport::new(&profile.name);
+    if !plan.install.is_empty() {
+        let catalog = match map_error(cache::load_cache())? {
+            Some((mods, _)) => mods,
+            None => return Err("Mod list is not cached yet".to_string()),
+        };
+        profiles::install_missing(
+            None,
+            &plan,
+            &catalog,
+            &mut report,
+            |entry, catalog_mod, archive, version| {
+                state.manager.record_install(
+                    &entry.name,
+                    &archive.path,
+                    &installer::catalog_dependencies(catalog_mod),
+                    version,
+                    Some(archive),
+                )
+            },
+        )
+        .await;
+    }
+
+    profiles::apply_states(&state.manager, &plan, &mut report);
+    change_setting(
+        &app,
+        &state,
+        "active_profile",
+        serde_json::json!(profile.name),
+    )?;
+    Ok(report)
+}
+
+#[tauri::command]
+async fn export_lockfile(state: tauri::State<'_, AppState>, path: String) -> Result<(), String> {
+    let catalog = map_error(cache::load_cache())?
+        .map(|(mods, _)| mods)
+        .unwrap_or_default();
+    let manager = &state.manager;
+    let lockfile = map_error(Lockfile::generate(manager, &catalog))?;
+    map_error(lockfile.write(Path::new(&path)))
+}
+
+#[tauri::command]
+async fn sync_lockfile(
+    state: tauri::State<'_, AppState>,
+    path: String,
+) -> Result<SyncReport, String> {
+    let _operation = state.operations.lock().await;
+    let lockfile = map_error(Lockfile::read(Path::new(&path)))?;
+    let plan = {
+        let manager = &state.manager;
+        let installed_mods = map_error(manager.db().get_installed_mods())?;
+        lockfile::plan_sync(&lockfile, &installed_mods, |m| {
+            manager
+                .verify_mod(&m.name)
+                .is_ok_and(|report| report.is_clean())
+        })
+    };
+
+    let mut report = SyncReport::default();
+    let downloads = lockfile::fetch_locked(&plan, &mut report).await;
+
+    let manager = &state.manager;
+    lockfile::apply_sync(manager, &lockfile, &plan, downloads, &mut report);
+    Ok(report)
+}
+
+#[tauri::command]
+async fn list_collections(state: tauri::State<'_, AppState>) -> Result<Vec<ModCollection>, String> {
+    map_error(state.manager.db().list_collections())
+}
+
+#[tauri::command]
+async fn get_collection(
+    state: tauri::State<'_, AppState>,
+    id: String,
+) -> Result<ModCollection, String> {
+    map_error(state.manager.db().get_collection(&id))
+}
+
+#[tauri::command]
+async fn save_collection(
+    state: tauri::State<'_, AppState>,
+    collection: ModCollection,
+) -> Result<(), String> {
+    map_error(state.manager.db().save_collection(&collection))
+}
+
+// Captures every installed mod with its exact version and download
+#[tauri::command]
+async fn create_collection(
+    state: tauri::State<'_, AppState>,
+    name: String,
+    description: Option<String>,
+) -> Result<ModCollection, String> {
+    let catalog = map_error(cache::load_cache())?
+        .map(|(mods, _)| mods)
+        .unwrap_or_default();
+    let manager = &state.manager;
+    let installed_mods = map_error(manager.db().get_installed_mods())?;
+    let mut collection = ModCollection::from_installed(name, &installed_mods, &catalog);
+    collection.description = description.filter(|d| !d.trim().is_empty());
+    map_error(manager.db().save_collection(&collection))?;
+    Ok(collection)
+}
+
+#[tauri::command]
+async fn delete_collection(state: tauri::State<'_, AppState>, id: String) -> Result<(), String> {
+    map_error(state.manager.db().delete_collection(&id))
+}
+
+// Writes JSON or TOML depending on the file extension
+#[tauri::command]
+async fn export_collection(
+    state: tauri::State<'_, AppState>,
+    id: String,
+    path: String,
+) -> Result<(), String> {
+    let collection = map_error(state.manager.db().get_collection(&id))?;
+    let path = PathBuf::from(path);
+    let content = map_error(collection.export(CollectionFormat::from_path(&path)))?;
+    std::fs::write(&path, content).map_err(|e| {
+        AppError::FileWrite {
+            path: path.clone(),
+            source: e.to_string(),
+        }
+        .to_string()
+    })
+}
+
+#[tauri::command]
+async fn import_collection(
+    state: tauri::State<'_, AppState>,
+    path: String,
+) -> Result<ModCollection, String> {
+    let path = PathBuf::from(path);
+    let content = std::fs::read_to_string(&path).map_err(|e| {
+        AppError::FileRead {
+            path: path.clone(),
+            source: e.to_string(),
+        }
+        .to_string()
+    })?;
+    let collection = map_error(ModCollection::import(
+        &content,
+        CollectionFormat::from_path(&path),
+    ))?;
+    // Comes back with a new id if the file's id is already taken
+    map_error(state.manager.db().import_collection(collection))
+}
+
+#[tauri::command]
+async fn install_collection(
+    state: tauri::State<'_, AppState>,
+    id: String,
+) -> Result<CollectionReport, String> {
+    let _operation = state.operations.lock().await;
+    let (collection, installed_mods) = {
+        let manager = &state.manager;
+        (
+            map_error(manager.db().get_collection(&id))?,
+            map_error(manager.db().get_installed_mods())?,
+        )
+    };
+    let catalog = map_error(cache::load_cache())?
+        .map(|(mods, _)| mods)
+        .unwrap_or_default();
+
+    let report = mod_collections::install_collection(
+        None,
+        &collection,
+        &installed_mods,
+        &catalog,
+        |entry, archive, version, dependencies| {
+            state.manager.record_install(
+                &entry.name,
+                &archive.path,
+                dependencies,
+                version,
+                Some(archive),
+            )
+        },
+    )
+    .await;
+
+    Ok(report)
 }
 
 #[tauri::command]
 async fn get_repo_path() -> Result<String, String> {
-    let config_dir = dirs::config_dir()
-        .ok_or_else(|| AppError::DirNotFound(PathBuf::from("config directory")).to_string())?;
-    let repo_path = config_dir.join("Balatro").join("mod_index");
+    let repo_path = map_error(index::get_repo_path())?;
     Ok(repo_path.to_string_lossy().into_owned())
 }
 
 #[tauri::command]
-async fn clone_repo(url: &str, path: &str) -> Result<(), String> {
+async fn clone_repo(url: &str, path: &str) -> Result<RepoSyncSummary, String> {
     github_repo::clone_repository(url, path).await
 }
 
+#[derive(Debug, Serialize)]
+pub struct IndexSourceSync {
+    pub name: String,
+    pub summary: Option<RepoSyncSummary>,
+    pub error: Option<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ModCacheInfo {
     pub path: String,
     pub last_commit: i64,
 }
 
+fn image_data_url(path: &Path) -> Option<String> {
+    let mime = match path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_lowercase)
+        .as_deref()
+    {
+        Some("png") => "image/png",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        _ => "image/jpeg",
+    };
+    let image_data = std::fs::read(path).ok()?;
+    let base64 = STANDARD.encode(image_data);
+    Some(format!("data:{};base64,{}", mime, base64))
+}
+
 #[allow(non_snake_case)]
 #[tauri::command]
 async fn get_mod_thumbnail(modPath: String) -> Result<Option<String>, String> {
@@ -149,15 +638,121 @@ async fn get_mod_thumbnail(modPath: String) -> Result<Option<String>, String> {
         .join(modPath)
         .join("thumbnail.jpg");
 
-    let image_data = match std::fs::read(&full_path) {
-        Ok(data) => data,
-        Err(_) => {
-            return Ok(None);
+    Ok(image_data_url(&full_path))
+}
+
+// Index sources are synced at most this often
+const INDEX_SYNC_INTERVAL: u64 = 60 * 60;
+
+/// Sync the configured index sources when they are due, merge them into
+/// one catalog and store it. Thumbnails come back as data URLs.
+#[tauri::command]
+async fn refresh_catalog(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<Mod>, String> {
+    let settings = map_error(state.manager.db().settings())?;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+
+    let never_synced = settings.index_sources.iter().any(|source| {
+        !source
+            .local_dir()
+            .is_ok_and(|dir| dir.join("mods").is_dir())
+    });
+    if never_synced || now.saturating_sub(settings.last_fetched) > INDEX_SYNC_INTERVAL {
+        for (name, result) in index::sync_sources(&settings.index_sources).await {
+            if let Err(e) = result {
+                log::warn!("Failed to sync index source {}: {}", name, e);
+            }
+        }
+        change_setting(&app, &state, "last_fetched", serde_json::json!(now))?;
+    }
+
+    let mut mods = map_error(index::load_sources(&settings.index_sources))?;
+    for entry in &mut mods {
+        if let Some(data_url) = image_data_url(Path::new(&entry.image)) {
+            entry.image = data_url;
         }
-    };
+    }
+    map_error(cache::save_cache(&mods))?;
+    spawn_release_check(app);
+    // Read back so looked-up releases are applied
+    map_error(map_error(CatalogStore::open())?.mods())
+}
 
-    let base64 = STANDARD.encode(image_data);
-    Ok(Some(format!("data:image/jpeg;base64,{}", base64)))
+fn announce_releases(app: &tauri::AppHandle, summary: &ReleaseCheckSummary) {
+    if summary.updated > 0 {
+        if let Err(e) = app.emit("catalog-releases-updated", summary) {
+            log::warn!("Failed to announce new releases: {}", e);
+        }
+    }
+}
+
+// Look up releases for entries that are new or due, without holding up the caller
+fn spawn_release_check(app: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let result = match CatalogStore::open() {
+            Ok(store) => releases::check_releases(&store, false).await,
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(summary) => announce_releases(&app, &summary),
+            Err(e) => log::warn!("Release check failed: {}", e),
+        }
+    });
+}
+
+/// Ask GitHub for the latest release of every mod that opts into automatic
+/// version checks, including ones checked recently when `force` is set.
+#[tauri::command]
+async fn check_mod_releases(
+    app: tauri::AppHandle,
+    force: Option<bool>,
+) -> Result<ReleaseCheckSummary, String> {
+    let store = map_error(CatalogStore::open())?;
+    let summary = map_error(releases::check_releases(&store, force.unwrap_or(false)).await)?;
+    announce_releases(&app, &summary);
+    Ok(summary)
+}
+
+/// GitHub's request quota as of the last API call, if one was made yet.
+#[tauri::command]
+async fn get_github_rate_limit() -> Result<Option<RateLimit>, String> {
+    Ok(github_api::shared().rate_limit())
+}
+
+/// Sync every configured index source now, reporting each one separately.
+#[tauri::command]
+async fn sync_index_sources(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<IndexSourceSync>, String> {
+    let settings = map_error(state.manager.db().settings())?;
+    let results = index::sync_sources(&settings.index_sources).await;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    change_setting(&app, &state, "last_fetched", serde_json::json!(now))?;
+
+    Ok(results
+        .into_iter()
+        .map(|(name, result)| match result {
+            Ok(summary) => IndexSourceSync {
+                name,
+                summary: Some(summary),
+                error: None,
+            },
+            Err(e) => IndexSourceSync {
+                name,
+                summary: None,
+                error: Some(e.to_string()),
+            },
+        })
+        .collect())
 }
 
 // #[allow(non_snake_case)]
@@ -167,15 +762,14 @@ async fn get_mod_thumbnail(modPath: String) -> Result<Option<String>, String> {
 // }
 
 #[tauri::command]
-async fn pull_repo(path: &str) -> Result<(), String> {
+async fn pull_repo(path: &str) -> Result<RepoSyncSummary, String> {
     let path_buf = PathBuf::from(path);
     if !path_buf.exists() {
         return Err(format!("Directory '{}' does not exist", path));
     }
 
     if !github_repo::is_repository_directory(path) {
-        let repo_url = "https://github.com/skyline69/balatro-mod-index";
-        return github_repo::clone_repository(repo_url, path).await;
+        return github_repo::clone_repository(index::INDEX_REPO_URL, path).await;
     }
 
     github_repo::pull_repository(path).await
@@ -215,22 +809,7 @@ async fn list_directories(path: &str) -> Result<Vec<String>, String> {
 
 #[tauri::command]
 async fn read_json_file(path: &str) -> Result<ModMeta, String> {
-    let path = PathBuf::from(path);
-    let file = File::open(&path).map_err(|e| {
-        AppError::FileRead {
-            path: path.clone(),
-            source: e.to_string(),
-        }
-        .to_string()
-    })?;
-
-    serde_json::from_reader(file).map_err(|e| {
-        AppError::JsonParse {
-            path,
-            source: e.to_string(),
-        }
-        .to_string()
-    })
+    map_error(index::read_mod_meta(Path::new(path)))
 }
 
 #[tauri::command]
@@ -247,20 +826,19 @@ async fn read_text_file(path: &str) -> Result<String, String> {
 
 #[tauri::command]
 async fn get_last_fetched(state: tauri::State<'_, AppState>) -> Result<u64, String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    db.get_last_fetched().map_err(|e| e.to_string())
+    Ok(map_error(state.manager.db().settings())?.last_fetched)
 }
 
 #[tauri::command]
-async fn update_last_fetched(state: tauri::State<'_, AppState>) -> Result<(), String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    db.set_last_fetched(
-        SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs(),
-    )
-    .map_err(|e| e.to_string())
+async fn update_last_fetched(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<(), String> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    change_setting(&app, &state, "last_fetched", serde_json::json!(now)).map(|_| ())
 }
 
 #[tauri::command]
@@ -308,906 +886,195 @@ async fn load_mods_cache() -> Result<Option<(Vec<Mod>, u64)>, String> {
 }
 
 #[tauri::command]
-async fn get_lovely_console_status(state: tauri::State<'_, AppState>) -> Result<bool, String> {
-    let db = state
-        .db
-        .lock()
-        .map_err(|_| AppError::LockPoisoned("Database lock poisoned".to_string()))?;
-    map_error(db.is_lovely_console_enabled())
-}
-
-#[tauri::command]
-async fn set_lovely_console_status(
+async fn search_mods(
     state: tauri::State<'_, AppState>,
-    enabled: bool,
-) -> Result<(), String> {
-    let db = state
-        .db
-        .lock()
-        .map_err(|_| AppError::LockPoisoned("Database lock poisoned".to_string()))?;
-    map_error(db.set_lovely_console_status(enabled))
+    query: SearchQuery,
+) -> Result<SearchResults, String> {
+    let store = map_error(CatalogStore::open())?;
+    let search_index = map_error(SearchIndex::load(&store, state.manager.db()))?;
+    Ok(search_index.search(&query))
 }
 
+/// One catalog entry with its changelog, release date, size and the other
+/// optional details.
 #[tauri::command]
-async fn check_untracked_mods() -> Result<bool, String> {
-    // Always return false since we're not removing untracked files
-    Ok(false)
+async fn get_catalog_mod(title: String) -> Result<Option<Mod>, String> {
+    map_error(map_error(CatalogStore::open())?.get(&title))
 }
 
+/// A mod's screenshots, ready to use as image sources: links are passed
+/// through, images shipped with the index entry become data URLs.
 #[tauri::command]
-async fn get_mods_folder() -> Result<String, String> {
-    let config_dir = dirs::config_dir()
-        .ok_or_else(|| AppError::DirNotFound(PathBuf::from("config directory")).to_string())?;
-    Ok(config_dir
-        .join("Balatro")
-        .join("Mods")
-        .to_string_lossy()
-        .into_owned())
+async fn get_mod_screenshots(title: String) -> Result<Vec<String>, String> {
+    let Some(entry) = map_error(map_error(CatalogStore::open())?.get(&title))? else {
+        return Ok(Vec::new());
+    };
+    Ok(entry
+        .details
+        .screenshots
+        .into_iter()
+        .filter_map(|shot| {
+            if shot.starts_with("https://") || shot.starts_with("http://") {
+                Some(shot)
+            } else {
+                image_data_url(Path::new(&shot))
+            }
+        })
+        .collect())
 }
 
 #[tauri::command]
-async fn is_mod_enabled(
-    state: tauri::State<'_, AppState>,
-    mod_name: String,
-) -> Result<bool, String> {
-    let db = state
-        .db
-        .lock()
-        .map_err(|_| AppError::LockPoisoned("Database lock poisoned".to_string()))?;
-    let installed_mods = db.get_installed_mods()?;
-    let mod_dir = &installed_mods
-        .iter()
-        .find(|m| m.name == mod_name)
-        .ok_or_else(|| format!("Mod not found: {}", mod_name))?
-        .path
-        .clone();
-    let mod_dir: &Path = Path::new(mod_dir);
-
-    if !mod_dir.exists() {
-        return Err(format!("Mod directory not found: {}", mod_name));
-    }
-
-    // Check if .lovelyignore file exists in the mod directory
-    let ignore_file_path = mod_dir.join(".lovelyignore");
-
-    // If the file exists, the mod is disabled
-    Ok(!ignore_file_path.exists())
+async fn get_catalog_status() -> Result<CatalogStatus, String> {
+    map_error(map_error(CatalogStore::open())?.status())
 }
 
 #[tauri::command]
-async fn toggle_mod_enabled(
-    state: tauri::State<'_, AppState>,
-    mod_name: String,
-    enabled: bool,
-) -> Result<(), String> {
-    // Database lock and mod lookup remain the same
-    let db = state
-        .db
-        .lock()
-        .map_err(|_| AppError::LockPoisoned("Database lock poisoned".to_string()))?;
-    let installed_mods = db.get_installed_mods()?;
-    let mod_dir = &installed_mods
-        .iter()
-        .find(|m| m.name == mod_name)
-        .ok_or_else(|| format!("Mod not found: {}", mod_name))?
-        .path
-        .clone();
-    let mod_dir: &Path = Path::new(mod_dir);
-
-    if !mod_dir.exists() {
-        return Err(format!("Mod directory not found: {}", mod_name));
-    }
-
-    // Collect entries first - this prevents the borrow of mod_dir from being split across threads
-    let entries: Vec<_> = fs::read_dir(mod_dir)
-        .map_err(|e| format!("Failed to read mod directory: {}", e))?
-        .collect::<Result<_, _>>()
-        .map_err(|e| format!("Failed to read entry: {}", e))?;
-
-    let ignore_file_path = mod_dir.join(".lovelyignore");
-
-    if enabled {
-        // Process directories in parallel
-        entries
-            .par_iter()
-            .filter(|entry| entry.path().is_dir())
-            .try_for_each(|entry| {
-                let ignore_path = entry.path().join(".lovelyignore");
-                if ignore_path.exists() {
-                    fs::remove_file(&ignore_path).map_err(|e| {
-                        format!(
-                            "Failed to remove .lovelyignore in {}: {}",
-                            entry.path().display(),
-                            e
-                        )
-                    })
-                } else {
-                    Ok(())
-                }
-            })?;
-
-        // Handle the top-level ignore file
-        if ignore_file_path.exists() {
-            fs::remove_file(&ignore_file_path)
-                .map_err(|e| format!("Failed to remove top-level .lovelyignore: {}", e))?;
-        }
-    } else {
-        // Process directories in parallel for disabling
-        entries
-            .par_iter()
-            .filter(|entry| entry.path().is_dir())
-            .try_for_each(|entry| {
-                fs::write(entry.path().join(".lovelyignore"), "").map_err(|e| {
-                    format!(
-                        "Failed to create .lovelyignore in {}: {}",
-                        entry.path().display(),
-                        e
-                    )
-                })
-            })?;
-
-        // Handle the top-level ignore file
-        fs::write(&ignore_file_path, "")
-            .map_err(|e| format!("Failed to create top-level .lovelyignore: {}", e))?;
-    }
-
-    Ok(())
+async fn query_catalog(
+    text: Option<String>,
+    category: Option<Category>,
+    author: Option<String>,
+) -> Result<Vec<Mod>, String> {
+    let store = map_error(CatalogStore::open())?;
+    map_error(store.query(&CatalogQuery {
+        text,
+        category,
+        author,
+    }))
 }
 
 #[tauri::command]
-async fn is_mod_enabled_by_path(mod_path: String) -> Result<bool, String> {
-    let path = PathBuf::from(&mod_path);
-
-    // Check if the path exists
-    if !path.exists() {
-        return Err(format!("Mod path does not exist: {}", mod_path));
-    }
-
-    // Check if .lovelyignore file exists in the mod directory
-    let ignore_file_path = path.join(".lovelyignore");
-
-    // If the file exists, the mod is disabled
-    Ok(!ignore_file_path.exists())
+async fn get_lovely_console_status(state: tauri::State<'_, AppState>) -> Result<bool, String> {
+    Ok(map_error(state.manager.db().settings())?.lovely_console)
 }
 
 #[tauri::command]
-async fn toggle_mod_enabled_by_path(mod_path: String, enabled: bool) -> Result<(), String> {
-    let path = PathBuf::from(&mod_path);
-
-    // Check if the mod directory exists
-    if !path.exists() {
-        return Err(format!("Mod path does not exist: {}", mod_path));
-    }
-
-    // Read directory entries to find subdirectories
-    let entries = fs::read_dir(&path)
-        .map_err(|e| format!("Failed to read mod directory: {}", e))?
-        .collect::<Result<Vec<_>, _>>()
-        .map_err(|e| format!("Failed to collect directory entries: {}", e))?;
-
-    // The .lovelyignore file path in this mod's root directory
-    let ignore_file_path = path.join(".lovelyignore");
-
-    if enabled {
-        // Process subdirectories in parallel using Rayon
-        entries
-            .par_iter()
-            .filter(|entry| entry.path().is_dir())
-            .try_for_each(|entry| {
-                // Check for .lovelyignore in each subdirectory
-                let subdir_ignore = entry.path().join(".lovelyignore");
-                if subdir_ignore.exists() {
-                    fs::remove_file(&subdir_ignore).map_err(|e| {
-                        format!(
-                            "Failed to remove .lovelyignore in {}: {}",
-                            entry.path().display(),
-                            e
-                        )
-                    })
-                } else {
-                    Ok(())
-                }
-            })?;
-
-        // If enabling the mod, remove the root .lovelyignore file if it exists
-        if ignore_file_path.exists() {
-            fs::remove_file(&ignore_file_path)
-                .map_err(|e| format!("Failed to remove .lovelyignore file: {}", e))?;
-        }
-    } else {
-        // Process subdirectories in parallel using Rayon
-        entries
-            .par_iter()
-            .filter(|entry| entry.path().is_dir())
-            .try_for_each(|entry| {
-                // Create .lovelyignore in each subdirectory
-                fs::write(entry.path().join(".lovelyignore"), "").map_err(|e| {
-                    format!(
-                        "Failed to create .lovelyignore in {}: {}",
-                        entry.path().display(),
-                        e
-                    )
-                })
-            })?;
-
-        // If disabling the mod, create an empty .lovelyignore file in the root
-        fs::write(&ignore_file_path, "")
-            .map_err(|e| format!("Failed to create .lovelyignore file: {}", e))?;
-    }
-
-    Ok(())
+async fn set_lovely_console_status(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    enabled: bool,
+) -> Result<(), String> {
+    change_setting(&app, &state, "lovely_console", serde_json::json!(enabled)).map(|_| ())
 }
 
 #[tauri::command]
-async fn process_dropped_file(path: String) -> Result<String, String> {
-    // Get the mods directory path
-    let config_dir =
-        dirs::config_dir().ok_or_else(|| "Could not find config directory".to_string())?;
-    let mods_dir = config_dir.join("Balatro").join("Mods");
-
-    // Create the mods directory if it doesn't exist
-    fs::create_dir_all(&mods_dir).map_err(|e| format!("Failed to create mods directory: {}", e))?;
-
-    // Get the filename from the path
-    let file_path = Path::new(&path);
-    let file_name = file_path
-        .file_name()
-        .ok_or_else(|| "Invalid file path".to_string())?
-        .to_str()
-        .ok_or_else(|| "Invalid file name".to_string())?;
-
-    // Determine the name of the mod (without extension)
-    let mod_name = file_name
-        .trim_end_matches(".zip")
-        .trim_end_matches(".tar")
-        .trim_end_matches(".tar.gz")
-        .trim_end_matches(".tgz");
-
-    // Create a target directory for this mod
-    let mod_dir = mods_dir.join(mod_name);
-
-    // If the mod directory already exists, remove it first
-    if mod_dir.exists() {
-        fs::remove_dir_all(&mod_dir)
-            .map_err(|e| format!("Failed to remove existing mod directory: {}", e))?;
-    }
-
-    // Process based on file extension
-    if file_name.ends_with(".zip") {
-        extract_zip(&path, &mod_dir)?;
-    } else if file_name.ends_with(".tar") {
-        extract_tar(&path, &mod_dir)?;
-    } else if file_name.ends_with(".tar.gz") || file_name.ends_with(".tgz") {
-        extract_tar_gz(&path, &mod_dir)?;
-    } else {
-        return Err(
-            "Unsupported file format. Only ZIP, TAR, and TAR.GZ are supported.".to_string(),
-        );
-    }
-
-    // Check for nested directories (common with GitHub downloads)
-    if let Ok(entries) = fs::read_dir(&mod_dir) {
-        let dirs: Vec<_> = entries
-            .filter_map(Result::ok)
-            .filter(|e| e.file_type().map(|t| t.is_dir()).unwrap_or(false))
-            .collect();
-
-        // If there's only one directory and no other files at the top level
-        if dirs.len() == 1 && fs::read_dir(&mod_dir).map(|e| e.count()).unwrap_or(0) == 1 {
-            // This is likely a nested directory structure from GitHub
-            let nested_dir = dirs[0].path();
-
-            // Move all contents from the nested directory up one level
-            for entry in fs::read_dir(&nested_dir)
-                .map_err(|e| format!("Failed to read nested directory: {}", e))?
-            {
-                let entry = entry.map_err(|e| format!("Failed to read entry: {}", e))?;
-                let target_path = mod_dir.join(entry.file_name());
-                if entry
-                    .file_type()
-                    .map_err(|e| format!("Failed to get file type: {}", e))?
-                    .is_dir()
-                {
-                    fs::rename(entry.path(), &target_path)
-                        .map_err(|e| format!("Failed to move directory: {}", e))?;
-                } else {
-                    fs::rename(entry.path(), &target_path)
-                        .map_err(|e| format!("Failed to move file: {}", e))?;
-                }
-            }
-
-            // Remove the now-empty nested directory
-            fs::remove_dir_all(&nested_dir)
-                .map_err(|e| format!("Failed to remove nested directory: {}", e))?;
-        }
-    }
-
-    let has_lua_files = check_for_lua_files(&mod_dir)?;
-
-    if !has_lua_files {
-        // Clean up invalid mod directory
-        fs::remove_dir_all(&mod_dir)
-            .map_err(|e| format!("Failed to remove invalid mod directory: {}", e))?;
-
-        return Err(
-            "No Lua files found in the archive. This doesn't appear to be a valid Balatro mod."
-                .to_string(),
-        );
-    }
-
-    // Return the path to the installed mod
-    Ok(mod_dir.to_string_lossy().to_string())
-}
-
-// Helper function to check for .lua files
-fn check_for_lua_files(dir: &PathBuf) -> Result<bool, String> {
-    // Walk the directory recursively to find any .lua files
-    for entry in WalkDir::new(dir).into_iter().filter_map(|e| e.ok()) {
-        if entry.file_type().is_file() {
-            if let Some(extension) = entry.path().extension() {
-                if extension == "lua" {
-                    return Ok(true);
-                }
-            }
-        }
-    }
+async fn check_untracked_mods() -> Result<bool, String> {
+    // Always return false since we're not removing untracked files
     Ok(false)
-}
-
-/// Process a mod archive from raw binary data (alternative approach if needed)
-#[tauri::command]
-fn process_mod_archive(filename: String, data: Vec<u8>) -> Result<String, String> {
-    // Get the mods directory path
-    let config_dir =
-        dirs::config_dir().ok_or_else(|| "Could not find config directory".to_string())?;
-    let mods_dir = config_dir.join("Balatro").join("Mods");
-
-    // Create the mods directory if it doesn't exist
-    fs::create_dir_all(&mods_dir).map_err(|e| format!("Failed to create mods directory: {}", e))?;
-
-    // Determine the name of the mod (without extension)
-    let mod_name = filename
-        .trim_end_matches(".zip")
-        .trim_end_matches(".tar")
-        .trim_end_matches(".tar.gz")
-        .trim_end_matches(".tgz");
-
-    // Create a target directory for this mod
-    let mod_dir = mods_dir.join(mod_name);
-
-    // If the mod directory already exists, remove it first
-    if mod_dir.exists() {
-        fs::remove_dir_all(&mod_dir)
-            .map_err(|e| format!("Failed to remove existing mod directory: {}", e))?;
-    }
-
-    // Create a cursor for the data
-    let cursor = Cursor::new(data);
-
-    // Process based on file extension
-    if filename.ends_with(".zip") {
-        extract_zip_from_memory(cursor, &mod_dir)?;
-    } else if filename.ends_with(".tar") {
-        extract_tar_from_memory(cursor, &mod_dir)?;
-    } else if filename.ends_with(".tar.gz") || filename.ends_with(".tgz") {
-        extract_tar_gz_from_memory(cursor, &mod_dir)?;
-    } else {
-        return Err(
-            "Unsupported file format. Only ZIP, TAR, and TAR.GZ are supported.".to_string(),
-        );
-    }
-
-    // Check for nested directories (same as in process_dropped_file)
-    if let Ok(entries) = fs::read_dir(&mod_dir) {
-        let dirs: Vec<_> = entries
-            .filter_map(Result::ok)
-            .filter(|e| e.file_type().map(|t| t.is_dir()).unwrap_or(false))
-            .collect();
-
-        if dirs.len() == 1 && fs::read_dir(&mod_dir).map(|e| e.count()).unwrap_or(0) == 1 {
-            let nested_dir = dirs[0].path();
-            for entry in fs::read_dir(&nested_dir)
-                .map_err(|e| format!("Failed to read nested directory: {}", e))?
-            {
-                let entry = entry.map_err(|e| format!("Failed to read entry: {}", e))?;
-                let target_path = mod_dir.join(entry.file_name());
-                if entry
-                    .file_type()
-                    .map_err(|e| format!("Failed to get file type: {}", e))?
-                    .is_dir()
-                {
-                    fs::rename(entry.path(), &target_path)
-                        .map_err(|e| format!("Failed to move directory: {}", e))?;
-                } else {
-                    fs::rename(entry.path(), &target_path)
-                        .map_err(|e| format!("Failed to move file: {}", e))?;
-                }
-            }
-            fs::remove_dir_all(&nested_dir)
-                .map_err(|e| format!("Failed to remove nested directory: {}", e))?;
-        }
-    }
-
-    // Return the path to the installed mod
-    Ok(mod_dir.to_string_lossy().to_string())
-}
-
-// Helper function to extract ZIP files from disk
-fn extract_zip(path: &str, target_dir: &PathBuf) -> Result<(), String> {
-    // Create the directory if it doesn't exist
-    fs::create_dir_all(target_dir)
-        .map_err(|e| format!("Failed to create target directory: {}", e))?;
-
-    // Open the ZIP file
-    let file = fs::File::open(path).map_err(|e| format!("Failed to open ZIP file: {}", e))?;
-
-    // Open the ZIP archive
-    let mut archive =
-        ZipArchive::new(file).map_err(|e| format!("Failed to open ZIP archive: {}", e))?;
-
-    // Extract all files
-    for i in 0..archive.len() {
-        let mut file = archive
-            .by_index(i)
-            .map_err(|e| format!("Failed to access file in archive: {}", e))?;
-
-        let file_path = match file.enclosed_name() {
-            Some(path) => path.to_owned(),
-            None => continue,
-        };
-
-        let output_path = target_dir.join(&file_path);
-
-        if file.is_dir() {
-            fs::create_dir_all(&output_path)
-                .map_err(|e| format!("Failed to create directory: {}", e))?;
-        } else {
-            // Ensure the parent directory exists
-            if let Some(parent) = output_path.parent() {
-                fs::create_dir_all(parent)
-                    .map_err(|e| format!("Failed to create parent directory: {}", e))?;
-            }
-
-            let mut outfile = fs::File::create(&output_path)
-                .map_err(|e| format!("Failed to create file {}: {}", output_path.display(), e))?;
-
-            std::io::copy(&mut file, &mut outfile)
-                .map_err(|e| format!("Failed to write file {}: {}", output_path.display(), e))?;
-        }
-    }
-
-    Ok(())
-}
-
-// Helper function to extract ZIP files from memory
-fn extract_zip_from_memory(cursor: Cursor<Vec<u8>>, target_dir: &PathBuf) -> Result<(), String> {
-    // Create the directory if it doesn't exist
-    fs::create_dir_all(target_dir)
-        .map_err(|e| format!("Failed to create target directory: {}", e))?;
-
-    // Open the ZIP archive
-    let mut archive =
-        ZipArchive::new(cursor).map_err(|e| format!("Failed to open ZIP archive: {}", e))?;
-
-    // Extract all files
-    for i in 0..archive.len() {
-        let mut file = archive
-            .by_index(i)
-            .map_err(|e| format!("Failed to access file in archive: {}", e))?;
-
-        let file_path = match file.enclosed_name() {
-            Some(path) => path.to_owned(),
-            None => continue,
-        };
-
-        let output_path = target_dir.join(&file_path);
-
-        if file.is_dir() {
-            fs::create_dir_all(&output_path)
-                .map_err(|e| format!("Failed to create directory: {}", e))?;
-        } else {
-            // Ensure the parent directory exists
-            if let Some(parent) = output_path.parent() {
-                fs::create_dir_all(parent)
-                    .map_err(|e| format!("Failed to create parent directory: {}", e))?;
-            }
-
-            let mut outfile = fs::File::create(&output_path)
-                .map_err(|e| format!("Failed to create file {}: {}", output_path.display(), e))?;
-
-            std::io::copy(&mut file, &mut outfile)
-                .map_err(|e| format!("Failed to write file {}: {}", output_path.display(), e))?;
-        }
-    }
-
-    Ok(())
-}
-
-// Helper function to extract TAR files from disk
-fn extract_tar(path: &str, target_dir: &PathBuf) -> Result<(), String> {
-    // Create the directory if it doesn't exist
-    fs::create_dir_all(target_dir)
-        .map_err(|e| format!("Failed to create target directory: {}", e))?;
-
-    // Open the TAR file
-    let file = fs::File::open(path).map_err(|e| format!("Failed to open TAR file: {}", e))?;
-
-    // Open the TAR archive
-    let mut archive = Archive::new(file);
-
-    // Extract all files
-    for entry in archive
-        .entries()
-        .map_err(|e| format!("Failed to read TAR entries: {}", e))?
-    {
-        let mut entry = entry.map_err(|e| format!("Failed to read TAR entry: {}", e))?;
-
-        let path = entry
-            .path()
-            .map_err(|e| format!("Failed to get entry path: {}", e))?;
-        let output_path = target_dir.join(path);
-
-        // Ensure the parent directory exists
-        if let Some(parent) = output_path.parent() {
-            fs::create_dir_all(parent)
-                .map_err(|e| format!("Failed to create parent directory: {}", e))?;
-        }
-
-        entry
-            .unpack(&output_path)
-            .map_err(|e| format!("Failed to unpack file {}: {}", output_path.display(), e))?;
-    }
-
-    Ok(())
-}
-
-// Helper function to extract TAR files from memory
-fn extract_tar_from_memory(cursor: Cursor<Vec<u8>>, target_dir: &PathBuf) -> Result<(), String> {
-    // Create the directory if it doesn't exist
-    fs::create_dir_all(target_dir)
-        .map_err(|e| format!("Failed to create target directory: {}", e))?;
-
-    // Open the TAR archive
-    let mut archive = Archive::new(cursor);
-
-    // Extract all files
-    for entry in archive
-        .entries()
-        .map_err(|e| format!("Failed to read TAR entries: {}", e))?
-    {
-        let mut entry = entry.map_err(|e| format!("Failed to read TAR entry: {}", e))?;
-
-        let path = entry
-            .path()
-            .map_err(|e| format!("Failed to get entry path: {}", e))?;
-        let output_path = target_dir.join(path);
-
-        // Ensure the parent directory exists
-        if let Some(parent) = output_path.parent() {
-            fs::create_dir_all(parent)
-                .map_err(|e| format!("Failed to create parent directory: {}", e))?;
-        }
-
-        entry
-            .unpack(&output_path)
-            .map_err(|e| format!("Failed to unpack file {}: {}", output_path.display(), e))?;
-    }
-
-    Ok(())
-}
-
-// Helper function to extract TAR.GZ files from disk
-fn extract_tar_gz(path: &str, target_dir: &PathBuf) -> Result<(), String> {
-    // Create the directory if it doesn't exist
-    fs::create_dir_all(target_dir)
-        .map_err(|e| format!("Failed to create target directory: {}", e))?;
-
-    // Open the TAR.GZ file
-    let file = fs::File::open(path).map_err(|e| format!("Failed to open TAR.GZ file: {}", e))?;
-
-    // Create a GzDecoder to decompress the data
-    let gz = GzDecoder::new(file);
-
-    // Open the TAR archive
-    let mut archive = Archive::new(gz);
-
-    // Extract all files
-    for entry in archive
-        .entries()
-        .map_err(|e| format!("Failed to read TAR entries: {}", e))?
-    {
-        let mut entry = entry.map_err(|e| format!("Failed to read TAR entry: {}", e))?;
-
-        let path = entry
-            .path()
-            .map_err(|e| format!("Failed to get entry path: {}", e))?;
-        let output_path = target_dir.join(path);
-
-        // Ensure the parent directory exists
-        if let Some(parent) = output_path.parent() {
-            fs::create_dir_all(parent)
-                .map_err(|e| format!("Failed to create parent directory: {}", e))?;
-        }
-
-        entry
-            .unpack(&output_path)
-            .map_err(|e| format!("Failed to unpack file {}: {}", output_path.display(), e))?;
-    }
-
-    Ok(())
-}
-
-// Helper function to extract TAR.GZ files from memory
-fn extract_tar_gz_from_memory(cursor: Cursor<Vec<u8>>, target_dir: &PathBuf) -> Result<(), String> {
-    // Create the directory if it doesn't exist
-    fs::create_dir_all(target_dir)
-        .map_err(|e| format!("Failed to create target directory: {}", e))?;
-
-    // Create a GzDecoder to decompress the data
-    let gz = GzDecoder::new(cursor);
-
-    // Open the TAR archive
-    let mut archive = Archive::new(gz);
-
-    // Extract all files
-    for entry in archive
-        .entries()
-        .map_err(|e| format!("Failed to read TAR entries: {}", e))?
-    {
-        let mut entry = entry.map_err(|e| format!("Failed to read TAR entry: {}", e))?;
-
-        let path = entry
-            .path()
-            .map_err(|e| format!("Failed to get entry path: {}", e))?;
-        let output_path = target_dir.join(path);
-
-        // Ensure the parent directory exists
-        if let Some(parent) = output_path.parent() {
-            fs::create_dir_all(parent)
-                .map_err(|e| format!("Failed to create parent directory: {}", e))?;
-        }
+}
 
-        entry
-            .unpack(&output_path)
-            .map_err(|e| format!("Failed to unpack file {}: {}", output_path.display(), e))?;
-    }
+#[tauri::command]
+async fn get_mods_folder(state: tauri::State<'_, AppState>) -> Result<String, String> {
+    // The folder mods are actually installed into
+    Ok(state.manager.mods_dir().to_string_lossy().into_owned())
+}
 
-    Ok(())
+#[tauri::command]
+async fn is_mod_enabled(
+    state: tauri::State<'_, AppState>,
+    mod_name: String,
+) -> Result<bool, String> {
+    map_error(state.manager.is_mod_enabled(&mod_name))
 }
 
 #[tauri::command]
-async fn refresh_mods_folder(state: tauri::State<'_, AppState>) -> Result<(), String> {
-    let mod_dir = dirs::config_dir()
-        .ok_or_else(|| AppError::DirNotFound(PathBuf::from("config directory")))?
-        .join("Balatro")
-        .join("Mods");
+async fn toggle_mod_enabled(
+    state: tauri::State<'_, AppState>,
+    mod_name: String,
+    enabled: bool,
+) -> Result<(), String> {
+    let _operation = state.operations.lock().await;
+    map_error(state.manager.set_mod_enabled(&mod_name, enabled))
+}
 
-    let db = state
-        .db
-        .lock()
-        .map_err(|_| AppError::LockPoisoned("Database lock poisoned".to_string()))?;
-    let installed_mods = db.get_installed_mods()?;
+#[tauri::command]
+async fn is_mod_enabled_by_path(mod_path: String) -> Result<bool, String> {
+    map_error(installer::is_mod_enabled(Path::new(&mod_path)))
+}
 
-    let entries = std::fs::read_dir(&mod_dir).map_err(|e| AppError::FileRead {
-        path: mod_dir.clone(),
-        source: e.to_string(),
-    })?;
+#[tauri::command]
+async fn toggle_mod_enabled_by_path(
+    state: tauri::State<'_, AppState>,
+    mod_path: String,
+    enabled: bool,
+) -> Result<(), String> {
+    let _operation = state.operations.lock().await;
+    map_error(installer::set_mod_enabled(Path::new(&mod_path), enabled))
+}
 
-    for entry in entries {
-        let entry = entry.map_err(|e| AppError::FileRead {
-            path: mod_dir.clone(),
-            source: e.to_string(),
-        })?;
-        let path = entry.path();
-        let name = path
-            .file_name()
-            .and_then(|n| n.to_str())
-            .ok_or_else(|| AppError::InvalidState("Invalid filename".to_string()))?;
-
-        if name.contains(".lovely") || name.contains("lovely") {
-            continue;
-        }
+#[tauri::command]
+async fn process_dropped_file(
+    state: tauri::State<'_, AppState>,
+    path: String,
+) -> Result<String, String> {
+    let _operation = state.operations.lock().await;
+    let manager = &state.manager;
+    let mod_dir = map_error(manager.install_archive_file(Path::new(&path)))?;
+    Ok(mod_dir.to_string_lossy().to_string())
+}
 
-        let ft = entry.file_type().map_err(|e| AppError::FileRead {
-            path: path.clone(),
-            source: e.to_string(),
-        })?;
+/// Process a mod archive from raw binary data (alternative approach if needed)
+#[tauri::command]
+async fn process_mod_archive(
+    state: tauri::State<'_, AppState>,
+    filename: String,
+    data: Vec<u8>,
+) -> Result<String, String> {
+    let _operation = state.operations.lock().await;
+    let manager = &state.manager;
+    let mod_dir = map_error(manager.install_archive_bytes(&filename, data))?;
+    Ok(mod_dir.to_string_lossy().to_string())
+}
 
-        match (ft.is_dir(), ft.is_file()) {
-            (true, _) => {
-                if !installed_mods.iter().any(|m| m.path.contains(name)) {
-                    std::fs::remove_dir_all(&path).map_err(|e| AppError::FileWrite {
-                        path: path.clone(),
-                        source: e.to_string(),
-                    })?;
-                }
-            }
-            (_, true) => {
-                if !installed_mods.iter().any(|m| m.path.contains(name)) {
-                    std::fs::remove_file(&path).map_err(|e| AppError::FileWrite {
-                        path: path.clone(),
-                        source: e.to_string(),
-                    })?;
-                }
-            }
-            _ => continue,
-        }
-    }
-    Ok(())
+#[tauri::command]
+async fn refresh_mods_folder(state: tauri::State<'_, AppState>) -> Result<(), String> {
+    let _operation = state.operations.lock().await;
+    map_error(state.manager.refresh_mods_folder())
 }
 
 #[tauri::command]
 async fn get_discord_rpc_status(state: tauri::State<'_, AppState>) -> Result<bool, String> {
-    let db = state
-        .db
-        .lock()
-        .map_err(|_| AppError::LockPoisoned("Database lock poisoned".to_string()))?;
-    map_error(db.is_discord_rpc_enabled())
+    Ok(map_error(state.manager.db().settings())?.discord_rpc)
 }
 
 #[tauri::command]
 async fn set_discord_rpc_status(
+    app: tauri::AppHandle,
     state: tauri::State<'_, AppState>,
     enabled: bool,
 ) -> Result<(), String> {
-    // Step 1: Update database setting
-    let db = state
-        .db
-        .lock()
-        .map_err(|_| AppError::LockPoisoned("Database lock poisoned".to_string()))?;
-
-    map_error(db.set_discord_rpc_enabled(enabled))?;
-
-    // Step 2: Update Discord RPC manager
-    let discord_rpc = state
-        .discord_rpc
-        .lock()
-        .map_err(|_| AppError::LockPoisoned("Discord RPC lock poisoned".to_string()))?;
-
-    discord_rpc.set_enabled(enabled);
-
-    Ok(())
+    change_setting(&app, &state, "discord_rpc", serde_json::json!(enabled)).map(|_| ())
 }
 
 #[tauri::command]
 async fn launch_balatro(state: tauri::State<'_, AppState>) -> Result<(), String> {
     let (path_str, lovely_console_enabled) = {
-        let db = state
-            .db
-            .lock()
-            .map_err(|_| AppError::LockPoisoned("Database lock poisoned".to_string()))?;
+        let manager = &state.manager;
+        let settings = manager.db().settings()?;
 
         (
-            db.get_installation_path()?
+            settings
+                .installation_path
                 .ok_or_else(|| AppError::InvalidState("No installation path set".to_string()))?,
-            db.is_lovely_console_enabled()?,
+            settings.lovely_console,
         )
     };
 
-    let path = PathBuf::from(path_str);
-
-    #[cfg(target_os = "macos")]
-    {
-        // macOS code remains unchanged
-        let lovely_path = map_error(lovely::ensure_lovely_exists().await)?;
-        let balatro_executable = path.join("Balatro.app/Contents/MacOS/love");
-
-        if lovely_console_enabled {
-            // If the console is disabled, add the flag
-            let disable_arg = if !lovely_console_enabled {
-                " --disable-console"
-            } else {
-                ""
-            };
-            // Instead of using double quotes which cause conflicts in AppleScript,
-            // wrap the file paths in single quotes.
-            let command_line = format!(
-                "cd '{}' && DYLD_INSERT_LIBRARIES='{}' '{}'{}",
-                path.display(),
-                lovely_path.display(),
-                balatro_executable.display(),
-                disable_arg
-            );
-
-            // Construct the AppleScript command to run the command_line in Terminal.
-            let applescript = format!(
-                "tell application \"Terminal\" to do script \"{}\"",
-                command_line
-            );
-
-            Command::new("osascript")
-                .arg("-e")
-                .arg(applescript)
-                .spawn()
-                .map_err(|e| AppError::ProcessExecution(e.to_string()))?;
-        } else {
-            let mut command = Command::new(path.join("Balatro.app/Contents/MacOS/love"));
-            command
-                .env("DYLD_INSERT_LIBRARIES", lovely_path)
-                .current_dir(&path);
-            command
-                .spawn()
-                .map_err(|e| AppError::ProcessExecution(e.to_string()))?;
-        }
-    }
-
-    #[cfg(target_os = "windows")]
-    {
-        // Find the executable file in the directory
-        let exe_path = find_executable_in_directory(&path)
-            .ok_or_else(|| format!("No executable found in {}", path.display()))?;
-
-        let dll_path = path.join("version.dll");
-
-        // If version.dll doesn't exist, download it
-        if !dll_path.exists() {
-            lovely::ensure_version_dll_exists(&path).await?;
-        }
-
-        // Launch the game
-        if lovely_console_enabled {
-            Command::new(&exe_path)
-                .current_dir(&path)
-                .spawn()
-                .map_err(|e| format!("Failed to launch {}: {}", exe_path.display(), e))?;
-        } else {
-            Command::new(&exe_path)
-                .current_dir(&path)
-                .arg("--disable-console")
-                .spawn()
-                .map_err(|e| format!("Failed to launch {}: {}", exe_path.display(), e))?;
-        }
-
-        log::debug!("Launched game from {}", exe_path.display());
-    }
-
-    Ok(())
-}
-
-#[cfg(target_os = "windows")]
-fn find_executable_in_directory(dir: &PathBuf) -> Option<PathBuf> {
-    if let Ok(entries) = std::fs::read_dir(dir) {
-        // Create a Vec to hold all executable files
-        let mut executables: Vec<PathBuf> = Vec::new();
-
-        // First, collect all executable files in the directory
-        for entry in entries.filter_map(Result::ok) {
-            let path = entry.path();
-            if path.is_file() && path.extension() == Some(std::ffi::OsStr::new("exe")) {
-                executables.push(path);
-            }
-        }
-
-        if executables.is_empty() {
-            return None;
-        }
-
-        // First, look for any executable with "balatro" in the name (case-insensitive)
-        for exe in &executables {
-            if let Some(file_name) = exe.file_name().and_then(|n| n.to_str()) {
-                if file_name.to_lowercase().contains("balatro") {
-                    return Some(exe.clone());
-                }
-            }
-        }
-
-        // If no Balatro-specific executable was found, return the first executable
-        return Some(executables[0].clone());
-    }
-
-    None
+    map_error(launcher::launch_balatro(&PathBuf::from(path_str), lovely_console_enabled).await)
 }
 
 #[tauri::command]
-async fn check_mod_installation(mod_type: String) -> Result<bool, String> {
-    let db = map_error(Database::new())?;
+async fn check_mod_installation(
+    state: tauri::State<'_, AppState>,
+    mod_type: String,
+) -> Result<bool, String> {
+    let db = state.manager.db();
     let installed_mods = map_error(db.get_installed_mods())?;
 
     let cached_mods = match cache::load_cache() {
         Ok(Some((mods, _))) => mods,
         _ => Vec::new(), // Empty vector if no cache
     };
-    let detected_mods = local_mod_detection::detect_manual_mods(&db, &cached_mods)?;
+    let detected_mods = local_mod_detection::detect_manual_mods(db, &cached_mods)?;
 
     let mod_name = mod_type.as_str();
     match mod_name {
@@ -1219,18 +1086,16 @@ async fn check_mod_installation(mod_type: String) -> Result<bool, String> {
 
 #[tauri::command]
 async fn check_existing_installation(
+    app: tauri::AppHandle,
     state: tauri::State<'_, AppState>,
 ) -> Result<Option<String>, String> {
-    let db = state
-        .db
-        .lock()
-        .map_err(|_| AppError::LockPoisoned("Database lock poisoned".to_string()))?;
-    if let Some(path) = db.get_installation_path()? {
+    let installation_path = map_error(state.manager.db().settings())?.installation_path;
+    if let Some(path) = installation_path {
         let path_buf = PathBuf::from(&path);
         if bmm_lib::balamod::Balatro::from_custom_path(path_buf).is_some() {
             Ok(Some(path))
         } else {
-            map_error(db.remove_installation_path())?;
+            change_setting(&app, &state, "installation_path", serde_json::Value::Null)?;
             Ok(None)
         }
     } else {
@@ -1240,7 +1105,16 @@ async fn check_existing_installation(
 
 #[allow(non_snake_case)]
 #[tauri::command]
-async fn install_mod(url: String, folderName: String) -> Result<PathBuf, String> {
+async fn install_mod(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    url: String,
+    folderName: String,
+    sha256: Option<String>,
+    operation_id: Option<String>,
+) -> Result<InstalledArchive, String> {
+    let _operation = state.operations.lock().await;
+    let progress = progress_sink(&app, &state, operation_id)?;
     let folderName = {
         if folderName.is_empty() {
             None
@@ -1248,18 +1122,32 @@ async fn install_mod(url: String, folderName: String) -> Result<PathBuf, String>
             Some(folderName)
         }
     };
-    map_error(bmm_lib::installer::install_mod(None, url, folderName).await)
+    // Fall back to the hash the index publishes for this download
+    let expected = sha256.or_else(|| {
+        cache::load_cache().ok().flatten().and_then(|(mods, _)| {
+            mods.into_iter()
+                .find(|m| m.download_url == url)
+                .and_then(|m| m.sha256)
+        })
+    });
+    // Handed back so add_installed_mod can record where the mod came from
+    map_error(
+        bmm_lib::installer::install_mod_with_progress(
+            None,
+            url,
+            folderName,
+            expected.as_deref(),
+            progress.as_ref(),
+        )
+        .await,
+    )
 }
 
 #[tauri::command]
 async fn get_installed_mods_from_db(
     state: tauri::State<'_, AppState>,
 ) -> Result<Vec<InstalledMod>, String> {
-    let db = state
-        .db
-        .lock()
-        .map_err(|_| AppError::LockPoisoned("Database lock poisoned".to_string()))?;
-    map_error(db.get_installed_mods())
+    map_error(state.manager.db().get_installed_mods())
 }
 
 #[tauri::command]
@@ -1267,10 +1155,12 @@ async fn add_installed_mod(
     state: tauri::State<'_, AppState>,
     name: String,
     path: String,
+    archive: Option<InstalledArchive>,
     dependencies: Vec<String>,
     current_version: String,
 ) -> Result<(), String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let _operation = state.operations.lock().await;
+    let manager = &state.manager;
     let current_version = {
         if current_version.is_empty() {
             None
@@ -1278,113 +1168,127 @@ async fn add_installed_mod(
             Some(current_version)
         }
     };
-    map_error(db.add_installed_mod(&name, &path, &dependencies, current_version))
+    map_error(manager.record_install(
+        &name,
+        Path::new(&path),
+        &dependencies,
+        current_version,
+        archive.as_ref(),
+    ))
 }
 
 #[tauri::command]
-async fn force_remove_mod(
+async fn plan_mod_install(
     state: tauri::State<'_, AppState>,
-    name: String,
-    path: String,
-) -> Result<(), String> {
-    map_error(bmm_lib::installer::uninstall_mod(None, PathBuf::from(path)))?;
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    map_error(db.remove_installed_mod(&name))
+    names: Vec<String>,
+) -> Result<InstallPlan, String> {
+    let catalog = match map_error(cache::load_cache())? {
+        Some((mods, _)) => mods,
+        None => return Err("Mod list is not cached yet".to_string()),
+    };
+    let manager = &state.manager;
+    let installed = map_error(resolver::installed_packages(manager.db(), &catalog))?;
+    let names: Vec<&str> = names.iter().map(String::as_str).collect();
+    map_error(resolver::plan_install(&names, &installed, &catalog))
 }
 
-// Update the reindex_mods function to only clean database entries
 #[tauri::command]
-async fn reindex_mods(state: tauri::State<'_, AppState>) -> Result<(usize, usize), String> {
-    let db = state
-        .db
-        .lock()
-        .map_err(|e| AppError::LockPoisoned(format!("Database lock poisoned: {}", e)))?;
-
-    // Database cleanup only - don't touch the filesystem
-    let installed_mods = db.get_installed_mods().map_err(|e| e.to_string())?;
-    let mut to_remove = Vec::new();
-
-    for (index, installed_mod) in installed_mods.iter().enumerate() {
-        if !PathBuf::from(&installed_mod.path).exists() {
-            to_remove.push(index);
-        }
-    }
-
-    let cleaned_entries = to_remove.len();
-    for &index in to_remove.iter().rev() {
-        db.remove_installed_mod(&installed_mods[index].name)
-            .map_err(|e| e.to_string())?;
-    }
-
-    // Return 0 for files_removed since we don't remove any files
-    Ok((0, cleaned_entries))
+async fn verify_mod(
+    state: tauri::State<'_, AppState>,
+    name: String,
+) -> Result<VerifyReport, String> {
+    map_error(state.manager.verify_mod(&name))
 }
 
 #[tauri::command]
-async fn delete_manual_mod(path: String) -> Result<(), String> {
-    let path = PathBuf::from(path);
+async fn repair_mod(
+    state: tauri::State<'_, AppState>,
+    name: String,
+) -> Result<VerifyReport, String> {
+    let _operation = state.operations.lock().await;
+    map_error(state.manager.repair_mod(&name))
+}
 
-    // Verify that this path exists
-    if !path.exists() {
-        return Err(format!(
-            "Invalid path '{}': Path doesn't exist",
-            path.display()
-        ));
-    }
+#[tauri::command]
+async fn set_mod_pinned(
+    state: tauri::State<'_, AppState>,
+    name: String,
+    pinned: bool,
+) -> Result<(), String> {
+    let _operation = state.operations.lock().await;
+    map_error(state.manager.db().set_mod_pinned(&name, pinned))
+}
 
-    let config_dir =
-        dirs::config_dir().ok_or_else(|| "Could not find config directory".to_string())?;
+#[tauri::command]
+async fn get_mod_version_history(
+    state: tauri::State<'_, AppState>,
+    name: String,
+) -> Result<Vec<VersionRecord>, String> {
+    map_error(state.manager.db().get_version_history(&name))
+}
 
-    let mods_dir = config_dir.join("Balatro").join("Mods");
+#[tauri::command]
+async fn rollback_mod(state: tauri::State<'_, AppState>, name: String) -> Result<(), String> {
+    let _operation = state.operations.lock().await;
+    let target = map_error(state.manager.rollback_target(&name))?;
+
+    let (data, _) = map_error(
+        installer::fetch_recorded_archive(
+            target.download_url.as_deref(),
+            target.archive_sha256.as_deref(),
+        )
+        .await,
+    )?;
 
-    // Security check: Make sure the path is within the Mods directory
-    let canonicalized_path = match path.canonicalize() {
-        Ok(p) => p,
-        Err(e) => {
-            return Err(format!(
-                "Failed to canonicalize path {}: {}",
-                path.display(),
-                e
-            ))
-        }
-    };
+    map_error(state.manager.rollback_mod(&name, &target, data))
+}
 
-    let canonicalized_mods_dir = match mods_dir.canonicalize() {
-        Ok(p) => p,
-        Err(e) => return Err(format!("Failed to canonicalize mods directory: {}", e)),
-    };
+#[tauri::command]
+async fn list_cached_archives(
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<StoredArchive>, String> {
+    map_error(state.manager.list_archives())
+}
 
-    if !canonicalized_path.starts_with(&canonicalized_mods_dir) {
-        return Err(format!(
-            "Path is outside of the mods directory: {}",
-            path.display()
-        ));
-    }
+#[tauri::command]
+async fn prune_archive_cache(
+    state: tauri::State<'_, AppState>,
+    max_mb: Option<u64>,
+) -> Result<Vec<StoredArchive>, String> {
+    let _operation = state.operations.lock().await;
+    let max_bytes = max_mb.map_or(archive_store::DEFAULT_MAX_BYTES, |mb| mb * 1024 * 1024);
+    map_error(state.manager.prune_archives(max_bytes))
+}
 
-    // Log what we're about to delete
-    log::info!("Deleting manual mod at path: {}", path.display());
+#[tauri::command]
+async fn force_remove_mod(
+    state: tauri::State<'_, AppState>,
+    name: String,
+    path: String,
+) -> Result<(), String> {
+    let _operation = state.operations.lock().await;
+    map_error(state.manager.force_remove_mod(&name, Path::new(&path)))
+}
 
-    // Delete the directory or file
-    if path.is_dir() {
-        match std::fs::remove_dir_all(&path) {
-            Ok(_) => log::info!("Successfully removed directory: {}", path.display()),
-            Err(e) => return Err(format!("Failed to remove directory: {}", e)),
-        }
-    } else {
-        match std::fs::remove_file(&path) {
-            Ok(_) => log::info!("Successfully removed file: {}", path.display()),
-            Err(e) => return Err(format!("Failed to remove file: {}", e)),
-        }
-    }
+// Only cleans database entries, the filesystem is left alone
+#[tauri::command]
+async fn reindex_mods(state: tauri::State<'_, AppState>) -> Result<(usize, usize), String> {
+    let _operation = state.operations.lock().await;
+    map_error(state.manager.reindex())
+}
 
-    Ok(())
+#[tauri::command]
+async fn delete_manual_mod(state: tauri::State<'_, AppState>, path: String) -> Result<(), String> {
+    let _operation = state.operations.lock().await;
+    map_error(state.manager.delete_manual_mod(Path::new(&path)))
 }
 
 #[tauri::command]
 async fn get_detected_local_mods(
     state: tauri::State<'_, AppState>,
 ) -> Result<Vec<local_mod_detection::DetectedMod>, String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let manager = &state.manager;
+    let db = manager.db();
     let cached_mods = match cache::load_cache() {
         Ok(Some((mods, _))) => mods,
         _ => Vec::new(), // Empty vector if no cache
@@ -1393,17 +1297,11 @@ async fn get_detected_local_mods(
 }
 
 #[tauri::command]
-async fn get_dependents(mod_name: String) -> Result<Vec<String>, String> {
-    let db = Database::new().map_err(|e| e.to_string())?;
-    let all_dependents = db.get_dependents(&mod_name).map_err(|e| e.to_string())?;
-
-    // Filter out self-references
-    let filtered_dependents: Vec<String> = all_dependents
-        .into_iter()
-        .filter(|dep| dep != &mod_name)
-        .collect();
-
-    Ok(filtered_dependents)
+async fn get_dependents(
+    state: tauri::State<'_, AppState>,
+    mod_name: String,
+) -> Result<Vec<String>, String> {
+    map_error(state.manager.get_dependents(&mod_name))
 }
 
 #[tauri::command]
@@ -1411,30 +1309,9 @@ async fn cascade_uninstall(
     state: tauri::State<'_, AppState>,
     root_mod: String,
 ) -> Result<(), String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    let mut to_uninstall = vec![root_mod.clone()];
-    let mut processed = HashSet::new();
-
-    while let Some(current) = to_uninstall.pop() {
-        if processed.contains(&current) {
-            continue;
-        }
-        processed.insert(current.clone());
-
-        // Get mod details
-        let mod_details = map_error(db.get_mod_details(&current))?;
-
-        // Add dependents to queue
-        let dependents = map_error(db.get_dependents(&current))?;
-        to_uninstall.extend(dependents);
-
-        // Perform actual uninstall
-        map_error(bmm_lib::installer::uninstall_mod(None, PathBuf::from(
-            mod_details.path,
-        )))?;
-        map_error(db.remove_installed_mod(&current))?;
-    }
-
+    let _operation = state.operations.lock().await;
+    let manager = &state.manager;
+    map_error(manager.cascade_uninstall(&root_mod))?;
     Ok(())
 }
 
@@ -1444,54 +1321,33 @@ async fn remove_installed_mod(
     name: String,
     path: String,
 ) -> Result<(), String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-
-    // Only check dependencies for framework mods
-    let is_framework = name.to_lowercase() == "steamodded" || name.to_lowercase() == "talisman";
-
-    if is_framework {
-        let all_dependents = map_error(db.get_dependents(&name))?;
-
-        // Filter out self-references
-        let real_dependents: Vec<String> = all_dependents
-            .into_iter()
-            .filter(|dep| dep != &name)
-            .collect();
-
-        if !real_dependents.is_empty() {
-            return Err(format!(
-                "Use cascade_uninstall to remove {} with {} dependents",
-                name,
-                real_dependents.len()
-            ));
-        }
-    }
-
-    map_error(bmm_lib::installer::uninstall_mod(None, PathBuf::from(path)))?;
-    map_error(db.remove_installed_mod(&name))
+    let _operation = state.operations.lock().await;
+    map_error(state.manager.remove_mod(&name, Path::new(&path)))
 }
 
 #[tauri::command]
 async fn get_balatro_path(state: tauri::State<'_, AppState>) -> Result<Option<String>, String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    map_error(db.get_installation_path())
+    Ok(map_error(state.manager.db().settings())?.installation_path)
 }
 
 #[tauri::command]
-async fn set_balatro_path(state: tauri::State<'_, AppState>, path: String) -> Result<(), String> {
-    let db = match state.db.lock() {
-        Ok(db) => db,
-        Err(e) => return Err(e.to_string()),
-    };
-    map_error(db.set_installation_path(&path))
+async fn set_balatro_path(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    path: String,
+) -> Result<(), String> {
+    change_setting(&app, &state, "installation_path", serde_json::json!(path)).map(|_| ())
 }
 
 #[tauri::command]
-async fn find_steam_balatro(state: tauri::State<'_, AppState>) -> Result<Vec<String>, String> {
+async fn find_steam_balatro(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<String>, String> {
     let balatros = find_balatros();
     if let Some(path) = balatros.first() {
-        let db = state.db.lock().map_err(|e| e.to_string())?;
-        map_error(db.set_installation_path(&path.path.to_string_lossy()))?;
+        let path = path.path.to_string_lossy();
+        change_setting(&app, &state, "installation_path", serde_json::json!(path))?;
     }
 
     Ok(balatros
@@ -1511,10 +1367,17 @@ async fn get_steamodded_versions() -> Result<Vec<String>, String> {
 }
 
 #[tauri::command]
-async fn install_steamodded_version(version: String) -> Result<String, String> {
+async fn install_steamodded_version(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    version: String,
+    operation_id: Option<String>,
+) -> Result<InstalledArchive, String> {
+    let _operation = state.operations.lock().await;
+    let progress = progress_sink(&app, &state, operation_id)?;
     let installer = ModInstaller::new(None, ModType::Steamodded);
     installer
-        .install_version(&version)
+        .install_version_verified(&version, None, progress.as_ref())
         .await
         .map_err(|e| e.to_string())
 }
@@ -1568,204 +1431,59 @@ async fn get_latest_steamodded_release() -> Result<String, String> {
 }
 
 #[tauri::command]
-async fn install_talisman_version(version: String) -> Result<String, String> {
+async fn install_talisman_version(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    version: String,
+    operation_id: Option<String>,
+) -> Result<InstalledArchive, String> {
+    let _operation = state.operations.lock().await;
+    let progress = progress_sink(&app, &state, operation_id)?;
     let installer = ModInstaller::new(None, ModType::Talisman);
     installer
-        .install_version(&version)
+        .install_version_verified(&version, None, progress.as_ref())
         .await
         .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-async fn backup_local_mod(path: String) -> Result<(), String> {
-    let path = PathBuf::from(path);
-    if !path.exists() {
-        return Err(format!("Path doesn't exist: {}", path.display()));
-    }
-
-    let backup_dir = get_backup_dir()?;
-
-    // Create a unique backup ID
-    let backup_id = format!(
-        "backup_{}",
-        std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .map_err(|e| format!("Failed to get timestamp: {}", e))?
-            .as_millis()
-    );
-
-    let backup_path = backup_dir.join(backup_id);
-
-    // Create the backup directory
-    std::fs::create_dir_all(&backup_path)
-        .map_err(|e| format!("Failed to create backup directory: {}", e))?;
-
-    // Copy the mod to the backup
-    if path.is_dir() {
-        copy_dir_all(&path, &backup_path.join(path.file_name().unwrap()))
-            .map_err(|e| format!("Failed to copy mod to backup: {}", e))?;
-    } else {
-        std::fs::copy(&path, backup_path.join(path.file_name().unwrap()))
-            .map_err(|e| format!("Failed to copy mod file to backup: {}", e))?;
-    }
-
-    // Store the original path in a metadata file for restoration
-    let metadata = json!({
-        "original_path": path.to_string_lossy().to_string(),
-        "backup_time": std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_secs()
-    });
-
-    std::fs::write(
-        backup_path.join("metadata.json"),
-        serde_json::to_string_pretty(&metadata)
-            .map_err(|e| format!("Failed to serialize metadata: {}", e))?,
-    )
-    .map_err(|e| format!("Failed to write metadata: {}", e))?;
-
-    Ok(())
+async fn backup_local_mod(state: tauri::State<'_, AppState>, path: String) -> Result<(), String> {
+    let _operation = state.operations.lock().await;
+    map_error(state.manager.backup_local_mod(Path::new(&path)))
 }
 
 #[tauri::command]
-async fn restore_from_backup(path: String) -> Result<(), String> {
-    let path = PathBuf::from(path);
-    let backup_dir = get_backup_dir()?;
-
-    // Find the latest backup for this path
-    let mut latest_backup = None;
-    let mut latest_time = 0;
-
-    for entry in std::fs::read_dir(&backup_dir)
-        .map_err(|e| format!("Failed to read backup directory: {}", e))?
-    {
-        let entry = entry.map_err(|e| format!("Failed to read backup entry: {}", e))?;
-        let metadata_path = entry.path().join("metadata.json");
-
-        if metadata_path.exists() {
-            let metadata: serde_json::Value = serde_json::from_str(
-                &std::fs::read_to_string(&metadata_path)
-                    .map_err(|e| format!("Failed to read metadata file: {}", e))?,
-            )
-            .map_err(|e| format!("Failed to parse metadata: {}", e))?;
-
-            if let Some(original_path) = metadata.get("original_path").and_then(|v| v.as_str()) {
-                if original_path == path.to_string_lossy() {
-                    if let Some(backup_time) = metadata.get("backup_time").and_then(|v| v.as_u64())
-                    {
-                        if backup_time > latest_time {
-                            latest_time = backup_time;
-                            latest_backup = Some(entry.path());
-                        }
-                    }
-                }
-            }
-        }
-    }
-
-    let backup_path = latest_backup.ok_or_else(|| "No backup found for this path".to_string())?;
-
-    // Ensure the parent directory exists
-    if let Some(parent) = path.parent() {
-        std::fs::create_dir_all(parent)
-            .map_err(|e| format!("Failed to create parent directory: {}", e))?;
-    }
-
-    // Restore the mod from backup
-    for entry in std::fs::read_dir(&backup_path)
-        .map_err(|e| format!("Failed to read backup directory: {}", e))?
-    {
-        let entry = entry.map_err(|e| format!("Failed to read backup entry: {}", e))?;
-        let file_name = entry.file_name();
-
-        // Skip metadata file
-        if file_name == "metadata.json" {
-            continue;
-        }
-
-        let dest_path = path.parent().unwrap().join(&file_name);
-
-        if entry.path().is_dir() {
-            copy_dir_all(&entry.path(), &dest_path)
-                .map_err(|e| format!("Failed to restore directory from backup: {}", e))?;
-        } else {
-            std::fs::copy(entry.path(), &dest_path)
-                .map_err(|e| format!("Failed to restore file from backup: {}", e))?;
-        }
-    }
-
-    Ok(())
+async fn restore_from_backup(
+    state: tauri::State<'_, AppState>,
+    path: String,
+) -> Result<(), String> {
+    let _operation = state.operations.lock().await;
+    map_error(state.manager.restore_from_backup(Path::new(&path)))
 }
 
 #[tauri::command]
-async fn remove_backup(path: String) -> Result<(), String> {
-    let path = PathBuf::from(path);
-    let backup_dir = get_backup_dir()?;
-
-    // Find all backups for this path
-    for entry in std::fs::read_dir(&backup_dir)
-        .map_err(|e| format!("Failed to read backup directory: {}", e))?
-    {
-        let entry = entry.map_err(|e| format!("Failed to read backup entry: {}", e))?;
-        let metadata_path = entry.path().join("metadata.json");
-
-        if metadata_path.exists() {
-            let metadata: serde_json::Value = serde_json::from_str(
-                &std::fs::read_to_string(&metadata_path)
-                    .map_err(|e| format!("Failed to read metadata file: {}", e))?,
-            )
-            .map_err(|e| format!("Failed to parse metadata: {}", e))?;
-
-            if let Some(original_path) = metadata.get("original_path").and_then(|v| v.as_str()) {
-                if original_path == path.to_string_lossy() {
-                    // Remove this backup
-                    std::fs::remove_dir_all(entry.path())
-                        .map_err(|e| format!("Failed to remove backup: {}", e))?;
-                }
-            }
-        }
-    }
-
-    Ok(())
-}
-
-fn get_backup_dir() -> Result<PathBuf, String> {
-    let temp_dir = std::env::temp_dir().join("balatro_mod_manager_backups");
-    std::fs::create_dir_all(&temp_dir)
-        .map_err(|e| format!("Failed to create backup directory: {}", e))?;
-    Ok(temp_dir)
-}
-
-fn copy_dir_all(src: &Path, dst: &Path) -> std::io::Result<()> {
-    std::fs::create_dir_all(dst)?;
-    for entry in std::fs::read_dir(src)? {
-        let entry = entry?;
-        let ty = entry.file_type()?;
-        let path = entry.path();
-
-        if ty.is_dir() {
-            copy_dir_all(&path, &dst.join(path.file_name().unwrap()))?;
-        } else {
-            std::fs::copy(&path, dst.join(path.file_name().unwrap()))?;
-        }
-    }
-    Ok(())
+async fn remove_backup(state: tauri::State<'_, AppState>, path: String) -> Result<(), String> {
+    map_error(state.manager.remove_backup(Path::new(&path)))
 }
 
 #[tauri::command]
 async fn get_background_state(state: tauri::State<'_, AppState>) -> Result<bool, String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    map_error(db.get_background_enabled())
+    Ok(map_error(state.manager.db().settings())?.background_enabled)
 }
 
 #[tauri::command]
 async fn set_background_state(
+    app: tauri::AppHandle,
     state: tauri::State<'_, AppState>,
     enabled: bool,
 ) -> Result<(), String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    map_error(db.set_background_enabled(enabled))
+    change_setting(
+        &app,
+        &state,
+        "background_enabled",
+        serde_json::json!(enabled),
+    )
+    .map(|_| ())
 }
 
 #[tauri::command]
@@ -1787,6 +1505,7 @@ async fn path_exists(path: String) -> Result<bool, String> {
 
 #[tauri::command]
 async fn check_custom_balatro(
+    app: tauri::AppHandle,
     state: tauri::State<'_, AppState>,
     path: String,
 ) -> Result<bool, String> {
@@ -1804,8 +1523,8 @@ async fn check_custom_balatro(
     let is_valid = bmm_lib::balamod::Balatro::from_custom_path(path_to_check.clone()).is_some();
 
     if is_valid {
-        let db = state.db.lock().map_err(|e| e.to_string())?;
-        map_error(db.set_installation_path(&path_to_check.to_string_lossy()))?;
+        let path = path_to_check.to_string_lossy();
+        change_setting(&app, &state, "installation_path", serde_json::json!(path))?;
     }
 
     Ok(is_valid)
@@ -1815,17 +1534,22 @@ async fn check_custom_balatro(
 async fn is_security_warning_acknowledged(
     state: tauri::State<'_, AppState>,
 ) -> Result<bool, String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    map_error(db.is_security_warning_acknowledged())
+    Ok(map_error(state.manager.db().settings())?.security_warning_acknowledged)
 }
 
 #[tauri::command]
 async fn set_security_warning_acknowledged(
+    app: tauri::AppHandle,
     state: tauri::State<'_, AppState>,
     acknowledged: bool,
 ) -> Result<(), String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    map_error(db.set_security_warning_acknowledged(acknowledged))
+    change_setting(
+        &app,
+        &state,
+        "security_warning_acknowledged",
+        serde_json::json!(acknowledged),
+    )
+    .map(|_| ())
 }
 
 #[tauri::command]
@@ -1861,12 +1585,23 @@ pub fn run() {
             let discord_rpc = DiscordRpcManager::new();
 
             // Get initial Discord RPC setting from database
-            let discord_rpc_enabled = db.is_discord_rpc_enabled().unwrap_or(true);
+            let discord_rpc_enabled = db.settings().map_or(true, |s| s.discord_rpc);
             discord_rpc.set_enabled(discord_rpc_enabled);
 
+            if let Ok(settings) = db.settings() {
+                github_api::shared().set_token(settings.github_token);
+                if let Err(e) = download::shared().set_proxy(settings.download_proxy) {
+                    log::warn!("Ignoring download proxy: {}", e);
+                }
+            }
+
+            let manager = ModManager::new(db, get_lovely_mods_dir(None));
+
             app.manage(AppState {
-                db: Mutex::new(db),
+                manager,
+                operations: tauri::async_runtime::Mutex::new(()),
                 discord_rpc: Mutex::new(discord_rpc),
+                cancel_flags: Mutex::new(HashMap::new()),
             });
 
             let app_dir = app
@@ -1879,6 +1614,11 @@ pub fn run() {
                 source: e.to_string(),
             })?;
 
+            let handle = app.handle().clone();
+            tauri::async_runtime::spawn(releases::run_release_checker(move |summary| {
+                announce_releases(&handle, summary)
+            }));
+
             #[cfg(debug_assertions)]
             if let Some(window) = app.get_webview_window("main") {
                 window.open_devtools();
@@ -1898,6 +1638,34 @@ pub fn run() {
             get_installed_mods_from_db,
             install_mod,
             add_installed_mod,
+            plan_mod_install,
+            get_settings,
+            get_setting,
+            set_setting,
+            list_profiles,
+            get_profile,
+            get_active_profile,
+            save_profile,
+            snapshot_profile,
+            delete_profile,
+            activate_profile,
+            export_lockfile,
+            sync_lockfile,
+            list_collections,
+            get_collection,
+            save_collection,
+            create_collection,
+            delete_collection,
+            export_collection,
+            import_collection,
+            install_collection,
+            verify_mod,
+            repair_mod,
+            set_mod_pinned,
+            get_mod_version_history,
+            rollback_mod,
+            list_cached_archives,
+            prune_archive_cache,
             remove_installed_mod,
             get_steamodded_versions,
             install_steamodded_version,
@@ -1909,6 +1677,14 @@ pub fn run() {
             refresh_mods_folder,
             save_mods_cache,
             load_mods_cache,
+            get_catalog_status,
+            query_catalog,
+            get_catalog_mod,
+            get_mod_screenshots,
+            check_mod_releases,
+            get_github_rate_limit,
+            cancel_operation,
+            search_mods,
             save_versions_cache,
             load_versions_cache,
             set_lovely_console_status,
@@ -1923,6 +1699,8 @@ pub fn run() {
             set_background_state,
             get_last_fetched,
             update_last_fetched,
+            refresh_catalog,
+            sync_index_sources,
             get_repo_path,
             clone_repo,
             pull_repo,
@@ -1934,6 +1712,8 @@ pub fn run() {
             set_discord_rpc_status,
             get_latest_steamodded_release,
             mod_update_available,
+            get_mod_update_kind,
+            update_all_mods,
             get_detected_local_mods,
             delete_manual_mod,
             backup_local_mod,
@@ -17,9 +17,34 @@ export interface Mod {
 	downloadURL: string;
 	folderName: string;
 	version: string;
+	sha256?: string;
+	dependencies?: string[];
+	conflicts?: string[];
+	// Name of the index source the mod was listed in
+	source?: string;
+	details?: ModDetails;
 	installed: boolean;
 }
 
+// Optional extras from the index entry; older entries have none of them
+export interface ModDetails {
+	changelog?: string;
+	// Unix seconds
+	last_updated?: number;
+	// Bytes
+	archive_size?: number;
+	min_steamodded_version?: string;
+	game_versions: string[];
+	screenshots: string[];
+}
+
+// Payload of "operation-progress", sent for installs given an operationId
+export type OperationProgress = { operationId: string } & (
+	| { phase: "resolving" | "finalising" }
+	| { phase: "downloading"; received: number; total: number | null }
+	| { phase: "extracting"; done: number; total: number }
+);
+
 export interface LocalMod {
 	name: string;
 	id: string;
@@ -102,9 +127,39 @@ export interface DependencyCheck {
 export interface InstalledMod {
 	name: string;
 	path: string;
+	pinned?: boolean;
 	// collection_hash: string | null;
 }
 
+// What plan_mod_install says installing a mod takes, dependencies first
+export interface InstallPlan {
+	install: PlannedInstall[];
+	problems: InstallProblem[];
+}
+
+export interface PlannedInstall {
+	name: string;
+	version: string | null;
+	reason: string;
+}
+
+export type InstallProblem =
+	| { kind: "missing"; dependency: string; required_by: string }
+	| {
+			kind: "unsatisfiable";
+			dependency: string;
+			required_by: string;
+			available: string | null;
+	  }
+	| { kind: "conflict"; package: string; conflicts_with: string; rule: string };
+
+// Where install_mod put a mod, and the archive it came from
+export interface InstalledArchive {
+	path: string;
+	url: string;
+	sha256: string;
+}
+
 interface InstallationStatus {
 	[key: string]: boolean;
 }